@@ -0,0 +1,64 @@
+//! Measures how fast CIDR lists are parsed, run with
+//! `cargo run --release --example parse_bench`
+use ipnetwork::IpNetwork;
+use std::time::Instant;
+
+/// Newline separated list of networks of both families, like a feed
+fn feed(lines: usize) -> Vec<u8> {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut text = String::new();
+    for line in 0..lines {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let network = match line % 4 {
+            0 => {
+                let first = (seed as u128) << 64 & !(u128::MAX >> 48);
+                IpNetwork::V6(ipnetwork::Ipv6Network::new(first, 48).unwrap())
+            }
+            _ => {
+                let cidr = 16 + (seed >> 40) as u8 % 17;
+                let first = (seed as u32) & !(u32::MAX.checked_shr(u32::from(cidr)).unwrap_or(0));
+                IpNetwork::V4(ipnetwork::Ipv4Network { first, cidr })
+            }
+        };
+        text.push_str(&network.to_string());
+        text.push('\n');
+    }
+    text.into_bytes()
+}
+
+fn measure<F: FnMut(&[u8]) -> bool>(name: &str, feed: &[u8], mut parse: F) {
+    let start = Instant::now();
+    let mut parsed = 0;
+    for _ in 0..10 {
+        parsed += feed
+            .split(|&b| b == b'\n')
+            .filter(|line| parse(line))
+            .count();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let megabytes = (feed.len() * 10) as f64 / 1e6;
+    println!(
+        "{:>12}: {:8.1} MB/s, {} networks",
+        name,
+        megabytes / elapsed,
+        parsed / 10
+    );
+}
+
+fn main() {
+    let feed = feed(1_000_000);
+    measure("parse_ascii", &feed, |line| {
+        IpNetwork::parse_ascii(line).is_ok()
+    });
+    measure("std", &feed, |line| {
+        let line = std::str::from_utf8(line).unwrap_or("");
+        match line.split_once('/') {
+            Some((addr, cidr)) => {
+                addr.parse::<std::net::IpAddr>().is_ok() && cidr.parse::<u8>().is_ok()
+            }
+            None => false,
+        }
+    });
+}
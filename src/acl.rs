@@ -0,0 +1,333 @@
+//! Access lists of ordered allow and deny rules evaluated by first match
+use crate::IpNetwork;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AclAction {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclRule {
+    pub network: IpNetwork,
+    pub action: AclAction,
+    /// Free form data kept with the rule, like a remark or a ticket
+    pub metadata: Option<String>,
+}
+
+/// A rule that never decides, because earlier rules already match every
+/// address it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowedRule {
+    /// Index of the shadowed rule
+    pub rule: usize,
+    /// Indices of the earlier rules covering it
+    pub by: Vec<usize>,
+    /// True when one of the covering rules has a different action, so
+    /// the rule probably does not do what its author intended
+    pub conflicting: bool,
+}
+
+/// Ordered list of rules. The first rule whose network contains an
+/// address decides, addresses matching no rule get the default action.
+/// ```
+/// use ipnetwork::acl::{Acl, AclAction};
+/// use ipnetwork::IpNetwork;
+/// let mut acl = Acl::new(AclAction::Deny);
+/// acl.push(IpNetwork::V4("10.0.1.0/24".parse().unwrap()), AclAction::Deny, None);
+/// acl.push(IpNetwork::V4("10.0.0.0/8".parse().unwrap()), AclAction::Allow, None);
+/// assert_eq!(acl.evaluate(&"10.0.1.1".parse().unwrap()), AclAction::Deny);
+/// assert_eq!(acl.evaluate(&"10.2.0.1".parse().unwrap()), AclAction::Allow);
+/// assert_eq!(acl.evaluate(&"192.0.2.1".parse().unwrap()), AclAction::Deny);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    rules: Vec<AclRule>,
+    default: AclAction,
+}
+
+/// Address range of a network and the family it belongs to
+fn range(network: &IpNetwork) -> (bool, u128, u128) {
+    match network {
+        IpNetwork::V4(n) => (false, u128::from(n.first), u128::from(u32::from(n.last()))),
+        IpNetwork::V6(n) => (true, n.first, u128::from(n.last())),
+    }
+}
+
+/// Adds the parts of `first..=last` not yet covered by the sorted,
+/// disjoint `ranges`, tagged with `rule`. Returns the number of parts
+/// added.
+fn paint(ranges: &mut Vec<(u128, u128, usize)>, first: u128, last: u128, rule: usize) -> usize {
+    let mut gaps = Vec::new();
+    let mut next = Some(first);
+    let start = ranges.partition_point(|(_, end, _)| *end < first);
+    for (begin, end, _) in &ranges[start..] {
+        let from = match next {
+            Some(from) if from <= last => from,
+            _ => break,
+        };
+        if *begin > last {
+            break;
+        }
+        if *begin > from {
+            gaps.push((from, begin - 1, rule));
+        }
+        next = end.checked_add(1);
+    }
+    if let Some(from) = next.filter(|from| *from <= last) {
+        gaps.push((from, last, rule));
+    }
+    let added = gaps.len();
+    ranges.extend(gaps);
+    ranges.sort_unstable();
+    added
+}
+
+impl Acl {
+    /// Creates an empty list giving `default` to every address
+    pub fn new(default: AclAction) -> Acl {
+        Acl {
+            rules: Vec::new(),
+            default,
+        }
+    }
+
+    /// Appends a rule, evaluated after all present rules
+    pub fn push(&mut self, network: IpNetwork, action: AclAction, metadata: Option<String>) {
+        self.rules.push(AclRule {
+            network,
+            action,
+            metadata,
+        });
+    }
+
+    pub fn rules(&self) -> &[AclRule] {
+        &self.rules
+    }
+
+    pub fn default_action(&self) -> AclAction {
+        self.default
+    }
+
+    /// Returns the first rule containing the address
+    pub fn matching_rule(&self, addr: &IpAddr) -> Option<&AclRule> {
+        self.rules.iter().find(|rule| rule.network.contains(addr))
+    }
+
+    /// Returns the action for the address
+    pub fn evaluate(&self, addr: &IpAddr) -> AclAction {
+        self.matching_rule(addr)
+            .map_or(self.default, |rule| rule.action)
+    }
+
+    /// Returns the rules that can never match first, together with the
+    /// earlier rules that cover them. A rule may be covered by several
+    /// earlier rules together.
+    /// ```
+    /// use ipnetwork::acl::{Acl, AclAction};
+    /// use ipnetwork::IpNetwork;
+    /// let mut acl = Acl::new(AclAction::Deny);
+    /// acl.push(IpNetwork::V4("10.0.0.0/25".parse().unwrap()), AclAction::Allow, None);
+    /// acl.push(IpNetwork::V4("10.0.0.128/25".parse().unwrap()), AclAction::Allow, None);
+    /// acl.push(IpNetwork::V4("10.0.0.0/24".parse().unwrap()), AclAction::Deny, None);
+    /// let shadowed = acl.shadowed_rules();
+    /// assert_eq!(shadowed[0].rule, 2);
+    /// assert_eq!(shadowed[0].by, vec![0, 1]);
+    /// assert!(shadowed[0].conflicting);
+    /// ```
+    pub fn shadowed_rules(&self) -> Vec<ShadowedRule> {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        let mut shadowed = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let (is_v6, first, last) = range(&rule.network);
+            let ranges = match is_v6 {
+                false => &mut v4,
+                true => &mut v6,
+            };
+            if paint(ranges, first, last, index) > 0 {
+                continue;
+            }
+            let mut by: Vec<usize> = ranges
+                .iter()
+                .filter(|(begin, end, _)| *begin <= last && first <= *end)
+                .map(|(_, _, rule)| *rule)
+                .collect();
+            by.sort_unstable();
+            by.dedup();
+            let conflicting = by.iter().any(|i| self.rules[*i].action != rule.action);
+            shadowed.push(ShadowedRule {
+                rule: index,
+                by,
+                conflicting,
+            });
+        }
+        shadowed
+    }
+
+    /// Compiles the list into sorted disjoint ranges, so evaluating takes a
+    /// binary search instead of a walk over all rules
+    pub fn compile(&self) -> CompiledAcl {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let (is_v6, first, last) = range(&rule.network);
+            match is_v6 {
+                false => paint(&mut v4, first, last, index),
+                true => paint(&mut v6, first, last, index),
+            };
+        }
+        let tag = |ranges: Vec<(u128, u128, usize)>| {
+            ranges
+                .into_iter()
+                .map(|(first, last, rule)| (first, last, self.rules[rule].action, rule))
+                .collect()
+        };
+        CompiledAcl {
+            v4: tag(v4),
+            v6: tag(v6),
+            default: self.default,
+        }
+    }
+}
+
+/// Access list compiled by `Acl::compile`. Gives the same answers as the
+/// list it was compiled from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledAcl {
+    /// Disjoint ranges sorted by their first address, with the action and
+    /// index of the rule deciding them
+    v4: Vec<(u128, u128, AclAction, usize)>,
+    v6: Vec<(u128, u128, AclAction, usize)>,
+    default: AclAction,
+}
+
+impl CompiledAcl {
+    fn lookup(&self, addr: &IpAddr) -> Option<(AclAction, usize)> {
+        let (ranges, addr) = match addr {
+            IpAddr::V4(addr) => (&self.v4, u128::from(u32::from(*addr))),
+            IpAddr::V6(addr) => (&self.v6, u128::from(*addr)),
+        };
+        let index = ranges.partition_point(|(first, _, _, _)| *first <= addr);
+        match index.checked_sub(1).map(|i| ranges[i]) {
+            Some((_, last, action, rule)) if addr <= last => Some((action, rule)),
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the rule deciding for the address
+    pub fn matching_rule(&self, addr: &IpAddr) -> Option<usize> {
+        self.lookup(addr).map(|(_, rule)| rule)
+    }
+
+    /// Returns the action for the address
+    pub fn evaluate(&self, addr: &IpAddr) -> AclAction {
+        self.lookup(addr).map_or(self.default, |(action, _)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(s: &str) -> IpNetwork {
+        match s.contains(':') {
+            true => IpNetwork::V6(s.parse().unwrap()),
+            false => IpNetwork::V4(s.parse().unwrap()),
+        }
+    }
+
+    fn acl(rules: &[(&str, AclAction)]) -> Acl {
+        let mut acl = Acl::new(AclAction::Deny);
+        for (net, action) in rules {
+            acl.push(network(net), *action, Some(net.to_string()));
+        }
+        acl
+    }
+
+    #[test]
+    fn first_match() {
+        let acl = acl(&[
+            ("192.0.2.0/25", AclAction::Deny),
+            ("192.0.2.0/24", AclAction::Allow),
+            ("2001:db8::/32", AclAction::Allow),
+        ]);
+        let rule = acl.matching_rule(&"192.0.2.200".parse().unwrap()).unwrap();
+        assert_eq!(rule.metadata.as_deref(), Some("192.0.2.0/24"));
+        assert_eq!(acl.evaluate(&"192.0.2.1".parse().unwrap()), AclAction::Deny);
+        assert_eq!(
+            acl.evaluate(&"2001:db8::1".parse().unwrap()),
+            AclAction::Allow
+        );
+        assert_eq!(
+            acl.evaluate(&"::ffff:192.0.2.200".parse().unwrap()),
+            AclAction::Deny
+        );
+        assert!(
+            Acl::new(AclAction::Allow).evaluate(&"10.0.0.1".parse().unwrap()) == AclAction::Allow
+        );
+    }
+
+    #[test]
+    fn shadowed() {
+        let acl = acl(&[
+            ("10.0.0.0/8", AclAction::Allow),
+            ("10.1.0.0/16", AclAction::Allow),
+            ("0.0.0.0/1", AclAction::Deny),
+            ("128.0.0.0/1", AclAction::Deny),
+            ("0.0.0.0/0", AclAction::Deny),
+            ("::/0", AclAction::Allow),
+        ]);
+        let shadowed = acl.shadowed_rules();
+        assert_eq!(shadowed.len(), 2);
+        assert_eq!(
+            shadowed[0],
+            ShadowedRule {
+                rule: 1,
+                by: vec![0],
+                conflicting: false
+            }
+        );
+        assert_eq!(shadowed[1].rule, 4);
+        assert_eq!(shadowed[1].by, vec![0, 2, 3]);
+        assert!(shadowed[1].conflicting);
+    }
+
+    #[test]
+    fn compiled_matches_rules() {
+        let acl = acl(&[
+            ("10.0.0.128/25", AclAction::Deny),
+            ("10.0.0.0/24", AclAction::Allow),
+            ("10.0.0.0/23", AclAction::Deny),
+            ("0.0.0.0/0", AclAction::Allow),
+            ("ffff::/16", AclAction::Allow),
+            ("::/0", AclAction::Deny),
+        ]);
+        let compiled = acl.compile();
+        let addrs = [
+            "0.0.0.0",
+            "10.0.0.0",
+            "10.0.0.127",
+            "10.0.0.128",
+            "10.0.1.255",
+            "10.0.2.0",
+            "255.255.255.255",
+            "::",
+            "fffe:ffff:ffff:ffff:ffff:ffff:ffff:ffff",
+            "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff",
+        ];
+        for addr in addrs.iter() {
+            let addr: IpAddr = addr.parse().unwrap();
+            assert_eq!(compiled.evaluate(&addr), acl.evaluate(&addr), "{}", addr);
+            let rule = acl.matching_rule(&addr).map(|rule| rule.metadata.clone());
+            let index = compiled
+                .matching_rule(&addr)
+                .map(|i| acl.rules()[i].metadata.clone());
+            assert_eq!(rule, index);
+        }
+        let empty = Acl::new(AclAction::Allow).compile();
+        assert_eq!(empty.matching_rule(&"10.0.0.1".parse().unwrap()), None);
+        assert_eq!(empty.evaluate(&"::1".parse().unwrap()), AclAction::Allow);
+    }
+}
@@ -0,0 +1,395 @@
+//! Aggregated view of a prefix set that changes over time, kept up to
+//! date per insertion and removal instead of aggregating the whole set
+//! again
+use crate::bits::AddressBits;
+use crate::{IpNetwork, Ipv4Network, Ipv6Network, Network};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Changes of the aggregated view made by one operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateDelta<B: AddressBits> {
+    /// Aggregates that appeared, sorted
+    pub added: Vec<Network<B>>,
+    /// Aggregates that are gone, sorted
+    pub removed: Vec<Network<B>>,
+}
+
+impl<B: AddressBits> AggregateDelta<B> {
+    /// Returns true when the aggregated view did not change
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Prefix set that keeps the same aggregates as `Network::aggregate`
+/// would give for its members. A prefix may be inserted several times,
+/// for example when several peers announce it, and stays a member until
+/// it was removed as often.
+/// ```
+/// use ipnetwork::aggregate::Aggregator;
+/// use ipnetwork::Ipv4Network;
+/// let net = |s: &str| s.parse::<Ipv4Network>().unwrap();
+/// let mut aggregator = Aggregator::new();
+/// aggregator.insert(net("10.0.0.0/24"));
+/// let delta = aggregator.insert(net("10.0.1.0/24"));
+/// assert_eq!(delta.removed, vec![net("10.0.0.0/24")]);
+/// assert_eq!(delta.added, vec![net("10.0.0.0/23")]);
+/// let delta = aggregator.remove(&net("10.0.0.0/24"));
+/// assert_eq!(delta.removed, vec![net("10.0.0.0/23")]);
+/// assert_eq!(delta.added, vec![net("10.0.1.0/24")]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Aggregator<B: AddressBits> {
+    /// Members and how often each was inserted
+    members: BTreeMap<Network<B>, usize>,
+    aggregates: BTreeSet<Network<B>>,
+}
+
+impl<B: AddressBits> Aggregator<B> {
+    pub fn new() -> Aggregator<B> {
+        Aggregator {
+            members: BTreeMap::new(),
+            aggregates: BTreeSet::new(),
+        }
+    }
+
+    /// The current aggregates, sorted
+    pub fn aggregates(&self) -> impl Iterator<Item = &Network<B>> {
+        self.aggregates.iter()
+    }
+
+    /// The inserted prefixes, sorted and without duplicates
+    pub fn members(&self) -> impl Iterator<Item = &Network<B>> {
+        self.members.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Bounds of the networks sorted between the first and last address
+    /// of `network`, both included
+    fn span(network: &Network<B>) -> (Network<B>, Network<B>) {
+        let low = Network {
+            first: network.first,
+            cidr: 0,
+        };
+        let high = Network {
+            first: network.last_bits(),
+            cidr: u8::MAX,
+        };
+        (low, high)
+    }
+
+    /// Aggregate covering the network, if any
+    fn covering(&self, network: &Network<B>) -> Option<Network<B>> {
+        let (_, high) = Aggregator::span(&Network {
+            first: network.first,
+            cidr: B::BITS,
+        });
+        self.aggregates
+            .range(..=high)
+            .next_back()
+            .filter(|aggregate| aggregate.is_subnet(network))
+            .copied()
+    }
+
+    /// Adds the prefix. This takes a logarithmic time per address bit.
+    pub fn insert(&mut self, network: Network<B>) -> AggregateDelta<B> {
+        *self.members.entry(network).or_insert(0) += 1;
+        let mut delta = AggregateDelta {
+            added: Vec::new(),
+            removed: Vec::new(),
+        };
+        if self.covering(&network).is_some() {
+            return delta;
+        }
+        // Aggregates inside the new prefix are swallowed by it
+        let (low, high) = Aggregator::span(&network);
+        delta.removed = self.aggregates.range(low..=high).copied().collect();
+        let mut region = network;
+        // A fully covered sibling is always a single aggregate, anything
+        // larger would already cover the new prefix
+        while region.cidr > 0 {
+            let step = Network::<B>::cidr_to_stepping(region.cidr);
+            let sibling = Network {
+                first: (region.first & !step) | (!region.first & step),
+                cidr: region.cidr,
+            };
+            if !self.aggregates.contains(&sibling) {
+                break;
+            }
+            delta.removed.push(sibling);
+            region = Network {
+                first: region.first & !step,
+                cidr: region.cidr - 1,
+            };
+        }
+        for removed in &delta.removed {
+            self.aggregates.remove(removed);
+        }
+        delta.removed.sort_unstable();
+        self.aggregates.insert(region);
+        delta.added.push(region);
+        delta
+    }
+
+    /// Removes one insertion of the prefix, nothing changes when it is not
+    /// a member. This takes a time proportional to the number of members
+    /// inside the aggregate that covered it.
+    pub fn remove(&mut self, network: &Network<B>) -> AggregateDelta<B> {
+        let mut delta = AggregateDelta {
+            added: Vec::new(),
+            removed: Vec::new(),
+        };
+        match self.members.get_mut(network) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                return delta;
+            }
+            Some(_) => {
+                self.members.remove(network);
+            }
+            None => return delta,
+        }
+        let aggregate = self
+            .covering(network)
+            .expect("members are covered by an aggregate");
+        let (low, high) = Aggregator::span(&aggregate);
+        let inside: Vec<Network<B>> = self.members.range(low..=high).map(|(n, _)| *n).collect();
+        let replacement = Network::aggregate(&inside);
+        if replacement == [aggregate] {
+            return delta;
+        }
+        self.aggregates.remove(&aggregate);
+        self.aggregates.extend(replacement.iter().copied());
+        delta.removed.push(aggregate);
+        delta.added = replacement;
+        delta
+    }
+}
+
+/// Addresses covered by one prefix list only, returned by
+/// `covers_same_space` as the fewest networks
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Difference {
+    pub only_in_a: Vec<IpNetwork>,
+    pub only_in_b: Vec<IpNetwork>,
+}
+
+/// Addresses of the first aggregated list that the second one does not
+/// cover, as the fewest networks
+fn uncovered<B: AddressBits>(a: &[Network<B>], b: &[Network<B>]) -> Vec<Network<B>> {
+    let mut uncovered = Vec::new();
+    for network in a {
+        let start = b.partition_point(|n| n.last_bits() < network.first);
+        let inside = b[start..]
+            .iter()
+            .take_while(|n| n.first <= network.last_bits());
+        // Networks either nest or are apart, so whatever overlaps the
+        // network covers it or lies inside it
+        let mut next = Some(network.first);
+        for covered in inside {
+            if let Some(first) = next.filter(|first| *first < covered.first) {
+                uncovered.extend(Network::summarize_address_range(
+                    first.to_addr(),
+                    (covered.first - B::ONE).to_addr(),
+                ));
+            }
+            next = covered
+                .last_bits()
+                .checked_add(B::ONE)
+                .filter(|next| *next <= network.last_bits());
+        }
+        if let Some(first) = next {
+            uncovered.extend(Network::summarize_address_range(
+                first.to_addr(),
+                network.last(),
+            ));
+        }
+    }
+    uncovered
+}
+
+/// Checks that two prefix lists cover exactly the same addresses, however
+/// they are split or aggregated, and returns the addresses covered by only
+/// one of them otherwise
+/// ```
+/// use ipnetwork::aggregate::covers_same_space;
+/// use ipnetwork::IpNetwork;
+/// let nets = |list: &[&str]| -> Vec<IpNetwork> {
+///     list.iter().map(|n| IpNetwork::parse_ascii(n.as_bytes()).unwrap()).collect()
+/// };
+/// let before = nets(&["10.0.0.0/24", "10.0.1.0/24", "10.0.2.0/24"]);
+/// assert_eq!(covers_same_space(&before, &nets(&["10.0.0.0/23", "10.0.2.0/24"])), Ok(()));
+/// let difference = covers_same_space(&before, &nets(&["10.0.0.0/22"])).unwrap_err();
+/// assert!(difference.only_in_a.is_empty());
+/// assert_eq!(difference.only_in_b, nets(&["10.0.3.0/24"]));
+/// ```
+pub fn covers_same_space(a: &[IpNetwork], b: &[IpNetwork]) -> Result<(), Difference> {
+    let split = |networks: &[IpNetwork]| {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        for network in networks {
+            match network {
+                IpNetwork::V4(n) => v4.push(*n),
+                IpNetwork::V6(n) => v6.push(*n),
+            }
+        }
+        (Ipv4Network::aggregate(&v4), Ipv6Network::aggregate(&v6))
+    };
+    let (a4, a6) = split(a);
+    let (b4, b6) = split(b);
+    let only = |a4: &[Ipv4Network], a6: &[Ipv6Network], b4: &[Ipv4Network], b6: &[Ipv6Network]| {
+        let v4 = uncovered(a4, b4).into_iter().map(IpNetwork::V4);
+        let v6 = uncovered(a6, b6).into_iter().map(IpNetwork::V6);
+        v4.chain(v6).collect::<Vec<IpNetwork>>()
+    };
+    let difference = Difference {
+        only_in_a: only(&a4, &a6, &b4, &b6),
+        only_in_b: only(&b4, &b6, &a4, &a6),
+    };
+    match difference == Difference::default() {
+        true => Ok(()),
+        false => Err(difference),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ipv4Network, Ipv6Network};
+
+    fn v4(s: &str) -> Ipv4Network {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn inserts_and_removals() {
+        let mut aggregator = Aggregator::new();
+        for network in ["10.0.0.0/25", "10.0.1.0/24", "10.0.0.192/26"].iter() {
+            aggregator.insert(v4(network));
+        }
+        let delta = aggregator.insert(v4("10.0.0.128/26"));
+        assert_eq!(
+            delta.removed,
+            vec![v4("10.0.0.0/25"), v4("10.0.0.192/26"), v4("10.0.1.0/24")]
+        );
+        assert_eq!(delta.added, vec![v4("10.0.0.0/23")]);
+        assert!(aggregator.insert(v4("10.0.1.7/32")).is_empty());
+        let delta = aggregator.insert(v4("10.0.0.0/16"));
+        assert_eq!(delta.removed, vec![v4("10.0.0.0/23")]);
+        assert!(aggregator.remove(&v4("10.0.0.0/25")).is_empty());
+        assert!(aggregator.remove(&v4("192.0.2.0/24")).is_empty());
+        let delta = aggregator.remove(&v4("10.0.0.0/16"));
+        assert_eq!(delta.removed, vec![v4("10.0.0.0/16")]);
+        assert_eq!(delta.added, vec![v4("10.0.0.128/25"), v4("10.0.1.0/24")]);
+        assert_eq!(aggregator.len(), 4);
+    }
+
+    #[test]
+    fn duplicates_and_whole_space() {
+        let mut aggregator = Aggregator::new();
+        let all: Ipv6Network = "::/0".parse().unwrap();
+        let half: Ipv6Network = "8000::/1".parse().unwrap();
+        aggregator.insert(half);
+        aggregator.insert(half);
+        let delta = aggregator.insert("::/1".parse().unwrap());
+        assert_eq!(delta.added, vec![all]);
+        assert!(aggregator.insert(all).is_empty());
+        assert!(aggregator.remove(&all).is_empty());
+        assert!(!aggregator.remove(&"::/1".parse().unwrap()).is_empty());
+        assert!(aggregator.remove(&half).is_empty());
+        assert_eq!(aggregator.remove(&half).removed, vec![half]);
+        assert!(aggregator.is_empty());
+        assert_eq!(aggregator.aggregates().count(), 0);
+    }
+
+    #[test]
+    fn matches_full_aggregation() {
+        let mut seed = 0x9e37_79b9u32;
+        let mut aggregator = Aggregator::new();
+        for round in 0..2000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let cidr = 20 + (seed % 5) as u8;
+            let network = Ipv4Network {
+                first: (0x0a00_0000 | (seed >> 8) & 0xfff0) & !Ipv4Network::cidr_to_hostmask(cidr),
+                cidr,
+            };
+            let mut before: Vec<Ipv4Network> = aggregator.aggregates().copied().collect();
+            let delta = match round % 3 {
+                0 => aggregator.remove(&network),
+                _ => aggregator.insert(network),
+            };
+            before.retain(|n| !delta.removed.contains(n));
+            before.extend(delta.added);
+            before.sort_unstable();
+            let members: Vec<Ipv4Network> = aggregator.members().copied().collect();
+            let expected = Ipv4Network::aggregate(&members);
+            assert_eq!(before, expected);
+            assert_eq!(
+                aggregator.aggregates().copied().collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn same_space() {
+        let nets = |list: &[&str]| -> Vec<IpNetwork> {
+            list.iter()
+                .map(|n| IpNetwork::parse_ascii(n.as_bytes()).unwrap())
+                .collect()
+        };
+        let a = nets(&[
+            "10.0.0.0/8",
+            "2001:db8::/32",
+            "192.0.2.0/25",
+            "192.0.2.128/25",
+        ]);
+        let b = nets(&[
+            "2001:db8::/33",
+            "10.0.0.0/9",
+            "10.128.0.0/9",
+            "192.0.2.0/24",
+            "2001:db8:8000::/33",
+        ]);
+        assert_eq!(covers_same_space(&a, &b), Ok(()));
+        assert_eq!(covers_same_space(&[], &[]), Ok(()));
+        let b = nets(&[
+            "10.0.0.0/8",
+            "10.1.2.3/32",
+            "2001:db8::/33",
+            "192.0.2.64/26",
+            "::/0",
+        ]);
+        let difference = covers_same_space(&a, &b).unwrap_err();
+        assert_eq!(
+            difference.only_in_a,
+            nets(&["192.0.2.0/26", "192.0.2.128/25"])
+        );
+        assert_eq!(difference.only_in_b[0], nets(&["::/3"])[0]);
+        let v6: Vec<Ipv6Network> = difference
+            .only_in_b
+            .iter()
+            .filter_map(|n| match n {
+                IpNetwork::V6(n) => Some(*n),
+                IpNetwork::V4(_) => None,
+            })
+            .collect();
+        let mut whole = v6;
+        whole.push("2001:db8::/32".parse().unwrap());
+        assert_eq!(
+            Ipv6Network::aggregate(&whole),
+            vec!["::/0".parse().unwrap()]
+        );
+        let difference = covers_same_space(&nets(&["0.0.0.0/0"]), &nets(&["255.255.255.255/32"]));
+        assert_eq!(difference.unwrap_err().only_in_a.len(), 32);
+    }
+}
@@ -0,0 +1,204 @@
+//! Traffic summaries per prefix computed from streams of addresses
+use crate::hashing::aggregation_key;
+use crate::IpNetwork;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+
+/// Prefix reported by `HeavyHitters` with its approximate count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeavyHitter {
+    pub network: IpNetwork,
+    /// Upper bound of the number of addresses seen in the network
+    pub count: u64,
+    /// Most the count may be too high by, `count - error` addresses were
+    /// seen for sure
+    pub error: u64,
+}
+
+/// Finds the busiest prefixes of a stream of addresses in fixed memory,
+/// using the space saving algorithm (Metwally, Agrawal, El Abbadi).
+///
+/// At most `capacity` prefixes are tracked. Every prefix seen more than
+/// `total / capacity` times is guaranteed to be among them, with a count
+/// that is never too low.
+/// ```
+/// use ipnetwork::analytics::HeavyHitters;
+/// use ipnetwork::IpNetwork;
+/// let mut hitters = HeavyHitters::new(16, 24, 48);
+/// for host in 0..100u8 {
+///     hitters.observe(format!("198.51.100.{}", host).parse().unwrap());
+/// }
+/// hitters.observe("192.0.2.1".parse().unwrap());
+/// let top = hitters.top(1);
+/// assert_eq!(top[0].network, IpNetwork::V4("198.51.100.0/24".parse().unwrap()));
+/// assert_eq!(top[0].count, 100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeavyHitters {
+    capacity: usize,
+    v4_prefix: u8,
+    v6_prefix: u8,
+    /// Count and error of every tracked prefix
+    counters: HashMap<IpNetwork, (u64, u64)>,
+    /// Tracked prefixes ordered by count, to find the smallest one
+    by_count: BTreeSet<(u64, IpNetwork)>,
+}
+
+impl HeavyHitters {
+    /// Creates a tracker of at most `capacity` prefixes, addresses are
+    /// counted per /`v4_prefix` or /`v6_prefix` network. A capacity of 0
+    /// is treated as 1.
+    pub fn new(capacity: usize, v4_prefix: u8, v6_prefix: u8) -> HeavyHitters {
+        HeavyHitters {
+            capacity: capacity.max(1),
+            v4_prefix,
+            v6_prefix,
+            counters: HashMap::new(),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    pub fn observe(&mut self, addr: IpAddr) {
+        self.observe_weighted(addr, 1)
+    }
+
+    /// Counts the address `weight` times, for example its bytes or packets
+    pub fn observe_weighted(&mut self, addr: IpAddr, weight: u64) {
+        let network = aggregation_key(addr, self.v4_prefix, self.v6_prefix);
+        let (count, error) = match self.counters.get(&network) {
+            Some(&(count, error)) => {
+                self.by_count.remove(&(count, network));
+                (count, error)
+            }
+            None if self.counters.len() < self.capacity => (0, 0),
+            None => {
+                // The new prefix takes over the counter of the smallest one
+                let (smallest, evicted) = self.by_count.pop_first().expect("counters are full");
+                self.counters.remove(&evicted);
+                (smallest, smallest)
+            }
+        };
+        let count = count.saturating_add(weight);
+        self.counters.insert(network, (count, error));
+        self.by_count.insert((count, network));
+    }
+
+    /// Returns the `k` prefixes with the highest counts, highest first
+    pub fn top(&self, k: usize) -> Vec<HeavyHitter> {
+        self.by_count
+            .iter()
+            .rev()
+            .take(k)
+            .map(|&(count, network)| HeavyHitter {
+                network,
+                count,
+                error: self.counters[&network].1,
+            })
+            .collect()
+    }
+}
+
+/// Counts the addresses per /`prefix_len` network. The prefix length
+/// applies to both families and is cut to the address width. Buckets are
+/// sorted like `IpNetwork`, IPv4 networks first.
+/// ```
+/// use ipnetwork::analytics::histogram;
+/// use ipnetwork::IpNetwork;
+/// use std::net::IpAddr;
+/// let addrs: Vec<IpAddr> = ["192.0.2.1", "192.0.2.9", "198.51.100.1"]
+///     .iter()
+///     .map(|a| a.parse().unwrap())
+///     .collect();
+/// let counts = histogram(addrs, 24);
+/// assert_eq!(counts[&IpNetwork::V4("192.0.2.0/24".parse().unwrap())], 2);
+/// assert_eq!(counts.len(), 2);
+/// ```
+pub fn histogram<I: IntoIterator<Item = IpAddr>>(
+    addrs: I,
+    prefix_len: u8,
+) -> BTreeMap<IpNetwork, u64> {
+    // Counting in a hash map first keeps the per address cost constant,
+    // the tree is only built once per bucket
+    let mut counts: HashMap<IpNetwork, u64> = HashMap::new();
+    for addr in addrs {
+        *counts
+            .entry(aggregation_key(addr, prefix_len, prefix_len))
+            .or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_heavy_hitters() {
+        let mut hitters = HeavyHitters::new(4, 24, 48);
+        for round in 0..1000u32 {
+            // two busy networks and a long tail of single addresses
+            hitters.observe(format!("10.0.0.{}", round % 200).parse().unwrap());
+            hitters.observe("10.0.1.1".parse().unwrap());
+            let tail = 0x0b00_0000 + (round << 8);
+            hitters.observe(IpAddr::V4(tail.into()));
+        }
+        // arrives last and takes over the counter of a tail network
+        hitters.observe_weighted("2001:db8:1:2::1".parse().unwrap(), 5000);
+        let top = hitters.top(3);
+        assert_eq!(
+            top[0],
+            HeavyHitter {
+                network: IpNetwork::V6("2001:db8:1::/48".parse().unwrap()),
+                count: 5500,
+                error: 500,
+            }
+        );
+        let busy: Vec<IpNetwork> = top[1..].iter().map(|h| h.network).collect();
+        assert!(busy.contains(&IpNetwork::V4("10.0.0.0/24".parse().unwrap())));
+        assert!(busy.contains(&IpNetwork::V4("10.0.1.0/24".parse().unwrap())));
+        for hitter in &top[1..] {
+            assert!(hitter.count >= 1000 && hitter.count - hitter.error <= 1000);
+        }
+        assert_eq!(hitters.top(10).len(), 4);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut hitters = HeavyHitters::new(0, 32, 128);
+        hitters.observe("192.0.2.1".parse().unwrap());
+        hitters.observe("192.0.2.2".parse().unwrap());
+        let top = hitters.top(5);
+        assert_eq!(top.len(), 1);
+        assert_eq!((top[0].count, top[0].error), (2, 1));
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let addrs: Vec<IpAddr> = [
+            "2001:db8::1",
+            "10.0.0.1",
+            "2001:db8:0:1::1",
+            "10.0.1.1",
+            "10.1.0.1",
+        ]
+        .iter()
+        .map(|a| a.parse().unwrap())
+        .collect();
+        let counts: Vec<(String, u64)> = histogram(addrs.iter().copied(), 48)
+            .into_iter()
+            .map(|(network, count)| (network.to_string(), count))
+            .collect();
+        let expected = [
+            ("10.0.0.1/32", 1),
+            ("10.0.1.1/32", 1),
+            ("10.1.0.1/32", 1),
+            ("2001:db8::/48", 2),
+        ];
+        let expected: Vec<(String, u64)> =
+            expected.iter().map(|(n, c)| (n.to_string(), *c)).collect();
+        assert_eq!(counts, expected);
+        let counts = histogram(addrs, 0);
+        assert_eq!(counts.values().copied().collect::<Vec<_>>(), vec![3, 2]);
+        assert!(histogram(Vec::new(), 24).is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! Integer representation of addresses shared by the IPv4 and IPv6 types.
+//!
+//! `Network`, `Subnets` and `Hosts` are written once over `AddressBits` and
+//! used as `Network<u32>` for IPv4 and `Network<u128>` for IPv6.
+use std::fmt;
+use std::hash::Hash;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::{Add, BitAnd, BitOr, Not, Shl, Sub};
+
+/// Unsigned integer holding an address of one family
+pub trait AddressBits:
+    Copy
+    + Ord
+    + Hash
+    + fmt::Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + Not<Output = Self>
+    + Shl<u8, Output = Self>
+{
+    /// Address type of the family
+    type Addr: Copy + Ord + Hash + fmt::Debug + fmt::Display;
+
+    /// Width of an address in bits
+    const BITS: u8;
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn to_addr(self) -> Self::Addr;
+    fn from_addr(addr: Self::Addr) -> Self;
+    fn to_u128(self) -> u128;
+    /// Returns None when the value does not fit
+    fn from_u128(value: u128) -> Option<Self>;
+    fn checked_shl(self, shift: u32) -> Option<Self>;
+    fn checked_shr(self, shift: u32) -> Option<Self>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn count_ones(self) -> u32;
+}
+
+impl AddressBits for u32 {
+    type Addr = Ipv4Addr;
+
+    const BITS: u8 = 32;
+    const ZERO: u32 = 0;
+    const ONE: u32 = 1;
+    const MAX: u32 = u32::MAX;
+
+    fn to_addr(self) -> Ipv4Addr {
+        Ipv4Addr::from(self)
+    }
+    fn from_addr(addr: Ipv4Addr) -> u32 {
+        u32::from(addr)
+    }
+    fn to_u128(self) -> u128 {
+        u128::from(self)
+    }
+    fn from_u128(value: u128) -> Option<u32> {
+        match value <= u128::from(u32::MAX) {
+            true => Some(value as u32),
+            false => None,
+        }
+    }
+    fn checked_shl(self, shift: u32) -> Option<u32> {
+        u32::checked_shl(self, shift)
+    }
+    fn checked_shr(self, shift: u32) -> Option<u32> {
+        u32::checked_shr(self, shift)
+    }
+    fn checked_add(self, other: u32) -> Option<u32> {
+        u32::checked_add(self, other)
+    }
+    fn checked_sub(self, other: u32) -> Option<u32> {
+        u32::checked_sub(self, other)
+    }
+    fn count_ones(self) -> u32 {
+        u32::count_ones(self)
+    }
+}
+
+impl AddressBits for u128 {
+    type Addr = Ipv6Addr;
+
+    const BITS: u8 = 128;
+    const ZERO: u128 = 0;
+    const ONE: u128 = 1;
+    const MAX: u128 = u128::MAX;
+
+    fn to_addr(self) -> Ipv6Addr {
+        Ipv6Addr::from(self)
+    }
+    fn from_addr(addr: Ipv6Addr) -> u128 {
+        u128::from(addr)
+    }
+    fn to_u128(self) -> u128 {
+        self
+    }
+    fn from_u128(value: u128) -> Option<u128> {
+        Some(value)
+    }
+    fn checked_shl(self, shift: u32) -> Option<u128> {
+        u128::checked_shl(self, shift)
+    }
+    fn checked_shr(self, shift: u32) -> Option<u128> {
+        u128::checked_shr(self, shift)
+    }
+    fn checked_add(self, other: u128) -> Option<u128> {
+        u128::checked_add(self, other)
+    }
+    fn checked_sub(self, other: u128) -> Option<u128> {
+        u128::checked_sub(self, other)
+    }
+    fn count_ones(self) -> u32 {
+        u128::count_ones(self)
+    }
+}
@@ -0,0 +1,281 @@
+//! Subnet capacity as seen by cloud providers, which keep some addresses
+//! of every subnet for their own use, and readers for the IP range feeds
+//! they publish
+use crate::json::{self, Json};
+use crate::{Error, IpNetwork, Ipv4Network};
+use std::net::Ipv4Addr;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    /// Keeps the network address, the next three and the broadcast address
+    Aws,
+    /// Keeps the network address, the gateway, two DNS addresses and the
+    /// broadcast address
+    Azure,
+    /// Keeps the network address, the gateway, the second to last and the
+    /// broadcast address
+    Gcp,
+}
+
+impl CloudProvider {
+    /// Reserved addresses counted from the start and from the end of a subnet
+    fn reserved_counts(&self) -> (u32, u32) {
+        match self {
+            CloudProvider::Aws => (4, 1),
+            CloudProvider::Azure => (4, 1),
+            CloudProvider::Gcp => (2, 2),
+        }
+    }
+
+    /// Prefix lengths the provider accepts for an IPv4 subnet
+    pub fn subnet_prefix_range(&self) -> RangeInclusive<u8> {
+        match self {
+            CloudProvider::Aws => 16..=28,
+            CloudProvider::Azure => 2..=29,
+            CloudProvider::Gcp => 4..=29,
+        }
+    }
+
+    /// Fails with `InvalidNetwork` when the provider does not accept a
+    /// subnet of this size
+    pub fn check_subnet(&self, subnet: &Ipv4Network) -> Result<(), Error> {
+        match self.subnet_prefix_range().contains(&subnet.cidr) {
+            true => Ok(()),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+
+    /// Addresses of the subnet that cannot be assigned to instances
+    /// ```
+    /// use ipnetwork::cloud::CloudProvider;
+    /// use ipnetwork::Ipv4Network;
+    /// use std::net::Ipv4Addr;
+    /// let subnet: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+    /// let reserved = CloudProvider::Aws.reserved_addresses(&subnet);
+    /// assert_eq!(reserved.len(), 5);
+    /// assert_eq!(reserved[4], Ipv4Addr::new(10, 0, 0, 255));
+    /// ```
+    pub fn reserved_addresses(&self, subnet: &Ipv4Network) -> Vec<Ipv4Addr> {
+        let (head, tail) = self.reserved_counts();
+        let first = subnet.first;
+        let last = u32::from(subnet.last());
+        let mut reserved: Vec<u32> = (0..head)
+            .filter_map(|offset| first.checked_add(offset))
+            .chain(
+                (0..tail)
+                    .rev()
+                    .filter_map(|offset| last.checked_sub(offset)),
+            )
+            .filter(|addr| first <= *addr && *addr <= last)
+            .collect();
+        reserved.sort_unstable();
+        reserved.dedup();
+        reserved.into_iter().map(Ipv4Addr::from).collect()
+    }
+
+    /// Number of addresses left for instances
+    pub fn usable_hosts(&self, subnet: &Ipv4Network) -> u64 {
+        let total = u64::from(u32::from(subnet.last()) - subnet.first) + 1;
+        total - self.reserved_addresses(subnet).len() as u64
+    }
+
+    /// First and last address handed out by the provider's DHCP, or None
+    /// when the reservations take up the whole subnet
+    pub fn dhcp_pool(&self, subnet: &Ipv4Network) -> Option<(Ipv4Addr, Ipv4Addr)> {
+        let (head, tail) = self.reserved_counts();
+        let first = subnet.first.checked_add(head)?;
+        let last = u32::from(subnet.last()).checked_sub(tail)?;
+        match first <= last {
+            true => Some((Ipv4Addr::from(first), Ipv4Addr::from(last))),
+            false => None,
+        }
+    }
+}
+
+/// A string field of a feed entry
+fn field<'a>(entry: &'a Json, key: &str) -> Result<&'a str, Error> {
+    entry
+        .get(key)
+        .and_then(Json::as_str)
+        .ok_or(Error::NetworkParseError)
+}
+
+/// An array field of a feed entry
+fn list<'a>(entry: &'a Json, key: &str) -> Result<&'a [Json], Error> {
+    match entry.get(key) {
+        Some(Json::Array(values)) => Ok(values),
+        _ => Err(Error::NetworkParseError),
+    }
+}
+
+fn range(prefix: &str, service: &str, region: &str) -> Result<(IpNetwork, String, String), Error> {
+    Ok((
+        IpNetwork::parse_ascii(prefix.as_bytes())?,
+        service.to_string(),
+        region.to_string(),
+    ))
+}
+
+/// Reads the (network, service, region) entries of the AWS
+/// `ip-ranges.json` feed, IPv4 prefixes first. A network is listed once
+/// for every service using it, as in the feed.
+/// ```
+/// use ipnetwork::cloud::aws_ip_ranges;
+/// let feed = r#"{"syncToken":"1","createDate":"2024-01-01-00-00-00",
+///     "prefixes":[{"ip_prefix":"3.5.140.0/22","region":"ap-northeast-2","service":"AMAZON","network_border_group":"ap-northeast-2"}],
+///     "ipv6_prefixes":[{"ipv6_prefix":"2600:1f14::/35","region":"us-west-2","service":"EC2","network_border_group":"us-west-2"}]}"#;
+/// let ranges = aws_ip_ranges(feed).unwrap();
+/// assert_eq!(ranges[0].0.to_string(), "3.5.140.0/22");
+/// assert_eq!((ranges[1].1.as_str(), ranges[1].2.as_str()), ("EC2", "us-west-2"));
+/// ```
+pub fn aws_ip_ranges(json: &str) -> Result<Vec<(IpNetwork, String, String)>, Error> {
+    let feed = json::parse(json)?;
+    let mut ranges = Vec::new();
+    for (entries, prefix) in [("prefixes", "ip_prefix"), ("ipv6_prefixes", "ipv6_prefix")].iter() {
+        for entry in list(&feed, entries)? {
+            ranges.push(range(
+                field(entry, prefix)?,
+                field(entry, "service")?,
+                field(entry, "region")?,
+            )?);
+        }
+    }
+    Ok(ranges)
+}
+
+/// Reads the (network, service, region) entries of the GCP `cloud.json`
+/// feed, the region is the `scope` of an entry
+pub fn gcp_cloud_ranges(json: &str) -> Result<Vec<(IpNetwork, String, String)>, Error> {
+    let feed = json::parse(json)?;
+    let mut ranges = Vec::new();
+    for entry in list(&feed, "prefixes")? {
+        let prefix = field(entry, "ipv4Prefix").or_else(|_| field(entry, "ipv6Prefix"))?;
+        ranges.push(range(
+            prefix,
+            field(entry, "service")?,
+            field(entry, "scope")?,
+        )?);
+    }
+    Ok(ranges)
+}
+
+/// Reads the (network, service, region) entries of an Azure
+/// `ServiceTags` feed. The service is the name of the service tag, like
+/// `AzureStorage.westeurope`, and the region is empty for tags that are
+/// not bound to one.
+pub fn azure_service_tags(json: &str) -> Result<Vec<(IpNetwork, String, String)>, Error> {
+    // The downloads start with a byte order mark
+    let feed = json::parse(json.trim_start_matches('\u{feff}'))?;
+    let mut ranges = Vec::new();
+    for tag in list(&feed, "values")? {
+        let name = field(tag, "name")?;
+        let properties = tag.get("properties").ok_or(Error::NetworkParseError)?;
+        let region = field(properties, "region")?;
+        for prefix in list(properties, "addressPrefixes")? {
+            let prefix = prefix.as_str().ok_or(Error::NetworkParseError)?;
+            ranges.push(range(prefix, name, region)?);
+        }
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usable_hosts() {
+        let subnet: Ipv4Network = "10.0.0.0/28".parse().unwrap();
+        assert_eq!(CloudProvider::Aws.usable_hosts(&subnet), 11);
+        assert_eq!(CloudProvider::Azure.usable_hosts(&subnet), 11);
+        assert_eq!(CloudProvider::Gcp.usable_hosts(&subnet), 12);
+        let tiny: Ipv4Network = "10.0.0.0/30".parse().unwrap();
+        assert_eq!(CloudProvider::Aws.usable_hosts(&tiny), 0);
+        assert_eq!(CloudProvider::Aws.reserved_addresses(&tiny).len(), 4);
+    }
+
+    #[test]
+    fn dhcp_pool() {
+        let subnet: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+        assert_eq!(
+            CloudProvider::Gcp.dhcp_pool(&subnet),
+            Some((Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 253)))
+        );
+        let reserved = CloudProvider::Gcp.reserved_addresses(&subnet);
+        assert_eq!(reserved[2], Ipv4Addr::new(10, 0, 0, 254));
+        let tiny: Ipv4Network = "10.0.0.0/30".parse().unwrap();
+        assert_eq!(CloudProvider::Azure.dhcp_pool(&tiny), None);
+        let top: Ipv4Network = "255.255.255.252/30".parse().unwrap();
+        assert_eq!(CloudProvider::Aws.dhcp_pool(&top), None);
+    }
+
+    #[test]
+    fn check_subnet() {
+        let large: Ipv4Network = "10.0.0.0/12".parse().unwrap();
+        let small: Ipv4Network = "10.0.0.0/29".parse().unwrap();
+        assert_eq!(
+            CloudProvider::Aws.check_subnet(&large),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            CloudProvider::Aws.check_subnet(&small),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(CloudProvider::Azure.check_subnet(&small), Ok(()));
+    }
+
+    #[test]
+    fn read_feeds() {
+        let tagged = |ranges: Vec<(IpNetwork, String, String)>| -> Vec<String> {
+            ranges
+                .iter()
+                .map(|(network, service, region)| format!("{} {} {}", network, service, region))
+                .collect()
+        };
+        let gcp = r#"{"syncToken":"1","creationTime":"2024-01-01T00:00:00",
+            "prefixes":[{"ipv4Prefix":"34.35.0.0/16","service":"Google Cloud","scope":"africa-south1"},
+                        {"ipv6Prefix":"2600:1900:8000::/44","service":"Google Cloud","scope":"africa-south1"}]}"#;
+        assert_eq!(
+            tagged(gcp_cloud_ranges(gcp).unwrap()),
+            vec![
+                "34.35.0.0/16 Google Cloud africa-south1",
+                "2600:1900:8000::/44 Google Cloud africa-south1"
+            ]
+        );
+        let azure = "\u{feff}{\"changeNumber\":1,\"cloud\":\"Public\",\"values\":[\
+            {\"name\":\"ActionGroup\",\"id\":\"ActionGroup\",\"properties\":{\"changeNumber\":1,\"region\":\"\",\
+              \"regionId\":0,\"platform\":\"Azure\",\"systemService\":\"ActionGroup\",\
+              \"addressPrefixes\":[\"4.145.74.52/30\",\"2603:1030::/64\"],\"networkFeatures\":[\"API\"]}},\
+            {\"name\":\"AzureStorage.westeurope\",\"id\":\"AzureStorage.westeurope\",\"properties\":{\"changeNumber\":1,\
+              \"region\":\"westeurope\",\"regionId\":18,\"platform\":\"Azure\",\"systemService\":\"AzureStorage\",\
+              \"addressPrefixes\":[\"13.69.40.16/28\"]}}]}";
+        assert_eq!(
+            tagged(azure_service_tags(azure).unwrap()),
+            vec![
+                "4.145.74.52/30 ActionGroup ",
+                "2603:1030::/64 ActionGroup ",
+                "13.69.40.16/28 AzureStorage.westeurope westeurope"
+            ]
+        );
+        let bad = [
+            r#"{"prefixes":[]}"#,
+            r#"{"prefixes":[{"ip_prefix":"3.5.140.1/22","region":"r","service":"s"}],"ipv6_prefixes":[]}"#,
+            r#"{"prefixes":[{"ip_prefix":"3.5.140.0/22","service":"s"}],"ipv6_prefixes":[]}"#,
+            r#"{"prefixes":[],"ipv6_prefixes":[]"#,
+        ];
+        for feed in bad.iter() {
+            assert!(aws_ip_ranges(feed).is_err(), "{}", feed);
+        }
+        assert_eq!(
+            gcp_cloud_ranges(r#"{"prefixes":[{"service":"s","scope":"r"}]}"#),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            azure_service_tags(
+                r#"{"values":[{"name":"n","properties":{"region":"","addressPrefixes":[1]}}]}"#
+            ),
+            Err(Error::NetworkParseError)
+        );
+    }
+}
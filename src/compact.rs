@@ -0,0 +1,319 @@
+//! Lossy compaction of prefix lists to a fixed number of entries, for
+//! hardware access lists and TCAMs that only hold so many
+use crate::bits::AddressBits;
+use crate::{Error, Network};
+
+/// Prefix list returned by `compact`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compaction<B: AddressBits> {
+    /// The entries, sorted and disjoint
+    pub networks: Vec<Network<B>>,
+    /// Number of addresses the entries cover that no input network did
+    pub extra: B,
+}
+
+/// How a node of the prefix tree is covered with a given number of entries
+#[derive(Debug, Clone, Copy)]
+enum Choice {
+    /// By the node network itself
+    Cover,
+    /// By this many entries under the low half and the high half
+    Split(usize, usize),
+}
+
+/// Smallest network holding a run of input networks, with the least extra
+/// space each number of entries can cover the run with
+struct Node<B: AddressBits> {
+    network: Network<B>,
+    /// Addresses of the input networks
+    covered: B,
+    /// Extra space per number of entries, None when it is not enough
+    cost: Vec<Option<B>>,
+    choice: Vec<Choice>,
+    halves: Option<Box<(Node<B>, Node<B>)>>,
+}
+
+/// Smallest network holding every address from `low` to `high`
+fn common_supernet<B: AddressBits>(low: B, high: B) -> Network<B> {
+    let mut cidr = B::BITS;
+    while cidr > 0 {
+        let hostmask = Network::<B>::cidr_to_hostmask(cidr);
+        if low & !hostmask == high & !hostmask {
+            break;
+        }
+        cidr -= 1;
+    }
+    let first = low & !Network::<B>::cidr_to_hostmask(cidr);
+    Network { first, cidr }
+}
+
+/// Returns true when the network overlaps one of the sorted disjoint
+/// networks
+fn overlaps_any<B: AddressBits>(network: &Network<B>, sorted: &[Network<B>]) -> bool {
+    let start = sorted.partition_point(|n| n.last_bits() < network.first);
+    sorted
+        .get(start)
+        .is_some_and(|n| n.first <= network.last_bits())
+}
+
+fn build<B: AddressBits>(
+    networks: &[Network<B>],
+    budget: usize,
+    forbidden: &[Network<B>],
+) -> Node<B> {
+    if let [network] = networks {
+        return Node {
+            network: *network,
+            covered: network.saturating_hostcount(),
+            cost: vec![None, Some(B::ZERO)],
+            choice: vec![Choice::Cover, Choice::Cover],
+            halves: None,
+        };
+    }
+    let last = networks[networks.len() - 1].last_bits();
+    let network = common_supernet(networks[0].first, last);
+    let middle = network.first | Network::<B>::cidr_to_stepping(network.cidr + 1);
+    let split = networks.partition_point(|n| n.first < middle);
+    let low = build(&networks[..split], budget, forbidden);
+    let high = build(&networks[split..], budget, forbidden);
+    let covered = low.covered + high.covered;
+    let limit = budget.min(networks.len());
+    let mut cost = vec![None; limit + 1];
+    let mut choice = vec![Choice::Cover; limit + 1];
+    for (k, low_cost) in low.cost.iter().enumerate() {
+        for (l, high_cost) in high.cost.iter().enumerate().take(limit + 1 - k) {
+            if let (Some(a), Some(b)) = (low_cost, high_cost) {
+                if cost[k + l].is_none_or(|best| *a + *b < best) {
+                    cost[k + l] = Some(*a + *b);
+                    choice[k + l] = Choice::Split(k, l);
+                }
+            }
+        }
+    }
+    if !overlaps_any(&network, forbidden) {
+        // Every input network is at least a /1 here, so the count of the
+        // node minus one fits
+        let extra = Network::<B>::cidr_to_hostmask(network.cidr) - (covered - B::ONE);
+        if cost[1].is_none_or(|best| extra < best) {
+            cost[1] = Some(extra);
+            choice[1] = Choice::Cover;
+        }
+    }
+    // Fewer entries than allowed are fine
+    for k in 1..=limit {
+        if let Some(fewer) = cost[k - 1] {
+            if cost[k].is_none_or(|best| fewer <= best) {
+                cost[k] = Some(fewer);
+                choice[k] = choice[k - 1];
+            }
+        }
+    }
+    Node {
+        network,
+        covered,
+        cost,
+        choice,
+        halves: Some(Box::new((low, high))),
+    }
+}
+
+fn collect<B: AddressBits>(node: &Node<B>, entries: usize, networks: &mut Vec<Network<B>>) {
+    match (node.choice[entries], &node.halves) {
+        (Choice::Split(low, high), Some(halves)) => {
+            collect(&halves.0, low, networks);
+            collect(&halves.1, high, networks);
+        }
+        _ => networks.push(node.network),
+    }
+}
+
+/// Reduces the networks to at most `budget` entries covering every
+/// address of them, while covering as little extra address space as
+/// possible. No entry overlaps a network of `must_not_cover`.
+///
+/// Fails with `CidrOverlap` when a network overlaps a network of
+/// `must_not_cover`, and with `NotEnoughAddresses` when the budget cannot
+/// be met without covering one.
+/// ```
+/// use ipnetwork::compact::compact;
+/// use ipnetwork::Ipv4Network;
+/// let nets = |list: &[&str]| -> Vec<Ipv4Network> { list.iter().map(|n| n.parse().unwrap()).collect() };
+/// let networks = nets(&["10.0.0.0/24", "10.0.2.0/24", "10.0.3.0/24", "192.0.2.0/24"]);
+/// let compaction = compact(&networks, 2, &[]).unwrap();
+/// assert_eq!(compaction.networks, nets(&["10.0.0.0/22", "192.0.2.0/24"]));
+/// assert_eq!(compaction.extra, 256);
+/// // keep 10.0.1.0/24 out
+/// let compaction = compact(&networks, 3, &nets(&["10.0.1.0/24"])).unwrap();
+/// assert_eq!(compaction.networks, nets(&["10.0.0.0/24", "10.0.2.0/23", "192.0.2.0/24"]));
+/// assert_eq!(compaction.extra, 0);
+/// ```
+pub fn compact<B: AddressBits>(
+    networks: &[Network<B>],
+    budget: usize,
+    must_not_cover: &[Network<B>],
+) -> Result<Compaction<B>, Error> {
+    let networks = Network::aggregate(networks);
+    let forbidden = Network::aggregate(must_not_cover);
+    if networks
+        .iter()
+        .any(|network| overlaps_any(network, &forbidden))
+    {
+        return Err(Error::CidrOverlap);
+    }
+    if networks.len() <= budget {
+        return Ok(Compaction {
+            networks,
+            extra: B::ZERO,
+        });
+    }
+    if budget == 0 {
+        return Err(Error::NotEnoughAddresses);
+    }
+    let root = build(&networks, budget, &forbidden);
+    let extra = root.cost[budget].ok_or(Error::NotEnoughAddresses)?;
+    let mut compacted = Vec::with_capacity(budget);
+    collect(&root, budget, &mut compacted);
+    Ok(Compaction {
+        networks: compacted,
+        extra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ipv4Network, Ipv6Network};
+
+    fn nets(list: &[&str]) -> Vec<Ipv4Network> {
+        list.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    /// Least extra space over every grouping of the sorted networks into
+    /// runs covered by their smallest supernet
+    fn brute_force(
+        networks: &[Ipv4Network],
+        budget: usize,
+        forbidden: &[Ipv4Network],
+    ) -> Option<u32> {
+        let covered: u32 = networks.iter().map(|n| n.hostcount()).sum();
+        let mut best = None;
+        for cuts in 0u32..1 << (networks.len() - 1) {
+            let mut covers = Vec::new();
+            let mut start = 0;
+            for end in 1..=networks.len() {
+                if end == networks.len() || cuts & 1 << (end - 1) != 0 {
+                    covers.push(common_supernet(
+                        networks[start].first,
+                        networks[end - 1].last_bits(),
+                    ));
+                    start = end;
+                }
+            }
+            let covers = Ipv4Network::aggregate(&covers);
+            let allowed = covers.iter().all(|c| {
+                !forbidden
+                    .iter()
+                    .any(|f| f.first <= c.last_bits() && c.first <= f.last_bits())
+            });
+            if allowed && covers.len() <= budget {
+                let extra = covers.iter().map(|n| n.hostcount()).sum::<u32>() - covered;
+                best = Some(best.map_or(extra, |best: u32| best.min(extra)));
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn least_extra_space() {
+        let networks = nets(&[
+            "10.0.0.0/24",
+            "10.0.1.0/25",
+            "10.0.4.0/24",
+            "10.0.6.0/23",
+            "10.1.0.0/30",
+        ]);
+        let extras: Vec<u32> = (1..=5)
+            .map(|budget| compact(&networks, budget, &[]).unwrap().extra)
+            .collect();
+        assert_eq!(extras, vec![129_916, 896, 384, 128, 0]);
+        assert_eq!(
+            compact(&networks, 3, &[]).unwrap().networks,
+            nets(&["10.0.0.0/23", "10.0.4.0/22", "10.1.0.0/30"])
+        );
+        let forbidden = nets(&["10.0.5.0/24"]);
+        assert_eq!(
+            compact(&networks, 3, &forbidden),
+            Err(Error::NotEnoughAddresses)
+        );
+        let compaction = compact(&networks, 4, &forbidden).unwrap();
+        assert_eq!(
+            compaction.networks,
+            nets(&["10.0.0.0/23", "10.0.4.0/24", "10.0.6.0/23", "10.1.0.0/30"])
+        );
+        assert_eq!(compaction.extra, 128);
+        assert_eq!(
+            compact(&networks, 2, &nets(&["10.0.0.0/8"])),
+            Err(Error::CidrOverlap)
+        );
+        assert_eq!(compact(&networks, 0, &[]), Err(Error::NotEnoughAddresses));
+        let empty: Vec<Ipv4Network> = Vec::new();
+        assert_eq!(compact(&empty, 0, &[]).unwrap().networks, empty);
+        let halves: Vec<Ipv6Network> = vec!["::/2".parse().unwrap(), "c000::/2".parse().unwrap()];
+        let whole = compact(&halves, 1, &[]).unwrap();
+        assert_eq!(whole.networks, vec!["::/0".parse::<Ipv6Network>().unwrap()]);
+        assert_eq!(whole.extra, 1 << 127);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut seed = 0x2545_f491u32;
+        for _ in 0..300 {
+            let mut random = || {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed
+            };
+            let network = |n: u32| {
+                let cidr = 20 + (n % 9) as u8;
+                Ipv4Network {
+                    first: (0x0a00_0000 | n >> 12 & 0xfff00) & !Ipv4Network::cidr_to_hostmask(cidr),
+                    cidr,
+                }
+            };
+            let candidates: Vec<Ipv4Network> = (0..8).map(|_| network(random())).collect();
+            let forbidden: Vec<Ipv4Network> = (0..2)
+                .map(|_| network(random()))
+                .filter(|f| {
+                    !candidates
+                        .iter()
+                        .any(|n| n.first <= f.last_bits() && f.first <= n.last_bits())
+                })
+                .collect();
+            let networks = Ipv4Network::aggregate(&candidates);
+            let forbidden = Ipv4Network::aggregate(&forbidden);
+            let budget = 1 + random() as usize % networks.len();
+            let expected = brute_force(&networks, budget, &forbidden);
+            match compact(&networks, budget, &forbidden) {
+                Ok(compaction) => {
+                    assert_eq!(Some(compaction.extra), expected);
+                    assert!(compaction.networks.len() <= budget);
+                    for n in networks.iter() {
+                        assert!(compaction.networks.iter().any(|c| c.is_subnet(n)));
+                    }
+                    let size: u32 = compaction.networks.iter().map(|n| n.hostcount()).sum();
+                    let covered: u32 = networks.iter().map(|n| n.hostcount()).sum();
+                    assert_eq!(size - covered, compaction.extra);
+                    assert!(!compaction
+                        .networks
+                        .iter()
+                        .any(|c| overlaps_any(c, &forbidden)));
+                }
+                Err(error) => {
+                    assert_eq!(error, Error::NotEnoughAddresses);
+                    assert_eq!(expected, None);
+                }
+            }
+        }
+    }
+}
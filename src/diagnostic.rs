@@ -0,0 +1,300 @@
+//! Parsing with diagnostics that explain why a network was rejected and
+//! suggest what was probably meant, for messages shown to people
+use crate::{parse, Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::fmt;
+use std::net::IpAddr;
+
+/// Why a network was rejected, with the normalized network to suggest
+/// instead when there is one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The text is not an address in CIDR notation. A network is suggested
+    /// when the text only has surrounding whitespace or octets with
+    /// leading zeros.
+    Syntax { suggestion: Option<IpNetwork> },
+    /// The `/prefix` is missing, the host network is suggested
+    MissingPrefix { suggestion: IpNetwork },
+    /// The prefix length is above the address width `max`, the host
+    /// network is suggested
+    PrefixTooLong {
+        prefix: u8,
+        max: u8,
+        suggestion: IpNetwork,
+    },
+    /// The address has bits set past the prefix, the network holding it
+    /// is suggested
+    HostBitsSet { suggestion: IpNetwork },
+    /// The network is of the other family than asked for. IPv4-mapped
+    /// IPv6 networks are suggested as IPv4 networks and the other way round.
+    WrongFamily {
+        found: IpNetwork,
+        suggestion: Option<IpNetwork>,
+    },
+}
+
+impl Diagnostic {
+    pub fn suggestion(&self) -> Option<IpNetwork> {
+        match *self {
+            Diagnostic::Syntax { suggestion } => suggestion,
+            Diagnostic::MissingPrefix { suggestion } => Some(suggestion),
+            Diagnostic::PrefixTooLong { suggestion, .. } => Some(suggestion),
+            Diagnostic::HostBitsSet { suggestion } => Some(suggestion),
+            Diagnostic::WrongFamily { suggestion, .. } => suggestion,
+        }
+    }
+
+    /// The error `parse_ascii` reports for the same input
+    pub fn error(&self) -> Error {
+        match self {
+            Diagnostic::PrefixTooLong { .. } | Diagnostic::HostBitsSet { .. } => {
+                Error::InvalidNetwork
+            }
+            _ => Error::NetworkParseError,
+        }
+    }
+}
+
+impl From<Diagnostic> for Error {
+    fn from(diagnostic: Diagnostic) -> Error {
+        diagnostic.error()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::Syntax { .. } => write!(f, "not a network in CIDR notation")?,
+            Diagnostic::MissingPrefix { .. } => write!(f, "missing prefix length")?,
+            Diagnostic::PrefixTooLong { prefix, max, .. } => {
+                write!(f, "prefix length {} is longer than {}", prefix, max)?
+            }
+            Diagnostic::HostBitsSet { .. } => write!(f, "host bits are set")?,
+            Diagnostic::WrongFamily { found, .. } => {
+                let expected = match found {
+                    IpNetwork::V4(_) => "IPv6",
+                    IpNetwork::V6(_) => "IPv4",
+                };
+                write!(f, "expected an {} network, found {}", expected, found)?
+            }
+        }
+        match self.suggestion() {
+            Some(suggestion) => write!(f, ", did you mean {}?", suggestion),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// The /`cidr` network holding the address
+fn truncate(addr: IpAddr, cidr: u8) -> IpNetwork {
+    match addr {
+        IpAddr::V4(v4) => IpNetwork::V4(Ipv4Network {
+            first: u32::from(v4) & !Ipv4Network::cidr_to_hostmask(cidr),
+            cidr,
+        }),
+        IpAddr::V6(v6) => IpNetwork::V6(Ipv6Network {
+            first: u128::from(v6) & !Ipv6Network::cidr_to_hostmask(cidr),
+            cidr,
+        }),
+    }
+}
+
+/// Text with surrounding whitespace and leading zeros of IPv4 octets
+/// removed, None when there is nothing to remove
+fn repair(bytes: &[u8]) -> Option<Vec<u8>> {
+    let trimmed = bytes.trim_ascii();
+    let mut repaired = Vec::with_capacity(trimmed.len());
+    let mut in_octet = false;
+    for (i, &byte) in trimmed.iter().enumerate() {
+        let leading_zero = byte == b'0'
+            && !in_octet
+            && trimmed.get(i + 1).is_some_and(u8::is_ascii_digit)
+            && !trimmed.contains(&b':')
+            && !repaired.contains(&b'/');
+        if !leading_zero {
+            repaired.push(byte);
+            in_octet = byte.is_ascii_digit();
+        }
+    }
+    match repaired == bytes {
+        true => None,
+        false => Some(repaired),
+    }
+}
+
+/// Parses a network of either family in CIDR notation like
+/// `IpNetwork::parse_ascii`, explaining the failures
+/// ```
+/// use ipnetwork::diagnostic::{diagnose, Diagnostic};
+/// let diagnostic = diagnose(b"10.1.2.3/24").unwrap_err();
+/// assert_eq!(diagnostic.to_string(), "host bits are set, did you mean 10.1.2.0/24?");
+/// let diagnostic = diagnose(b"192.168.001.0/24").unwrap_err();
+/// assert_eq!(diagnostic.suggestion().unwrap().to_string(), "192.168.1.0/24");
+/// ```
+pub fn diagnose(bytes: &[u8]) -> Result<IpNetwork, Diagnostic> {
+    let syntax = || Diagnostic::Syntax {
+        suggestion: repair(bytes).and_then(|repaired| match diagnose(&repaired) {
+            Ok(network) => Some(network),
+            Err(diagnostic) => diagnostic.suggestion(),
+        }),
+    };
+    let (addr, prefix) = match parse::split_prefix(bytes) {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (bytes, None),
+    };
+    let addr = parse::ip_addr(addr).map_err(|_| syntax())?;
+    let width = match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    let prefix = match prefix {
+        Some(prefix) => parse::prefix_len(prefix).ok_or_else(syntax)?,
+        None => {
+            return Err(Diagnostic::MissingPrefix {
+                suggestion: truncate(addr, width),
+            })
+        }
+    };
+    if prefix > width {
+        return Err(Diagnostic::PrefixTooLong {
+            prefix,
+            max: width,
+            suggestion: truncate(addr, width),
+        });
+    }
+    let network = truncate(addr, prefix);
+    let first = match network {
+        IpNetwork::V4(n) => IpAddr::V4(n.first()),
+        IpNetwork::V6(n) => IpAddr::V6(n.first()),
+    };
+    match first == addr {
+        true => Ok(network),
+        false => Err(Diagnostic::HostBitsSet {
+            suggestion: network,
+        }),
+    }
+}
+
+/// Parses a network of either family like `diagnose`, for argument parsers
+/// taking a `fn(&str) -> Result<T, E>` and showing the error, like clap's
+/// `value_parser`
+/// ```
+/// use ipnetwork::diagnostic::diagnose_str;
+/// let diagnostic = diagnose_str("10.0.0.1/8").unwrap_err();
+/// assert_eq!(diagnostic.to_string(), "host bits are set, did you mean 10.0.0.0/8?");
+/// ```
+pub fn diagnose_str(s: &str) -> Result<IpNetwork, Diagnostic> {
+    diagnose(s.as_bytes())
+}
+
+/// Parses an IPv4 network like `Ipv4Network::parse_ascii`, explaining the
+/// failures
+/// ```
+/// use ipnetwork::diagnostic::diagnose_ipv4;
+/// let diagnostic = diagnose_ipv4(b"::ffff:192.0.2.0/120").unwrap_err();
+/// assert_eq!(diagnostic.suggestion().unwrap().to_string(), "192.0.2.0/24");
+/// ```
+pub fn diagnose_ipv4(bytes: &[u8]) -> Result<Ipv4Network, Diagnostic> {
+    match diagnose(bytes)? {
+        IpNetwork::V4(network) => Ok(network),
+        found @ IpNetwork::V6(network) => {
+            let mapped = Ipv6Network::new(0xffff << 32, 96).expect("valid network");
+            let suggestion = match network.cidr >= 96 && mapped.is_subnet(&network) {
+                true => Some(IpNetwork::V4(Ipv4Network {
+                    first: network.first as u32,
+                    cidr: network.cidr - 96,
+                })),
+                false => None,
+            };
+            Err(Diagnostic::WrongFamily { found, suggestion })
+        }
+    }
+}
+
+/// Parses an IPv6 network like `Ipv6Network::parse_ascii`, explaining the
+/// failures
+pub fn diagnose_ipv6(bytes: &[u8]) -> Result<Ipv6Network, Diagnostic> {
+    match diagnose(bytes)? {
+        IpNetwork::V6(network) => Ok(network),
+        found @ IpNetwork::V4(network) => Err(Diagnostic::WrongFamily {
+            found,
+            suggestion: Some(IpNetwork::V6(Ipv6Network {
+                first: 0xffff << 32 | u128::from(network.first),
+                cidr: network.cidr + 96,
+            })),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> String {
+        diagnose(text.as_bytes()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            message("10.0.0.1"),
+            "missing prefix length, did you mean 10.0.0.1/32?"
+        );
+        assert_eq!(
+            message("10.0.0.1/33"),
+            "prefix length 33 is longer than 32, did you mean 10.0.0.1/32?"
+        );
+        assert_eq!(
+            message("2001:db8::1/32"),
+            "host bits are set, did you mean 2001:db8::/32?"
+        );
+        assert_eq!(message("10.0.0/8"), "not a network in CIDR notation");
+        assert_eq!(message("10.0.0.0/x"), "not a network in CIDR notation");
+        assert_eq!(
+            message(" 010.001.0.1/8\n"),
+            "not a network in CIDR notation, did you mean 10.0.0.0/8?"
+        );
+        assert_eq!(
+            diagnose_ipv6(b"192.0.2.0/24").unwrap_err().to_string(),
+            "expected an IPv6 network, found 192.0.2.0/24, did you mean ::ffff:192.0.2.0/120?"
+        );
+        assert_eq!(
+            diagnose_ipv4(b"2001:db8::/32").unwrap_err().to_string(),
+            "expected an IPv4 network, found 2001:db8::/32"
+        );
+    }
+
+    #[test]
+    fn same_result_as_parse_ascii() {
+        let inputs = [
+            "10.0.0.0/8",
+            "10.0.0.1/8",
+            "10.0.0.0/40",
+            "10.0.0.0",
+            "2001:db8::/32",
+            "2001:db8::/129",
+            "garbage",
+            "::/0",
+            "",
+            "/",
+        ];
+        for input in inputs.iter() {
+            let expected = IpNetwork::parse_ascii(input.as_bytes());
+            assert_eq!(
+                diagnose(input.as_bytes()).map_err(Error::from),
+                expected,
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            diagnose_ipv4(b"10.0.0.0/8"),
+            Ok("10.0.0.0/8".parse().unwrap())
+        );
+        assert_eq!(
+            diagnose_ipv6(b"fe80::/10"),
+            Ok("fe80::/10".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! An IPv4 and an IPv6 network managed together as one dual-stack segment
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// The two networks of a dual-stack segment. Hosts are paired by their
+/// offset in the networks, so the fifth IPv4 host goes with the fifth IPv6
+/// address.
+///
+/// The text form is both networks separated by a comma, as in the
+/// dual-stack flags of Kubernetes. Either family may come first. With the
+/// `serde` feature, the network is serialized as this text.
+/// ```
+/// use ipnetwork::dual::DualStackNetwork;
+/// let segment: DualStackNetwork = "10.0.1.0/24,2001:db8:0:1::/64".parse().unwrap();
+/// assert!(segment.contains(&"10.0.1.7".parse().unwrap()));
+/// assert!(segment.contains(&"2001:db8:0:1::8".parse().unwrap()));
+/// assert_eq!(segment.paired_v6("10.0.1.7".parse().unwrap()), Some("2001:db8:0:1::7".parse().unwrap()));
+/// assert_eq!(segment.to_string(), "10.0.1.0/24,2001:db8:0:1::/64");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DualStackNetwork {
+    pub v4: Ipv4Network,
+    pub v6: Ipv6Network,
+}
+
+impl DualStackNetwork {
+    pub fn new(v4: Ipv4Network, v6: Ipv6Network) -> DualStackNetwork {
+        DualStackNetwork { v4, v6 }
+    }
+
+    /// Returns true when the address is in the network of its family.
+    /// IPv4-mapped IPv6 addresses are looked up as IPv4 addresses.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match addr {
+            IpAddr::V4(v4) => self.v4.contains(v4),
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => self.v4.contains(&v4),
+                None => self.v6.contains(v6),
+            },
+        }
+    }
+
+    /// Returns the network of the address family
+    pub fn network_for(&self, addr: &IpAddr) -> IpNetwork {
+        match addr {
+            IpAddr::V4(_) => IpNetwork::V4(self.v4),
+            IpAddr::V6(_) => IpNetwork::V6(self.v6),
+        }
+    }
+
+    /// Returns the IPv6 address paired with an IPv4 host, None when the
+    /// host is outside the IPv4 network or the IPv6 network is smaller
+    pub fn paired_v6(&self, addr: Ipv4Addr) -> Option<Ipv6Addr> {
+        if !self.v4.contains(&addr) {
+            return None;
+        }
+        let offset = u128::from(u32::from(addr) - self.v4.first);
+        let paired = self.v6.first.checked_add(offset)?;
+        match self.v6.contains(&Ipv6Addr::from(paired)) {
+            true => Some(Ipv6Addr::from(paired)),
+            false => None,
+        }
+    }
+
+    /// Returns the IPv4 host paired with an IPv6 address, None when the
+    /// address is outside the IPv6 network or past the IPv4 network
+    pub fn paired_v4(&self, addr: Ipv6Addr) -> Option<Ipv4Addr> {
+        if !self.v6.contains(&addr) {
+            return None;
+        }
+        let offset = u32::try_from(u128::from(addr) - self.v6.first).ok()?;
+        let paired = self.v4.first.checked_add(offset)?;
+        match self.v4.contains(&Ipv4Addr::from(paired)) {
+            true => Some(Ipv4Addr::from(paired)),
+            false => None,
+        }
+    }
+
+    /// Iterates over the pairs of hosts, as many as the smaller network has
+    /// ```
+    /// use ipnetwork::dual::DualStackNetwork;
+    /// let segment: DualStackNetwork = "2001:db8::/126,192.0.2.0/24".parse().unwrap();
+    /// let pairs: Vec<_> = segment.hosts().map(|(v4, v6)| format!("{} {}", v4, v6)).collect();
+    /// assert_eq!(pairs, ["192.0.2.0 2001:db8::", "192.0.2.1 2001:db8::1", "192.0.2.2 2001:db8::2", "192.0.2.3 2001:db8::3"]);
+    /// ```
+    pub fn hosts(&self) -> impl Iterator<Item = (Ipv4Addr, Ipv6Addr)> {
+        let segment = *self;
+        self.v4
+            .into_hosts()
+            .map_while(move |v4| segment.paired_v6(v4).map(|v6| (v4, v6)))
+    }
+}
+
+impl fmt::Display for DualStackNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.v4, self.v6)
+    }
+}
+
+impl FromStr for DualStackNetwork {
+    type Err = Error;
+
+    /// Fails with `NetworkParseError` unless there is exactly one network
+    /// of each family
+    fn from_str(s: &str) -> Result<DualStackNetwork, Error> {
+        let (first, second) = s.split_once(',').ok_or(Error::NetworkParseError)?;
+        let first = IpNetwork::parse_ascii(first.trim().as_bytes())?;
+        let second = IpNetwork::parse_ascii(second.trim().as_bytes())?;
+        match (first, second) {
+            (IpNetwork::V4(v4), IpNetwork::V6(v6)) | (IpNetwork::V6(v6), IpNetwork::V4(v4)) => {
+                Ok(DualStackNetwork { v4, v6 })
+            }
+            _ => Err(Error::NetworkParseError),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DualStackNetwork {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DualStackNetwork {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = DualStackNetwork;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an IPv4 and an IPv6 network separated by a comma")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<DualStackNetwork, E> {
+                s.parse()
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(s), &self))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(s: &str) -> DualStackNetwork {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let expected = segment("10.0.0.0/8,fd00::/8");
+        assert_eq!(segment(" fd00::/8 , 10.0.0.0/8"), expected);
+        assert_eq!(expected.to_string().parse(), Ok(expected));
+        let bad = [
+            "10.0.0.0/8",
+            "10.0.0.0/8,10.1.0.0/16",
+            "fd00::/8,fd01::/16",
+            "10.0.0.1/8,fd00::/8",
+            "10.0.0.0/8,fd00::/8,192.0.2.0/24",
+        ];
+        for input in bad.iter() {
+            assert!(input.parse::<DualStackNetwork>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn pair_hosts() {
+        let s = segment("192.0.2.0/24,2001:db8::/64");
+        let v4: Ipv4Addr = "192.0.2.200".parse().unwrap();
+        let v6 = s.paired_v6(v4).unwrap();
+        assert_eq!(v6, "2001:db8::c8".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(s.paired_v4(v6), Some(v4));
+        assert_eq!(s.paired_v4("2001:db8::100".parse().unwrap()), None);
+        assert_eq!(s.paired_v4("2001:db9::".parse().unwrap()), None);
+        assert_eq!(s.paired_v6("192.0.3.1".parse().unwrap()), None);
+        assert_eq!(s.hosts().count(), 256);
+        assert!(s.contains(&"::ffff:192.0.2.9".parse().unwrap()));
+        assert!(!s.contains(&"198.51.100.1".parse().unwrap()));
+        assert_eq!(s.network_for(&"::1".parse().unwrap()), IpNetwork::V6(s.v6));
+        let whole = segment("0.0.0.0/0,::/0");
+        let last = whole.paired_v6(Ipv4Addr::BROADCAST).unwrap();
+        assert_eq!(whole.paired_v4(last), Some(Ipv4Addr::BROADCAST));
+        let top = segment("0.0.0.0/0,ffff:ffff:ffff:ffff:ffff:ffff:ffff:ff00/120");
+        assert_eq!(top.paired_v6(Ipv4Addr::BROADCAST), None);
+        assert_eq!(
+            top.paired_v6("0.0.0.255".parse().unwrap()),
+            Some(Ipv6Addr::from(u128::MAX))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize() {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::Deserialize;
+        let text = |s| StrDeserializer::<Error>::new(s);
+        let parsed = DualStackNetwork::deserialize(text("fd00::/8,10.0.0.0/8"));
+        assert_eq!(parsed, Ok(segment("10.0.0.0/8,fd00::/8")));
+        assert!(DualStackNetwork::deserialize(text("10.0.0.0/8")).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Finding and scrubbing addresses and networks in free text, like log
+//! lines or tickets
+use crate::hashing::KeyPrefixes;
+use crate::{parse, IpNetwork, Ipv4Network, Ipv6Network};
+use std::fmt::Write;
+use std::net::IpAddr;
+
+/// Address found in text, with the prefix length when it was written in
+/// CIDR notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Found {
+    /// Byte range of the token in the text
+    pub start: usize,
+    pub end: usize,
+    pub addr: IpAddr,
+    pub prefix: Option<u8>,
+}
+
+impl Found {
+    /// Returns the network written. Host bits are cleared, so interface
+    /// notation like `192.0.2.1/24` gives its network.
+    pub fn network(&self) -> IpNetwork {
+        truncate(self.addr, self.prefix)
+    }
+}
+
+/// The /`prefix` network holding the address, a host network when there
+/// is no prefix
+fn truncate(addr: IpAddr, prefix: Option<u8>) -> IpNetwork {
+    match addr {
+        IpAddr::V4(v4) => {
+            let cidr = prefix.unwrap_or(32);
+            IpNetwork::V4(Ipv4Network {
+                first: u32::from(v4) & !Ipv4Network::cidr_to_hostmask(cidr),
+                cidr,
+            })
+        }
+        IpAddr::V6(v6) => {
+            let cidr = prefix.unwrap_or(128);
+            IpNetwork::V6(Ipv6Network {
+                first: u128::from(v6) & !Ipv6Network::cidr_to_hostmask(cidr),
+                cidr,
+            })
+        }
+    }
+}
+
+/// Bytes an address can be made of
+fn is_address_byte(byte: u8) -> bool {
+    byte.is_ascii_hexdigit() || byte == b'.' || byte == b':'
+}
+
+/// Bytes that glue a token to a word, like in `id5.6.7.8` or `10.0.0.1x`
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Reads the address of a token, the token may end with punctuation
+/// like a full stop, or an IPv4 address may carry a port
+fn token_addr(token: &[u8]) -> Option<(IpAddr, usize)> {
+    if let Ok(addr) = parse::ip_addr(token) {
+        return Some((addr, token.len()));
+    }
+    let trimmed = token.len()
+        - token
+            .iter()
+            .rev()
+            .take_while(|&&b| b == b'.' || b == b':')
+            .count();
+    if let Ok(addr) = parse::ip_addr(&token[..trimmed]) {
+        return Some((addr, trimmed));
+    }
+    let colon = token.iter().position(|&b| b == b':')?;
+    let port = &token[colon + 1..];
+    std::str::from_utf8(port).ok()?.parse::<u16>().ok()?;
+    let addr = parse::ipv4_addr(&token[..colon]).ok()?;
+    Some((IpAddr::V4(addr), colon))
+}
+
+/// Returns every address and network written in the text, in order.
+/// Tokens glued to words, like version numbers with more parts or
+/// hexadecimal identifiers, are skipped.
+/// ```
+/// use ipnetwork::extract::find_networks;
+/// let text = "denied 192.0.2.7:5060 from 2001:db8::/32, see 198.51.100.1.";
+/// let found = find_networks(text);
+/// assert_eq!(found.len(), 3);
+/// assert_eq!(&text[found[0].start..found[0].end], "192.0.2.7");
+/// assert_eq!(found[1].prefix, Some(32));
+/// assert_eq!(found[2].addr, "198.51.100.1".parse::<std::net::IpAddr>().unwrap());
+/// ```
+pub fn find_networks(text: &str) -> Vec<Found> {
+    let bytes = text.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !is_address_byte(bytes[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < bytes.len() && is_address_byte(bytes[i]) {
+            i += 1;
+        }
+        // The whole run is skipped when it is glued to a word, the search
+        // goes on after it
+        let token = &bytes[start..i];
+        let glued = (start > 0 && is_word_byte(bytes[start - 1]))
+            || bytes.get(i).is_some_and(|&b| is_word_byte(b));
+        if glued || !token.iter().any(u8::is_ascii_hexdigit) {
+            continue;
+        }
+        let (addr, len) = match token_addr(token) {
+            Some(found) => found,
+            None => continue,
+        };
+        let mut end = start + len;
+        let mut prefix = None;
+        if len == token.len() && bytes.get(end) == Some(&b'/') {
+            let digits = bytes[end + 1..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            let after = end + 1 + digits;
+            let width = match addr {
+                IpAddr::V4(_) => 32,
+                IpAddr::V6(_) => 128,
+            };
+            match parse::prefix_len(&bytes[end + 1..after]) {
+                Some(cidr) if cidr <= width => {
+                    if bytes.get(after).is_some_and(|&b| is_word_byte(b)) {
+                        i = after;
+                        continue;
+                    }
+                    prefix = Some(cidr);
+                    end = after;
+                    i = after;
+                }
+                _ if digits > 0 => {
+                    i = after;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        found.push(Found {
+            start,
+            end,
+            addr,
+            prefix,
+        });
+    }
+    found
+}
+
+/// How `scrub` replaces the addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scrub<'a> {
+    /// Replaces every address or network by the text
+    Mask(&'a str),
+    /// Replaces every address by the first address of its key network,
+    /// see `hashing::aggregation_key`. Networks keep their notation and are
+    /// cut to the key prefix when they are longer.
+    Anonymize(KeyPrefixes),
+}
+
+/// Returns the text with every address and network found by
+/// `find_networks` replaced
+/// ```
+/// use ipnetwork::extract::{scrub, Scrub};
+/// use ipnetwork::hashing::KeyPrefixes;
+/// let line = "login from 192.0.2.77 via 2001:db8:1:2::1";
+/// assert_eq!(scrub(line, Scrub::Mask("<ip>")), "login from <ip> via <ip>");
+/// let anonymized = scrub(line, Scrub::Anonymize(KeyPrefixes::ANONYMIZED));
+/// assert_eq!(anonymized, "login from 192.0.2.0 via 2001:db8:1::");
+/// ```
+pub fn scrub(text: &str, mode: Scrub) -> String {
+    let mut scrubbed = String::with_capacity(text.len());
+    let mut copied = 0;
+    for found in find_networks(text) {
+        scrubbed.push_str(&text[copied..found.start]);
+        copied = found.end;
+        match mode {
+            Scrub::Mask(mask) => scrubbed.push_str(mask),
+            Scrub::Anonymize(prefixes) => {
+                let key = prefixes.key(found.addr);
+                let first = key_addr(&key);
+                // Writing to a String cannot fail
+                let _ = match found.prefix {
+                    Some(prefix) if first.is_ipv4() == found.addr.is_ipv4() => {
+                        let cidr = match key {
+                            IpNetwork::V4(n) => n.cidr,
+                            IpNetwork::V6(n) => n.cidr,
+                        };
+                        write!(scrubbed, "{}", truncate(first, Some(prefix.min(cidr))))
+                    }
+                    Some(_) => write!(scrubbed, "{}", key),
+                    None => write!(scrubbed, "{}", first),
+                };
+            }
+        }
+    }
+    scrubbed.push_str(&text[copied..]);
+    scrubbed
+}
+
+/// First address of a network
+fn key_addr(network: &IpNetwork) -> IpAddr {
+    match network {
+        IpNetwork::V4(n) => IpAddr::V4(n.first()),
+        IpNetwork::V6(n) => IpAddr::V6(n.first()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<&str> {
+        find_networks(text)
+            .iter()
+            .map(|found| &text[found.start..found.end])
+            .collect()
+    }
+
+    #[test]
+    fn find_tokens() {
+        assert_eq!(
+            tokens("a 10.0.0.1, b [2001:db8::1]:443 c fe80::1%eth0 d ::1."),
+            vec!["10.0.0.1", "2001:db8::1", "fe80::1", "::1"]
+        );
+        assert_eq!(
+            tokens("route 10.0.0.0/8 and 192.0.2.5/24 via 2001:db8::/48:"),
+            vec!["10.0.0.0/8", "192.0.2.5/24", "2001:db8::/48"]
+        );
+        // versions, identifiers, times and bad prefixes are not addresses
+        let noise = "v1.2.3.4.5 id10.0.0.1 10.0.0.1x 12:30:45 cafe:babe std::vec \
+                     00:1a:2b:3c:4d:5e 10.0.0.0/33 10.0.0.0/8a deadbeef :: 1.2.3.4:99999";
+        assert_eq!(tokens(noise), Vec::<&str>::new());
+        let found = find_networks("10.1.2.3/16");
+        assert_eq!(found[0].network().to_string(), "10.1.0.0/16");
+        assert_eq!(
+            find_networks("2001:db8::")[0].network().to_string(),
+            "2001:db8::/128"
+        );
+        assert!(find_networks("").is_empty());
+    }
+
+    #[test]
+    fn scrub_text() {
+        let text = "nets 10.1.2.0/23 and 2001:db8:1::/64, host ::ffff:198.51.100.9: ok";
+        assert_eq!(
+            scrub(text, Scrub::Anonymize(KeyPrefixes::ANONYMIZED)),
+            "nets 10.1.2.0/23 and 2001:db8:1::/48, host 198.51.100.0: ok"
+        );
+        assert_eq!(
+            scrub(text, Scrub::Anonymize(KeyPrefixes { v4: 16, v6: 32 })),
+            "nets 10.1.0.0/16 and 2001:db8::/32, host 198.51.0.0: ok"
+        );
+        assert_eq!(scrub(text, Scrub::Mask("x")), "nets x and x, host x: ok");
+        assert_eq!(scrub("nothing here", Scrub::Mask("x")), "nothing here");
+    }
+}
@@ -0,0 +1,238 @@
+//! Prefix filters with `ge`/`le` length bounds, as used by BGP prefix
+//! lists and route maps
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{FromStr, SplitWhitespace};
+
+/// Matches the networks inside `network` whose prefix length is between
+/// `ge` and `le`, both included
+/// ```
+/// use ipnetwork::filter::PrefixFilter;
+/// use ipnetwork::IpNetwork;
+/// let filter: PrefixFilter = "10.0.0.0/8 ge 24 le 28".parse().unwrap();
+/// assert!(filter.matches(&IpNetwork::V4("10.1.2.0/24".parse().unwrap())));
+/// assert!(!filter.matches(&IpNetwork::V4("10.1.0.0/16".parse().unwrap())));
+/// assert!(!filter.matches(&IpNetwork::V4("192.168.0.0/24".parse().unwrap())));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PrefixFilter {
+    network: IpNetwork,
+    ge: u8,
+    le: u8,
+}
+
+fn prefix_len(network: &IpNetwork) -> u8 {
+    match network {
+        IpNetwork::V4(n) => n.cidr,
+        IpNetwork::V6(n) => n.cidr,
+    }
+}
+
+fn max_prefix_len(network: &IpNetwork) -> u8 {
+    match network {
+        IpNetwork::V4(_) => 32,
+        IpNetwork::V6(_) => 128,
+    }
+}
+
+impl PrefixFilter {
+    /// Creates a filter with the bounds of a prefix list entry: without
+    /// bounds only the network itself matches, `ge` alone matches up to
+    /// host routes and `le` alone matches from the network's own length.
+    /// Fails with `InvalidNetwork` unless the network length, `ge`, `le`
+    /// and the address width are in increasing order.
+    pub fn new(network: IpNetwork, ge: Option<u8>, le: Option<u8>) -> Result<PrefixFilter, Error> {
+        let len = prefix_len(&network);
+        let max = max_prefix_len(&network);
+        let (ge, le) = match (ge, le) {
+            (None, None) => (len, len),
+            (Some(ge), None) => (ge, max),
+            (None, Some(le)) => (len, le),
+            (Some(ge), Some(le)) => (ge, le),
+        };
+        match len <= ge && ge <= le && le <= max {
+            true => Ok(PrefixFilter { network, ge, le }),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+
+    /// Creates a filter matching only `network`
+    pub fn exact(network: IpNetwork) -> PrefixFilter {
+        let len = prefix_len(&network);
+        PrefixFilter {
+            network,
+            ge: len,
+            le: len,
+        }
+    }
+
+    pub fn network(&self) -> IpNetwork {
+        self.network
+    }
+    pub fn ge(&self) -> u8 {
+        self.ge
+    }
+    pub fn le(&self) -> u8 {
+        self.le
+    }
+
+    /// Returns true when `network` lies within the filter's network and
+    /// its prefix length is within the bounds
+    pub fn matches(&self, network: &IpNetwork) -> bool {
+        let len = prefix_len(network);
+        self.ge <= len && len <= self.le && self.network.is_subnet(network)
+    }
+
+    /// Returns the networks matching the filter, in their original order
+    pub fn filter(&self, networks: &[IpNetwork]) -> Vec<IpNetwork> {
+        networks
+            .iter()
+            .filter(|network| self.matches(network))
+            .copied()
+            .collect()
+    }
+}
+
+/// Returns true when any of the filters matches the network
+pub fn matches_any(filters: &[PrefixFilter], network: &IpNetwork) -> bool {
+    filters.iter().any(|filter| filter.matches(network))
+}
+
+/// Returns the networks matched by at least one of the filters, in their
+/// original order
+/// ```
+/// use ipnetwork::filter::{filter_networks, PrefixFilter};
+/// use ipnetwork::IpNetwork;
+/// let filters: Vec<PrefixFilter> = ["10.0.0.0/8 le 16", "2001:db8::/32 ge 48 le 48"]
+///     .iter()
+///     .map(|f| f.parse().unwrap())
+///     .collect();
+/// let networks = [
+///     IpNetwork::V4("10.0.0.0/24".parse().unwrap()),
+///     IpNetwork::V4("10.1.0.0/16".parse().unwrap()),
+///     IpNetwork::V6("2001:db8:1::/48".parse().unwrap()),
+/// ];
+/// assert_eq!(filter_networks(&filters, &networks), networks[1..].to_vec());
+/// ```
+pub fn filter_networks(filters: &[PrefixFilter], networks: &[IpNetwork]) -> Vec<IpNetwork> {
+    networks
+        .iter()
+        .filter(|network| matches_any(filters, network))
+        .copied()
+        .collect()
+}
+
+impl fmt::Display for PrefixFilter {
+    /// Writes the filter the way Cisco IOS prefix lists do, leaving out
+    /// bounds that are implied
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let len = prefix_len(&self.network);
+        write!(f, "{}", self.network)?;
+        if self.ge > len && self.le == max_prefix_len(&self.network) {
+            write!(f, " ge {}", self.ge)
+        } else if self.ge > len {
+            write!(f, " ge {} le {}", self.ge, self.le)
+        } else if self.le > len {
+            write!(f, " le {}", self.le)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Parses an optional `name N` bound
+fn bound(words: &mut Peekable<SplitWhitespace>, name: &str) -> Result<Option<u8>, Error> {
+    if words.peek() != Some(&name) {
+        return Ok(None);
+    }
+    words.next();
+    match words.next().map(str::parse::<u8>) {
+        Some(Ok(len)) => Ok(Some(len)),
+        _ => Err(Error::NetworkParseError),
+    }
+}
+
+impl FromStr for PrefixFilter {
+    type Err = Error;
+
+    /// Parses `network [ge N] [le N]`
+    fn from_str(s: &str) -> Result<PrefixFilter, Self::Err> {
+        let mut words = s.split_whitespace();
+        let network = match words.next() {
+            Some(word) if word.contains(':') => IpNetwork::V6(word.parse::<Ipv6Network>()?),
+            Some(word) => IpNetwork::V4(word.parse::<Ipv4Network>()?),
+            None => return Err(Error::NetworkParseError),
+        };
+        let mut words = words.peekable();
+        let ge = bound(&mut words, "ge")?;
+        let le = bound(&mut words, "le")?;
+        if words.next().is_some() {
+            return Err(Error::NetworkParseError);
+        }
+        PrefixFilter::new(network, ge, le)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> IpNetwork {
+        IpNetwork::V4(s.parse().unwrap())
+    }
+
+    #[test]
+    fn bounds() {
+        let filter: PrefixFilter = "10.0.0.0/8".parse().unwrap();
+        assert!(filter.matches(&v4("10.0.0.0/8")));
+        assert!(!filter.matches(&v4("10.0.0.0/9")));
+        let filter: PrefixFilter = "10.0.0.0/8 ge 30".parse().unwrap();
+        assert_eq!((filter.ge(), filter.le()), (30, 32));
+        assert!(filter.matches(&v4("10.255.255.255/32")));
+        let filter: PrefixFilter = "0.0.0.0/0 le 8".parse().unwrap();
+        assert!(filter.matches(&v4("0.0.0.0/0")));
+        assert!(filter.matches(&v4("11.0.0.0/8")));
+        assert!(!filter.matches(&IpNetwork::V6("::/0".parse().unwrap())));
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for text in [
+            "10.0.0.0/8",
+            "10.0.0.0/8 ge 24",
+            "10.0.0.0/8 le 24",
+            "10.0.0.0/8 ge 16 le 24",
+            "2001:db8::/32 ge 48 le 64",
+        ]
+        .iter()
+        {
+            let filter: PrefixFilter = text.parse().unwrap();
+            assert_eq!(&filter.to_string(), text);
+        }
+        assert_eq!(
+            "10.0.0.0/8 ge 8 le 32"
+                .parse::<PrefixFilter>()
+                .unwrap()
+                .to_string(),
+            "10.0.0.0/8 le 32"
+        );
+        let invalid = [
+            "10.0.0.0/8 ge 4",
+            "10.0.0.0/8 ge 24 le 16",
+            "10.0.0.0/8 le 33",
+        ];
+        for text in invalid.iter() {
+            assert_eq!(text.parse::<PrefixFilter>(), Err(Error::InvalidNetwork));
+        }
+        let malformed = [
+            "",
+            "10.0.0.0/8 le 24 ge 16",
+            "10.0.0.0/8 ge",
+            "10.0.0.0/8 eq 5",
+        ];
+        for text in malformed.iter() {
+            assert_eq!(text.parse::<PrefixFilter>(), Err(Error::NetworkParseError));
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Stable assignment of addresses to buckets and grouping keys
+use crate::bits::AddressBits;
+use crate::random::random_subnet;
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network, Network};
+use std::net::IpAddr;
+
+/// Subnets tried by `subnet_for_label` before giving up
+const LABEL_PROBES: usize = 64;
+
+/// splitmix64 finalizer, spreads the address bits over the whole key
+fn mix(mut key: u64) -> u64 {
+    key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    key ^ (key >> 31)
+}
+
+/// Jump consistent hash (Lamport, Veach). Growing the bucket count from
+/// n to n + 1 only moves keys into the new bucket.
+fn jump(mut key: u64, buckets: u32) -> u32 {
+    let mut bucket: i64 = -1;
+    let mut next: i64 = 0;
+    while next < i64::from(buckets) {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as u32
+}
+
+fn key_of(addr: IpAddr, seed: u64) -> u64 {
+    match addr {
+        IpAddr::V4(addr) => mix(seed ^ mix(u64::from(u32::from(addr)))),
+        IpAddr::V6(addr) => {
+            let bits = u128::from(addr);
+            let high = mix(seed ^ mix((bits >> 64) as u64));
+            mix(high ^ !(bits as u64))
+        }
+    }
+}
+
+/// Clears the host bits of the address beyond the family's prefix length
+pub(crate) fn truncate(addr: IpAddr, v4_prefix: u8, v6_prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(addr) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(v4_prefix.min(32)));
+            IpAddr::V4((u32::from(addr) & mask.unwrap_or(0)).into())
+        }
+        IpAddr::V6(addr) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(v6_prefix.min(128)));
+            IpAddr::V6((u128::from(addr) & mask.unwrap_or(0)).into())
+        }
+    }
+}
+
+/// Maps an address to one of `buckets` buckets.
+///
+/// The result only depends on the address, the bucket count and the seed,
+/// so every process computes the same assignment. When the bucket count
+/// grows, only the addresses that move to the new buckets change. A bucket
+/// count of 0 is treated as 1.
+/// ```
+/// use ipnetwork::hashing::bucket_of;
+/// let addr = "192.0.2.1".parse().unwrap();
+/// assert_eq!(bucket_of(addr, 8, 1), bucket_of(addr, 8, 1));
+/// assert!(bucket_of(addr, 8, 1) < 8);
+/// ```
+pub fn bucket_of(addr: IpAddr, buckets: u32, seed: u64) -> u32 {
+    jump(key_of(addr, seed), buckets.max(1))
+}
+
+/// Like `bucket_of`, but all addresses of the same /`v4_prefix` or
+/// /`v6_prefix` network land in the same bucket
+/// ```
+/// use ipnetwork::hashing::prefix_bucket_of;
+/// let a = "192.0.2.1".parse().unwrap();
+/// let b = "192.0.2.200".parse().unwrap();
+/// assert_eq!(prefix_bucket_of(a, 24, 48, 16, 0), prefix_bucket_of(b, 24, 48, 16, 0));
+/// ```
+pub fn prefix_bucket_of(
+    addr: IpAddr,
+    v4_prefix: u8,
+    v6_prefix: u8,
+    buckets: u32,
+    seed: u64,
+) -> u32 {
+    bucket_of(truncate(addr, v4_prefix, v6_prefix), buckets, seed)
+}
+
+/// Prefix lengths used to group client addresses of both families
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPrefixes {
+    pub v4: u8,
+    pub v6: u8,
+}
+
+impl KeyPrefixes {
+    /// One key per IPv4 host and per IPv6 /64, the usual unit handed to a
+    /// single subscriber. Suits rate limiting.
+    pub const SUBSCRIBER: KeyPrefixes = KeyPrefixes { v4: 32, v6: 64 };
+    /// IPv4 /24 and IPv6 /48, coarse enough to not identify a single
+    /// client. Suits analytics and logs.
+    pub const ANONYMIZED: KeyPrefixes = KeyPrefixes { v4: 24, v6: 48 };
+
+    /// Returns the key of the address, see `aggregation_key`
+    pub fn key(&self, addr: IpAddr) -> IpNetwork {
+        aggregation_key(addr, self.v4, self.v6)
+    }
+}
+
+/// Returns the /`v4_prefix` or /`v6_prefix` network containing the
+/// address, to be used as the key grouping clients. IPv4-mapped IPv6
+/// addresses get the key of their IPv4 address, so dual stack listeners
+/// group clients like IPv4 ones. Prefixes longer than the address are
+/// treated as host prefixes.
+/// ```
+/// use ipnetwork::hashing::{aggregation_key, KeyPrefixes};
+/// use ipnetwork::IpNetwork;
+/// let key = aggregation_key("2001:db8:1:2:3::1".parse().unwrap(), 32, 64);
+/// assert_eq!(key, IpNetwork::V6("2001:db8:1:2::/64".parse().unwrap()));
+/// let key = KeyPrefixes::ANONYMIZED.key("::ffff:192.0.2.77".parse().unwrap());
+/// assert_eq!(key, IpNetwork::V4("192.0.2.0/24".parse().unwrap()));
+/// ```
+pub fn aggregation_key(addr: IpAddr, v4_prefix: u8, v6_prefix: u8) -> IpNetwork {
+    let addr = match addr {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+        v4 => v4,
+    };
+    match truncate(addr, v4_prefix, v6_prefix) {
+        IpAddr::V4(first) => IpNetwork::V4(Ipv4Network {
+            first: u32::from(first),
+            cidr: v4_prefix.min(32),
+        }),
+        IpAddr::V6(first) => IpNetwork::V6(Ipv6Network {
+            first: u128::from(first),
+            cidr: v6_prefix.min(128),
+        }),
+    }
+}
+
+/// FNV-1a hash of the label
+fn label_key(label: &str) -> u64 {
+    label.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns the /`prefix` subnet of `parent` a label is assigned to, like a
+/// tenant name or a VNI. The subnet only depends on the label, the parent
+/// and the prefix, so it can be recomputed anywhere without storing it.
+///
+/// Subnets overlapping one of the `excluded` networks, for example the
+/// ones already given to other labels, are skipped by trying the next
+/// subnet in an order derived from the label. When none of the first 64
+/// tried is free the first free subnet in address order is taken. Fails
+/// with `CidrOverlap` when every subnet is excluded, and like
+/// `random_subnet` for invalid prefixes.
+/// ```
+/// use ipnetwork::hashing::subnet_for_label;
+/// use ipnetwork::Ipv4Network;
+/// let parent: Ipv4Network = "10.0.0.0/16".parse().unwrap();
+/// let blue = subnet_for_label(&parent, 24, "tenant-blue", &[]).unwrap();
+/// assert_eq!(subnet_for_label(&parent, 24, "tenant-blue", &[]), Ok(blue));
+/// let moved = subnet_for_label(&parent, 24, "tenant-blue", &[blue]).unwrap();
+/// assert_ne!(moved, blue);
+/// ```
+pub fn subnet_for_label<B: AddressBits>(
+    parent: &Network<B>,
+    prefix: u8,
+    label: &str,
+    excluded: &[Network<B>],
+) -> Result<Network<B>, Error> {
+    let mut state = label_key(label);
+    let mut rng = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(state)
+    };
+    let overlapping = |subnet: &Network<B>| {
+        excluded
+            .iter()
+            .filter(|n| n.is_subnet(subnet) || subnet.is_subnet(n))
+            .map(|n| n.last_bits())
+            .max()
+    };
+    for _ in 0..LABEL_PROBES {
+        let subnet = random_subnet(parent, prefix, &mut rng)?;
+        if overlapping(&subnet).is_none() {
+            return Ok(subnet);
+        }
+    }
+    // Scan the subnets in order, jumping past the excluded networks
+    let hostmask = Network::<B>::cidr_to_hostmask(prefix);
+    let mut subnet = Network::new_unchecked(parent.first, prefix);
+    while let Some(last) = overlapping(&subnet) {
+        subnet.first = match (last | hostmask).checked_add(B::ONE) {
+            Some(first) if first <= parent.last_bits() => first,
+            _ => return Err(Error::CidrOverlap),
+        };
+    }
+    Ok(subnet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn buckets_are_consistent() {
+        for host in 0..1000u32 {
+            let addr = IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + host * 7919));
+            let before = bucket_of(addr, 10, 3);
+            let after = bucket_of(addr, 11, 3);
+            assert!(after == before || after == 10);
+        }
+    }
+
+    #[test]
+    fn buckets_are_spread() {
+        let mut counts = [0; 4];
+        for host in 0..4000u32 {
+            counts[bucket_of(IpAddr::V4(Ipv4Addr::from(host)), 4, 0) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 800 && c < 1200), "{:?}", counts);
+        assert_eq!(bucket_of(IpAddr::V4(Ipv4Addr::LOCALHOST), 0, 0), 0);
+    }
+
+    #[test]
+    fn prefix_buckets() {
+        let a: Ipv6Addr = "2001:db8:1:2::1".parse().unwrap();
+        let b: Ipv6Addr = "2001:db8:1:ff::1".parse().unwrap();
+        assert_eq!(
+            prefix_bucket_of(IpAddr::V6(a), 24, 48, 64, 9),
+            prefix_bucket_of(IpAddr::V6(b), 24, 48, 64, 9)
+        );
+        assert_eq!(
+            truncate(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 0, 0),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+    }
+
+    #[test]
+    fn aggregation_keys() {
+        let addr: IpAddr = "192.0.2.77".parse().unwrap();
+        assert_eq!(
+            KeyPrefixes::SUBSCRIBER.key(addr),
+            IpNetwork::V4("192.0.2.77/32".parse().unwrap())
+        );
+        assert_eq!(
+            aggregation_key(addr, 40, 200),
+            KeyPrefixes::SUBSCRIBER.key(addr)
+        );
+        assert_eq!(
+            aggregation_key(addr, 0, 0),
+            IpNetwork::V4("0.0.0.0/0".parse().unwrap())
+        );
+        let v6: IpAddr = "2001:db8:aaaa:bbbb:1:2:3:4".parse().unwrap();
+        assert_eq!(
+            KeyPrefixes::ANONYMIZED.key(v6),
+            IpNetwork::V6("2001:db8:aaaa::/48".parse().unwrap())
+        );
+        assert_eq!(
+            aggregation_key(v6, 24, 200),
+            IpNetwork::V6("2001:db8:aaaa:bbbb:1:2:3:4/128".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn label_subnets() {
+        let parent: Ipv6Network = "2001:db8::/32".parse().unwrap();
+        let a = subnet_for_label(&parent, 48, "vni-100", &[]).unwrap();
+        let b = subnet_for_label(&parent, 48, "vni-101", &[]).unwrap();
+        assert!(parent.is_subnet(&a) && a.cidr == 48);
+        assert_ne!(a, b);
+        assert_eq!(subnet_for_label(&parent, 48, "vni-100", &[b]), Ok(a));
+
+        let parent: Ipv4Network = "10.0.0.0/30".parse().unwrap();
+        let mut taken = Vec::new();
+        for label in ["a", "b", "c", "d"].iter() {
+            taken.push(subnet_for_label(&parent, 32, label, &taken).unwrap());
+        }
+        taken.sort_unstable();
+        assert_eq!(taken, parent.into_subnets(32).collect::<Vec<_>>());
+        assert_eq!(
+            subnet_for_label(&parent, 32, "e", &taken),
+            Err(Error::CidrOverlap)
+        );
+        assert_eq!(
+            subnet_for_label(&parent, 31, "e", &["10.0.0.0/29".parse().unwrap()]),
+            Err(Error::CidrOverlap)
+        );
+        assert_eq!(
+            subnet_for_label(&parent, 8, "e", &[]),
+            Err(Error::CidrMissMatch)
+        );
+
+        // a single free subnet is found however unlucky the probes are
+        let parent: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+        let free: Ipv4Network = "10.0.0.77/32".parse().unwrap();
+        let mut taken: Vec<Ipv4Network> = parent.into_subnets(32).filter(|n| *n != free).collect();
+        taken.push("10.0.0.0/26".parse().unwrap());
+        for label in 0..50 {
+            let label = format!("tenant-{}", label);
+            assert_eq!(subnet_for_label(&parent, 32, &label, &taken), Ok(free));
+        }
+    }
+}
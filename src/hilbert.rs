@@ -0,0 +1,291 @@
+//! Hilbert curve layout of address space, as used by IPv4 maps and
+//! heatmaps. Consecutive addresses stay close on the map and every
+//! aligned network covers a square or a 2:1 rectangle.
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::net::IpAddr;
+
+/// Rotates and flips a quadrant of side `mask + 1`
+fn rotate(mask: u64, x: &mut u64, y: &mut u64, rx: u64, ry: u64) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = mask - *x;
+            *y = mask - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
+/// Position of the `index`th cell of a curve of `order` levels
+fn index_to_xy(index: u128, order: u8) -> (u64, u64) {
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut rest = index;
+    for level in 0..order {
+        let side = 1u64 << level;
+        let rx = (rest >> 1) as u64 & 1;
+        let ry = (rest as u64 ^ rx) & 1;
+        rotate(side - 1, &mut x, &mut y, rx, ry);
+        x += side * rx;
+        y += side * ry;
+        rest >>= 2;
+    }
+    (x, y)
+}
+
+/// Inverse of `index_to_xy`, coordinates must be below 2^`order`
+fn xy_to_index(mut x: u64, mut y: u64, order: u8) -> u128 {
+    let mask = u64::MAX.checked_shr(64 - u32::from(order)).unwrap_or(0);
+    let mut index = 0u128;
+    for level in (0..order).rev() {
+        let side = 1u64 << level;
+        let rx = u64::from(x & side != 0);
+        let ry = u64::from(y & side != 0);
+        index += u128::from(side) * u128::from(side) * u128::from((3 * rx) ^ ry);
+        rotate(mask, &mut x, &mut y, rx, ry);
+    }
+    index
+}
+
+/// First address, prefix length and address width of a network
+fn bounds(network: &IpNetwork) -> (u128, u8, u8) {
+    match network {
+        IpNetwork::V4(n) => (u128::from(n.first), n.cidr, 32),
+        IpNetwork::V6(n) => (n.first, n.cidr, 128),
+    }
+}
+
+/// Network laid out on a square of 2^`order` by 2^`order` cells along a
+/// Hilbert curve. Each cell is a /`space prefix + 2 * order` network.
+/// ```
+/// use ipnetwork::hilbert::HilbertMap;
+/// use ipnetwork::IpNetwork;
+/// // the xkcd map: every /8 of IPv4 is one cell of a 16 by 16 square
+/// let map = HilbertMap::new(IpNetwork::V4("0.0.0.0/0".parse().unwrap()), 4).unwrap();
+/// assert_eq!(map.xy(&"0.1.2.3".parse().unwrap()), Some((0, 0)));
+/// assert_eq!(map.xy(&"1.0.0.0".parse().unwrap()), Some((1, 0)));
+/// assert_eq!(map.cell(1, 1), Some(IpNetwork::V4("2.0.0.0/8".parse().unwrap())));
+/// assert_eq!(map.xy(&"255.0.0.0".parse().unwrap()), Some((15, 0)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HilbertMap {
+    space: IpNetwork,
+    order: u8,
+}
+
+impl HilbertMap {
+    /// Fails with `InvalidNetwork` when the cells would be smaller than
+    /// single addresses
+    pub fn new(space: IpNetwork, order: u8) -> Result<HilbertMap, Error> {
+        let (_, cidr, bits) = bounds(&space);
+        match u32::from(cidr) + 2 * u32::from(order) <= u32::from(bits) {
+            true => Ok(HilbertMap { space, order }),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+
+    pub fn space(&self) -> IpNetwork {
+        self.space
+    }
+
+    pub fn order(&self) -> u8 {
+        self.order
+    }
+
+    /// Number of cells along one side
+    pub fn side(&self) -> u128 {
+        1 << self.order
+    }
+
+    /// Prefix length of the cells
+    pub fn cell_prefix(&self) -> u8 {
+        bounds(&self.space).1 + 2 * self.order
+    }
+
+    /// Index along the curve of the cell holding the address, None when
+    /// the address is outside the space
+    fn index(&self, addr: &IpAddr) -> Option<u128> {
+        if !self.space.contains(addr) {
+            return None;
+        }
+        let (first, _, bits) = bounds(&self.space);
+        let addr = match addr {
+            IpAddr::V4(addr) => u128::from(u32::from(*addr)),
+            IpAddr::V6(addr) => u128::from(*addr),
+        };
+        Some(
+            (addr - first)
+                .checked_shr(u32::from(bits - self.cell_prefix()))
+                .unwrap_or(0),
+        )
+    }
+
+    /// Returns the coordinates of the cell holding the address, None when
+    /// the address is outside the space
+    pub fn xy(&self, addr: &IpAddr) -> Option<(u64, u64)> {
+        self.index(addr).map(|index| index_to_xy(index, self.order))
+    }
+
+    /// Returns the network of the cell at the coordinates, None when they
+    /// are outside the map. This is the inverse of `xy`.
+    pub fn cell(&self, x: u64, y: u64) -> Option<IpNetwork> {
+        if u128::from(x) >= self.side() || u128::from(y) >= self.side() {
+            return None;
+        }
+        let (first, _, bits) = bounds(&self.space);
+        let cidr = self.cell_prefix();
+        let offset = xy_to_index(x, y, self.order)
+            .checked_shl(u32::from(bits - cidr))
+            .unwrap_or(0);
+        Some(match self.space {
+            IpNetwork::V4(_) => IpNetwork::V4(Ipv4Network {
+                first: (first + offset) as u32,
+                cidr,
+            }),
+            IpNetwork::V6(_) => IpNetwork::V6(Ipv6Network {
+                first: first + offset,
+                cidr,
+            }),
+        })
+    }
+
+    /// Returns the smallest rectangle of cells covering the network, as
+    /// the inclusive corners `(x_min, y_min, x_max, y_max)`. Networks
+    /// smaller than a cell give the cell holding them, networks not inside
+    /// the space give None.
+    /// ```
+    /// use ipnetwork::hilbert::HilbertMap;
+    /// use ipnetwork::IpNetwork;
+    /// let map = HilbertMap::new(IpNetwork::V4("0.0.0.0/0".parse().unwrap()), 4).unwrap();
+    /// let private = IpNetwork::V4("10.0.0.0/8".parse().unwrap());
+    /// assert_eq!(map.rect(&private), Some((3, 3, 3, 3)));
+    /// let quarter = IpNetwork::V4("0.0.0.0/2".parse().unwrap());
+    /// assert_eq!(map.rect(&quarter), Some((0, 0, 7, 7)));
+    /// let half = IpNetwork::V4("0.0.0.0/1".parse().unwrap());
+    /// assert_eq!(map.rect(&half), Some((0, 0, 7, 15)));
+    /// ```
+    pub fn rect(&self, network: &IpNetwork) -> Option<(u64, u64, u64, u64)> {
+        if !self.space.is_subnet(network) {
+            return None;
+        }
+        let (first, cidr, _) = bounds(network);
+        let first = match network {
+            IpNetwork::V4(_) => IpAddr::V4((first as u32).into()),
+            IpNetwork::V6(_) => IpAddr::V6(first.into()),
+        };
+        let index = self.index(&first)?;
+        let spanned = self.cell_prefix().saturating_sub(cidr);
+        // Aligned runs of 4^k cells fill a square of side 2^k, which is
+        // the whole map of an order 64 IPv6 map
+        let k = u32::from(spanned / 2);
+        let mask = 1u64.checked_shl(k).map_or(u64::MAX, |side| side - 1);
+        let square = |index: u128| {
+            let (x, y) = index_to_xy(index, self.order);
+            (x & !mask, y & !mask)
+        };
+        let (x0, y0) = square(index);
+        let (x1, y1) = match spanned % 2 {
+            0 => (x0, y0),
+            _ => square(index + (1u128 << (2 * k))),
+        };
+        Some((x0.min(x1), y0.min(y1), x0.max(x1) + mask, y0.max(y1) + mask))
+    }
+}
+
+/// Returns the coordinates of an address on a Hilbert map of its whole
+/// family with `order` levels, where each cell is a /`2 * order` network.
+/// Fails with `InvalidNetwork` when the order is above 16 for IPv4 or 64
+/// for IPv6.
+/// ```
+/// use ipnetwork::hilbert::hilbert_xy;
+/// assert_eq!(hilbert_xy("192.0.2.1".parse().unwrap(), 4), Ok((15, 7)));
+/// ```
+pub fn hilbert_xy(addr: IpAddr, order: u8) -> Result<(u64, u64), Error> {
+    let space = match addr {
+        IpAddr::V4(_) => IpNetwork::V4(Ipv4Network { first: 0, cidr: 0 }),
+        IpAddr::V6(_) => IpNetwork::V6(Ipv6Network { first: 0, cidr: 0 }),
+    };
+    let map = HilbertMap::new(space, order)?;
+    Ok(map
+        .xy(&addr)
+        .expect("the whole family contains the address"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curve_round_trip() {
+        for order in [0, 1, 2, 3, 5].iter() {
+            let side = 1u64 << order;
+            let mut previous: Option<(u64, u64)> = None;
+            for index in 0..u128::from(side * side) {
+                let (x, y) = index_to_xy(index, *order);
+                assert!(x < side && y < side);
+                assert_eq!(xy_to_index(x, y, *order), index);
+                // consecutive cells are neighbours
+                if let Some((px, py)) = previous {
+                    assert_eq!(px.max(x) - px.min(x) + py.max(y) - py.min(y), 1);
+                }
+                previous = Some((x, y));
+            }
+        }
+        let last = u128::MAX;
+        let (x, y) = index_to_xy(last, 64);
+        assert_eq!(xy_to_index(x, y, 64), last);
+    }
+
+    #[test]
+    fn maps() {
+        let space = IpNetwork::V6("2001:db8::/32".parse().unwrap());
+        let map = HilbertMap::new(space, 8).unwrap();
+        assert_eq!(map.cell_prefix(), 48);
+        let addr: IpAddr = "2001:db8:1234::1".parse().unwrap();
+        let (x, y) = map.xy(&addr).unwrap();
+        assert_eq!(
+            map.cell(x, y),
+            Some(IpNetwork::V6("2001:db8:1234::/48".parse().unwrap()))
+        );
+        assert_eq!(map.xy(&"2001:db9::".parse().unwrap()), None);
+        assert_eq!(map.xy(&"10.0.0.1".parse().unwrap()), None);
+        assert_eq!(map.cell(256, 0), None);
+        assert_eq!(map.rect(&space), Some((0, 0, 255, 255)));
+        let host = IpNetwork::V6("2001:db8:1234::1/128".parse().unwrap());
+        assert_eq!(map.rect(&host), Some((x, y, x, y)));
+        assert_eq!(
+            map.rect(&IpNetwork::V6("2001:db9::/32".parse().unwrap())),
+            None
+        );
+        assert_eq!(HilbertMap::new(space, 49), Err(Error::InvalidNetwork));
+        assert_eq!(
+            hilbert_xy("::1".parse().unwrap(), 65),
+            Err(Error::InvalidNetwork)
+        );
+        let full = HilbertMap::new(IpNetwork::V4(Ipv4Network { first: 0, cidr: 0 }), 16).unwrap();
+        assert_eq!(
+            full.cell(0, 0),
+            Some(IpNetwork::V4("0.0.0.0/32".parse().unwrap()))
+        );
+        let whole = IpNetwork::V6("::/0".parse().unwrap());
+        let widest = HilbertMap::new(whole, 64).unwrap();
+        assert_eq!(widest.rect(&whole), Some((0, 0, u64::MAX, u64::MAX)));
+        let half = IpNetwork::V6("::/1".parse().unwrap());
+        assert_eq!(widest.rect(&half), Some((0, 0, u64::MAX >> 1, u64::MAX)));
+    }
+
+    #[test]
+    fn rectangles_cover_networks() {
+        let map = HilbertMap::new(IpNetwork::V4("10.0.0.0/8".parse().unwrap()), 3).unwrap();
+        for cidr in 8..=14u8 {
+            let network = IpNetwork::V4(Ipv4Network::new(10, 0, 0, 0, cidr).unwrap());
+            let (x0, y0, x1, y1) = map.rect(&network).unwrap();
+            let cells = u128::from((x1 - x0 + 1) * (y1 - y0 + 1));
+            // the rectangle holds exactly the cells of the network
+            assert_eq!(cells, 1 << (14 - cidr));
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    assert!(network.is_subnet(&map.cell(x, y).unwrap()));
+                }
+            }
+        }
+    }
+}
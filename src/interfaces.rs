@@ -0,0 +1,219 @@
+//! Networks configured on the interfaces of the local host. Needs the
+//! `interfaces` feature and is only implemented for Linux, other systems
+//! get an `Unsupported` error.
+use crate::IpNetwork;
+use std::io;
+use std::net::IpAddr;
+
+/// Address configured on an interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Configured {
+    addr: IpAddr,
+    network: IpNetwork,
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::Configured;
+    use crate::{IpNetwork, Ipv4Network, Ipv6Network};
+    use std::ffi::CStr;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+    const AF_INET: u16 = 2;
+    const AF_INET6: u16 = 10;
+
+    #[repr(C)]
+    struct SockAddr {
+        family: u16,
+    }
+
+    #[repr(C)]
+    struct SockAddrIn {
+        family: u16,
+        port: u16,
+        addr: [u8; 4],
+    }
+
+    #[repr(C)]
+    struct SockAddrIn6 {
+        family: u16,
+        port: u16,
+        flowinfo: u32,
+        addr: [u8; 16],
+        scope_id: u32,
+    }
+
+    #[repr(C)]
+    struct IfAddrs {
+        next: *mut IfAddrs,
+        name: *mut c_char,
+        flags: c_uint,
+        addr: *mut SockAddr,
+        netmask: *mut SockAddr,
+        broadcast: *mut SockAddr,
+        data: *mut c_void,
+    }
+
+    extern "C" {
+        fn getifaddrs(list: *mut *mut IfAddrs) -> c_int;
+        fn freeifaddrs(list: *mut IfAddrs);
+    }
+
+    /// Reads an address and its netmask, None for other families
+    unsafe fn read(addr: *const SockAddr, netmask: *const SockAddr) -> Option<(IpAddr, u8)> {
+        if addr.is_null() || netmask.is_null() {
+            return None;
+        }
+        match (*addr).family {
+            AF_INET => {
+                let addr = Ipv4Addr::from((*(addr as *const SockAddrIn)).addr);
+                let mask = u32::from_be_bytes((*(netmask as *const SockAddrIn)).addr);
+                Some((IpAddr::V4(addr), mask.count_ones() as u8))
+            }
+            AF_INET6 => {
+                let addr = Ipv6Addr::from((*(addr as *const SockAddrIn6)).addr);
+                let mask = u128::from_be_bytes((*(netmask as *const SockAddrIn6)).addr);
+                Some((IpAddr::V6(addr), mask.count_ones() as u8))
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn configured() -> io::Result<Vec<(String, Configured)>> {
+        let mut list = std::ptr::null_mut();
+        if unsafe { getifaddrs(&mut list) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut found = Vec::new();
+        let mut entry = list;
+        while !entry.is_null() {
+            let current = unsafe { &*entry };
+            if let Some((addr, cidr)) = unsafe { read(current.addr, current.netmask) } {
+                let name = unsafe { CStr::from_ptr(current.name) }
+                    .to_string_lossy()
+                    .into_owned();
+                let network = match addr {
+                    IpAddr::V4(v4) => IpNetwork::V4(Ipv4Network {
+                        first: u32::from(v4) & !Ipv4Network::cidr_to_hostmask(cidr),
+                        cidr,
+                    }),
+                    IpAddr::V6(v6) => IpNetwork::V6(Ipv6Network {
+                        first: u128::from(v6) & !Ipv6Network::cidr_to_hostmask(cidr),
+                        cidr,
+                    }),
+                };
+                found.push((name, Configured { addr, network }));
+            }
+            entry = current.next;
+        }
+        unsafe { freeifaddrs(list) };
+        Ok(found)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use super::Configured;
+    use std::io;
+
+    pub(super) fn configured() -> io::Result<Vec<(String, Configured)>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "interface enumeration is only implemented for Linux",
+        ))
+    }
+}
+
+/// Returns the name and network of every address configured on the local
+/// interfaces. An interface with several addresses appears once per
+/// address.
+pub fn interfaces() -> io::Result<Vec<(String, IpNetwork)>> {
+    Ok(sys::configured()?
+        .into_iter()
+        .map(|(name, configured)| (name, configured.network))
+        .collect())
+}
+
+/// Returns true when the address is configured on one of the local
+/// interfaces
+pub fn is_local(addr: IpAddr) -> io::Result<bool> {
+    Ok(sys::configured()?
+        .iter()
+        .any(|(_, configured)| configured.addr == addr))
+}
+
+/// Returns the name of the interface whose network contains the address,
+/// the most specific one when several do
+pub fn interface_for(addr: IpAddr) -> io::Result<Option<String>> {
+    let interfaces = interfaces()?;
+    Ok(most_specific(&interfaces, addr).map(str::to_string))
+}
+
+fn prefix_len(network: &IpNetwork) -> u8 {
+    match network {
+        IpNetwork::V4(n) => n.cidr,
+        IpNetwork::V6(n) => n.cidr,
+    }
+}
+
+fn most_specific(interfaces: &[(String, IpNetwork)], addr: IpAddr) -> Option<&str> {
+    interfaces
+        .iter()
+        .filter(|(_, network)| network.contains(&addr))
+        .max_by_key(|(_, network)| prefix_len(network))
+        .map(|(name, _)| name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ipv4Network, Ipv6Network};
+
+    #[test]
+    fn pick_most_specific() {
+        let interfaces = vec![
+            (
+                "eth0".to_string(),
+                IpNetwork::V4(Ipv4Network::new(10, 0, 0, 0, 8).unwrap()),
+            ),
+            (
+                "eth1".to_string(),
+                IpNetwork::V4(Ipv4Network::new(10, 1, 0, 0, 16).unwrap()),
+            ),
+            (
+                "lo".to_string(),
+                IpNetwork::V6(Ipv6Network::new(1, 128).unwrap()),
+            ),
+        ];
+        assert_eq!(
+            most_specific(&interfaces, "10.1.2.3".parse().unwrap()),
+            Some("eth1")
+        );
+        assert_eq!(
+            most_specific(&interfaces, "10.2.2.3".parse().unwrap()),
+            Some("eth0")
+        );
+        assert_eq!(
+            most_specific(&interfaces, "::1".parse().unwrap()),
+            Some("lo")
+        );
+        assert_eq!(
+            most_specific(&interfaces, "192.0.2.1".parse().unwrap()),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn loopback_is_local() {
+        let found = interfaces().unwrap();
+        let loopback = "127.0.0.1".parse().unwrap();
+        if found.iter().any(|(_, network)| network.contains(&loopback)) {
+            assert!(is_local(loopback).unwrap());
+            assert!(interface_for(loopback).unwrap().is_some());
+        }
+        assert!(!is_local("192.0.2.254".parse().unwrap()).unwrap());
+    }
+}
@@ -0,0 +1,190 @@
+//! A small JSON reader for the command output and published feeds the
+//! crate reads, fails with `NetworkParseError` on malformed input
+use crate::Error;
+
+/// A JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    /// Numbers are kept as written
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Field of an object
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Nesting allowed in the JSON read
+const MAX_DEPTH: usize = 32;
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        self.skip_whitespace();
+        match self.bytes.get(self.at) {
+            Some(&b) if b == byte => {
+                self.at += 1;
+                Ok(())
+            }
+            _ => Err(Error::NetworkParseError),
+        }
+    }
+
+    fn literal(&mut self, text: &[u8], value: Json) -> Result<Json, Error> {
+        match self.bytes[self.at..].starts_with(text) {
+            true => {
+                self.at += text.len();
+                Ok(value)
+            }
+            false => Err(Error::NetworkParseError),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut s = String::new();
+        loop {
+            let rest = &self.bytes[self.at..];
+            let end = rest
+                .iter()
+                .position(|&b| b == b'"' || b == b'\\')
+                .ok_or(Error::NetworkParseError)?;
+            s.push_str(std::str::from_utf8(&rest[..end]).map_err(|_| Error::NetworkParseError)?);
+            self.at += end + 1;
+            if rest[end] == b'"' {
+                return Ok(s);
+            }
+            let escaped = *self.bytes.get(self.at).ok_or(Error::NetworkParseError)?;
+            self.at += 1;
+            match escaped {
+                b'"' | b'\\' | b'/' => s.push(char::from(escaped)),
+                b'n' => s.push('\n'),
+                b't' => s.push('\t'),
+                b'r' => s.push('\r'),
+                b'b' => s.push('\u{8}'),
+                b'f' => s.push('\u{c}'),
+                b'u' => {
+                    let hex = self
+                        .bytes
+                        .get(self.at..self.at + 4)
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .ok_or(Error::NetworkParseError)?;
+                    self.at += 4;
+                    // Surrogate pairs do not occur in the text read, they are replaced
+                    s.push(char::from_u32(hex).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                _ => return Err(Error::NetworkParseError),
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::NetworkParseError);
+        }
+        self.skip_whitespace();
+        match self.bytes.get(self.at) {
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'n') => self.literal(b"null", Json::Null),
+            Some(b't') => self.literal(b"true", Json::Bool(true)),
+            Some(b'f') => self.literal(b"false", Json::Bool(false)),
+            Some(b'[') => {
+                self.at += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.bytes.get(self.at) == Some(&b']') {
+                    self.at += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.bytes.get(self.at) {
+                        Some(b',') => self.at += 1,
+                        Some(b']') => {
+                            self.at += 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(Error::NetworkParseError),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.at += 1;
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.bytes.get(self.at) == Some(&b'}') {
+                    self.at += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.bytes.get(self.at) {
+                        Some(b',') => self.at += 1,
+                        Some(b'}') => {
+                            self.at += 1;
+                            return Ok(Json::Object(fields));
+                        }
+                        _ => return Err(Error::NetworkParseError),
+                    }
+                }
+            }
+            Some(b'-') | Some(b'0'..=b'9') => {
+                let len = self.bytes[self.at..]
+                    .iter()
+                    .take_while(|&&b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+                    .count();
+                let number = std::str::from_utf8(&self.bytes[self.at..self.at + len])
+                    .expect("digits are ASCII");
+                self.at += len;
+                Ok(Json::Number(number.to_string()))
+            }
+            _ => Err(Error::NetworkParseError),
+        }
+    }
+}
+
+/// Reads a whole JSON text
+pub(crate) fn parse(text: &str) -> Result<Json, Error> {
+    let mut parser = JsonParser {
+        bytes: text.as_bytes(),
+        at: 0,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    match parser.at == text.len() {
+        true => Ok(value),
+        false => Err(Error::NetworkParseError),
+    }
+}
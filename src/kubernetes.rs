@@ -0,0 +1,237 @@
+//! Pod and service CIDR planning for Kubernetes clusters
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+
+/// Largest difference between the cluster and node prefix lengths that the
+/// node IPAM controller accepts for IPv6
+const MAX_V6_MASK_DIFF: u8 = 16;
+
+/// Result of `plan_cluster`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterPlan {
+    /// Prefix length of the pod CIDR given to each node, one per cluster CIDR
+    pub node_mask_sizes: Vec<u8>,
+    /// Pod CIDRs of every node, one per cluster CIDR
+    pub node_cidrs: Vec<Vec<IpNetwork>>,
+    /// Number of nodes the cluster CIDRs have room for
+    pub max_nodes: u128,
+    /// Number of pods a node can run, half of the smallest pod CIDR so
+    /// addresses are not reused right after a pod is deleted
+    pub max_pods_per_node: u128,
+}
+
+/// Smallest prefix of `bits` bits holding twice `pods` addresses
+fn pod_prefix(bits: u8, pods: u32) -> Option<u8> {
+    let addresses = (u128::from(pods.max(1)) * 2).next_power_of_two();
+    bits.checked_sub(addresses.trailing_zeros() as u8)
+}
+
+/// Returns the pod CIDR prefix length a node needs to run `pods_per_node`
+/// pods out of `cluster_cidr`, or None if the cluster CIDR is too small.
+///
+/// IPv4 nodes get twice the requested pods rounded up to a power of two,
+/// so 110 pods need a /24. IPv6 nodes get a /64 when the cluster prefix
+/// allows it.
+/// ```
+/// use ipnetwork::kubernetes::node_mask_size;
+/// use ipnetwork::IpNetwork;
+/// let cluster = IpNetwork::V4("10.0.0.0/14".parse().unwrap());
+/// assert_eq!(node_mask_size(&cluster, 110), Some(24));
+/// ```
+pub fn node_mask_size(cluster_cidr: &IpNetwork, pods_per_node: u32) -> Option<u8> {
+    let mask = match cluster_cidr {
+        IpNetwork::V4(_) => pod_prefix(32, pods_per_node)?,
+        IpNetwork::V6(n) if n.cidr <= 64 => 64.min(n.cidr + MAX_V6_MASK_DIFF),
+        IpNetwork::V6(n) => pod_prefix(128, pods_per_node)?.min(n.cidr + MAX_V6_MASK_DIFF),
+    };
+    let cluster_mask = match cluster_cidr {
+        IpNetwork::V4(n) => n.cidr,
+        IpNetwork::V6(n) => n.cidr,
+    };
+    match mask >= cluster_mask {
+        true => Some(mask),
+        false => None,
+    }
+}
+
+/// Number of /`mask` node CIDRs that fit in the cluster CIDR
+fn node_capacity(cluster_cidr: &IpNetwork, mask: u8) -> u128 {
+    let cluster_mask = match cluster_cidr {
+        IpNetwork::V4(n) => n.cidr,
+        IpNetwork::V6(n) => n.cidr,
+    };
+    1u128
+        .checked_shl(u32::from(mask - cluster_mask))
+        .unwrap_or(u128::MAX)
+}
+
+fn node_cidrs(cluster_cidr: &IpNetwork, mask: u8, nodes: usize) -> Vec<IpNetwork> {
+    match cluster_cidr {
+        IpNetwork::V4(n) => n
+            .into_subnets(mask)
+            .take(nodes)
+            .map(IpNetwork::V4)
+            .collect(),
+        IpNetwork::V6(n) => n
+            .into_subnets(mask)
+            .take(nodes)
+            .map(IpNetwork::V6)
+            .collect(),
+    }
+}
+
+/// Checks that no service CIDR collides with a cluster CIDR or another
+/// service CIDR, and that each is within the size the API server accepts
+/// (/12 for IPv4, /108 for IPv6).
+pub fn check_service_cidrs(
+    service_cidrs: &[IpNetwork],
+    cluster_cidrs: &[IpNetwork],
+) -> Result<(), Error> {
+    for (index, service) in service_cidrs.iter().enumerate() {
+        let too_large = match service {
+            IpNetwork::V4(Ipv4Network { cidr, .. }) => *cidr < 12,
+            IpNetwork::V6(Ipv6Network { cidr, .. }) => *cidr < 108,
+        };
+        if too_large {
+            return Err(Error::InvalidNetwork);
+        }
+        let mut others = cluster_cidrs.iter().chain(&service_cidrs[index + 1..]);
+        if others.any(|other| other.overlaps(service)) {
+            return Err(Error::CidrOverlap);
+        }
+    }
+    Ok(())
+}
+
+/// Plans the pod CIDRs of `nodes` nodes running `pods_per_node` pods each.
+///
+/// Every node gets one pod CIDR out of each cluster CIDR, so a dual-stack
+/// cluster passes one IPv4 and one IPv6 cluster CIDR. Fails with
+/// `CidrOverlap` when cluster or service CIDRs collide and with
+/// `NotEnoughAddresses` when the cluster CIDRs cannot hold the nodes.
+/// ```
+/// use ipnetwork::kubernetes::plan_cluster;
+/// use ipnetwork::IpNetwork;
+/// let cluster = [IpNetwork::V4("10.0.0.0/16".parse().unwrap())];
+/// let services = [IpNetwork::V4("10.96.0.0/12".parse().unwrap())];
+/// let plan = plan_cluster(&cluster, &services, 3, 110).unwrap();
+/// assert_eq!(plan.max_nodes, 256);
+/// assert_eq!(plan.node_cidrs[2][0], IpNetwork::V4("10.0.2.0/24".parse().unwrap()));
+/// ```
+pub fn plan_cluster(
+    cluster_cidrs: &[IpNetwork],
+    service_cidrs: &[IpNetwork],
+    nodes: usize,
+    pods_per_node: u32,
+) -> Result<ClusterPlan, Error> {
+    for (index, cluster) in cluster_cidrs.iter().enumerate() {
+        if cluster_cidrs[index + 1..]
+            .iter()
+            .any(|other| other.overlaps(cluster))
+        {
+            return Err(Error::CidrOverlap);
+        }
+    }
+    check_service_cidrs(service_cidrs, cluster_cidrs)?;
+
+    let mut node_mask_sizes = Vec::with_capacity(cluster_cidrs.len());
+    for cluster in cluster_cidrs {
+        node_mask_sizes
+            .push(node_mask_size(cluster, pods_per_node).ok_or(Error::NotEnoughAddresses)?);
+    }
+    let max_nodes = cluster_cidrs
+        .iter()
+        .zip(&node_mask_sizes)
+        .map(|(cluster, mask)| node_capacity(cluster, *mask))
+        .min()
+        .unwrap_or(0);
+    if nodes as u128 > max_nodes {
+        return Err(Error::NotEnoughAddresses);
+    }
+    let max_pods_per_node = cluster_cidrs
+        .iter()
+        .zip(&node_mask_sizes)
+        .map(|(cluster, mask)| match cluster {
+            IpNetwork::V4(_) => 1u128 << (32 - mask - 1),
+            IpNetwork::V6(_) => 1u128
+                .checked_shl(u32::from(128 - mask - 1))
+                .unwrap_or(u128::MAX),
+        })
+        .min()
+        .unwrap_or(0);
+
+    let per_cluster: Vec<Vec<IpNetwork>> = cluster_cidrs
+        .iter()
+        .zip(&node_mask_sizes)
+        .map(|(cluster, mask)| node_cidrs(cluster, *mask, nodes))
+        .collect();
+    let node_cidrs = (0..nodes)
+        .map(|node| per_cluster.iter().map(|cidrs| cidrs[node]).collect())
+        .collect();
+    Ok(ClusterPlan {
+        node_mask_sizes,
+        node_cidrs,
+        max_nodes,
+        max_pods_per_node,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> IpNetwork {
+        IpNetwork::V4(s.parse().unwrap())
+    }
+
+    fn v6(first: u128, cidr: u8) -> IpNetwork {
+        IpNetwork::V6(Ipv6Network::new(first, cidr).unwrap())
+    }
+
+    #[test]
+    fn mask_sizes() {
+        assert_eq!(node_mask_size(&v4("10.0.0.0/8"), 110), Some(24));
+        assert_eq!(node_mask_size(&v4("10.0.0.0/8"), 128), Some(24));
+        assert_eq!(node_mask_size(&v4("10.0.0.0/8"), 129), Some(23));
+        assert_eq!(node_mask_size(&v4("10.0.0.0/25"), 110), None);
+        assert_eq!(node_mask_size(&v6(0xfd00 << 112, 48), 110), Some(64));
+        assert_eq!(node_mask_size(&v6(0xfd00 << 112, 32), 110), Some(48));
+        assert_eq!(node_mask_size(&v6(0xfd00 << 112, 108), 110), Some(120));
+    }
+
+    #[test]
+    fn dual_stack() {
+        let cluster = [v4("10.244.0.0/16"), v6(0xfd00 << 112, 56)];
+        let plan = plan_cluster(&cluster, &[v4("10.96.0.0/12")], 2, 110).unwrap();
+        assert_eq!(plan.node_mask_sizes, vec![24, 64]);
+        assert_eq!(plan.max_nodes, 256);
+        assert_eq!(plan.max_pods_per_node, 128);
+        assert_eq!(
+            plan.node_cidrs[1],
+            vec![v4("10.244.1.0/24"), v6((0xfd00 << 112) | (1 << 64), 64)]
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let cluster = [v4("10.244.0.0/22")];
+        assert_eq!(
+            plan_cluster(&cluster, &[], 5, 110),
+            Err(Error::NotEnoughAddresses)
+        );
+        assert_eq!(
+            plan_cluster(&cluster, &[v4("10.244.0.0/16")], 1, 110),
+            Err(Error::CidrOverlap)
+        );
+        assert_eq!(
+            check_service_cidrs(&[v4("10.0.0.0/8")], &[]),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            plan_cluster(&cluster, &[], 4, 110)
+                .unwrap()
+                .node_cidrs
+                .len(),
+            4
+        );
+    }
+}
@@ -1,27 +1,101 @@
+//! IPv4 and IPv6 networks, their hosts and subnets, and the address
+//! arithmetic built on them.
+//!
+//! # Panics
+//!
+//! Parsing, constructors, iterators and checkpoints never panic, whatever
+//! the input: invalid input is reported through `Error`, and arithmetic
+//! that would leave the address space ends iterators or returns None. The
+//! exceptions are documented on the functions, like `hostcount` for a /0,
+//! and have `checked_` or `saturating_` variants. Networks built by hand,
+//! through the public fields or `new_unchecked`, must keep the prefix
+//! length within the address width.
 use std::cmp::Ordering;
-use std::convert::From;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::convert::{From, TryFrom};
+use std::fmt;
+use std::iter::{Filter, FusedIterator};
+use std::net::{IpAddr, Ipv6Addr};
 use std::result::Result;
 use std::str::FromStr;
-use std::u8;
+
+use crate::bits::AddressBits;
+
+pub mod acl;
+pub mod aggregate;
+pub mod analytics;
+pub mod bits;
+pub mod cloud;
+pub mod compact;
+pub mod diagnostic;
+pub mod dual;
+pub mod extract;
+pub mod filter;
+pub mod hashing;
+pub mod hilbert;
+#[cfg(feature = "interfaces")]
+pub mod interfaces;
+mod json;
+pub mod kubernetes;
+pub mod link;
+pub mod mapping;
+#[cfg(feature = "mmdb")]
+pub mod mmdb;
+pub mod nptv6;
+pub mod parse;
+pub mod plan;
+pub mod proxy;
+pub mod python;
+pub mod radius;
+pub mod random;
+pub mod reconcile;
+pub mod reverse;
+pub mod route;
+pub mod selection;
+pub mod sixrd;
+pub mod spf;
+pub mod terraform;
+pub mod validate;
+pub mod vendor;
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     InvalidNetwork,
     CidrMissMatch,
     NetworkParseError,
+    InvalidCheckpoint,
+    CidrOverlap,
+    NotEnoughAddresses,
+    InvalidNetmask,
+    RangeNotAligned,
 }
-#[derive(Debug, PartialEq)]
+/// A network of either family. Sorting puts every IPv4 network before the
+/// IPv6 networks, then orders by address and prefix length.
+/// ```
+/// use ipnetwork::IpNetwork;
+/// let mut networks = vec![
+///     IpNetwork::V6("::/0".parse().unwrap()),
+///     IpNetwork::V4("10.0.0.0/8".parse().unwrap()),
+///     IpNetwork::V4("10.0.0.0/16".parse().unwrap()),
+/// ];
+/// networks.sort();
+/// assert_eq!(networks[0], IpNetwork::V4("10.0.0.0/8".parse().unwrap()));
+/// assert_eq!(networks[2], IpNetwork::V6("::/0".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum IpNetwork {
     V4(Ipv4Network),
     V6(Ipv6Network),
 }
 
-#[derive(Debug, Eq)]
-pub struct Ipv6Network {
-    pub first: u128,
+/// A network of either family, made of the first address as an integer
+/// and the prefix length. Use it through `Ipv4Network` and `Ipv6Network`,
+/// or generically over `AddressBits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Network<B: AddressBits> {
+    pub first: B,
     pub cidr: u8,
 }
+
 /// An IPv4 network. The network is represented by
 /// Network consists of a network address and cidr
 /// the network address is represented as is represented
@@ -31,11 +105,37 @@ pub struct Ipv6Network {
 /// use ipnetwork::Ipv4Network;
 /// let network = "1.1.1.0/24".parse();
 /// assert_eq!(Ok(Ipv4Network{first: 16843008, cidr: 24}), network)
-#[derive(Debug, Eq)]
-pub struct Ipv4Network {
-    pub first: u32,
-    pub cidr: u8,
+pub type Ipv4Network = Network<u32>;
+
+/// An IPv6 network, the network address is represented by u128
+/// ```
+/// use ipnetwork::Ipv6Network;
+/// let network: Ipv6Network = "2001:db8::/32".parse().unwrap();
+/// assert_eq!(network, Ipv6Network::new(0x2001_0db8 << 96, 32).unwrap());
+/// ```
+pub type Ipv6Network = Network<u128>;
+
+/// What `walk` does with a network of the subdivision tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    /// Continue with the two halves of the network
+    Descend,
+    /// Skip the network and everything below it
+    Prune,
+    /// Return the network and do not descend below it
+    Match,
 }
+
+/// Where an address lies relative to a network, see `position_of`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AddressPosition {
+    /// The address is lower than the first address of the network
+    Before,
+    Inside,
+    /// The address is higher than the last address of the network
+    After,
+}
+
 /// Iterator to iterate over subnets of a network
 /// ```
 /// use ipnetwork::Ipv4Network;
@@ -44,79 +144,694 @@ pub struct Ipv4Network {
 /// assert_eq!(subnets.len(), 2);
 /// ```
 #[derive(Debug)]
-pub struct NetworkV4Iterator {
+pub struct Subnets<B: AddressBits> {
     /// The current network address
-    current: u32,
+    current: B,
     /// Upper bounds
-    max: u32,
+    max: B,
     /// How many addresses should the new network have
-    stepping: u32,
+    stepping: B,
     /// Cidr of the new network
     cidr: u8,
+    /// Set once the last network has been returned
+    done: bool,
+}
+
+pub type NetworkV4Iterator = Subnets<u32>;
+pub type NetworkV6Iterator = Subnets<u128>;
+
+/// Iterator over every address of a network
+/// ```
+/// use ipnetwork::Ipv4Network;
+/// let network: Ipv4Network = "1.0.0.0/30".parse().unwrap();
+/// assert_eq!(network.into_hosts().count(), 4);
+/// ```
+#[derive(Debug)]
+pub struct Hosts<B: AddressBits> {
+    /// The next address to return
+    current: B,
+    /// Last address of the network
+    max: B,
+    /// Set once the last address has been returned
+    done: bool,
 }
 
+pub type HostIterator = Hosts<u32>;
+pub type HostV6Iterator = Hosts<u128>;
+
+/// Iterator over every address of a network of either family, see
+/// `IpNetwork::hosts`
 #[derive(Debug)]
-pub struct NetworkV6Iterator {
-    /// The current network address
+pub enum IpHostIterator {
+    V4(HostIterator),
+    V6(HostV6Iterator),
+}
+
+/// Iterator over the addresses of a network that are not covered by any of
+/// the excluded networks. Excluded blocks are skipped as a whole.
+/// ```
+/// use ipnetwork::Ipv4Network;
+/// use std::net::Ipv4Addr;
+/// let network: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+/// let excluded: Ipv4Network = "10.0.0.0/25".parse().unwrap();
+/// let mut hosts = network.into_hosts_excluding(&[excluded]);
+/// assert_eq!(hosts.next(), Some(Ipv4Addr::new(10, 0, 0, 128)));
+/// ```
+#[derive(Debug)]
+pub struct ExcludingHosts<B: AddressBits> {
+    hosts: Hosts<B>,
+    /// Disjoint excluded address ranges sorted by their first address
+    excluded: Vec<(B, B)>,
+    /// Index of the first range that may still affect the iteration
+    next_range: usize,
+    /// One past the index of the last range that may still affect the
+    /// iteration from the back
+    back_range: usize,
+}
+
+pub type ExcludingHostIterator = ExcludingHosts<u32>;
+
+/// How `InterleavedHosts` picks the network to take the next address from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interleave {
+    /// One address from each network in turn
+    RoundRobin,
+    /// Larger networks are visited more often, so all networks finish at
+    /// about the same time
+    Proportional,
+}
+
+/// Iterator yielding the addresses of several networks interleaved, so
+/// consecutive addresses are spread across the networks
+/// ```
+/// use ipnetwork::{Interleave, InterleavedHostIterator, Ipv4Network};
+/// use std::net::Ipv4Addr;
+/// let a: Ipv4Network = "10.0.0.0/31".parse().unwrap();
+/// let b: Ipv4Network = "192.168.0.0/31".parse().unwrap();
+/// let hosts: Vec<Ipv4Addr> = InterleavedHostIterator::new(&[a, b], Interleave::RoundRobin).collect();
+/// assert_eq!(hosts[1], Ipv4Addr::new(192, 168, 0, 0));
+/// ```
+#[derive(Debug)]
+pub struct InterleavedHosts<B: AddressBits> {
+    hosts: Vec<Hosts<B>>,
+    /// Addresses already returned for each network
+    returned: Vec<u64>,
+    /// Total number of addresses of each network, saturated for an IPv6 /0
+    totals: Vec<u128>,
+    mode: Interleave,
+    /// Network to try first in round robin mode
+    position: usize,
+}
+
+pub type InterleavedHostIterator = InterleavedHosts<u32>;
+pub type InterleavedHostV6Iterator = InterleavedHosts<u128>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckpointKind {
+    HostV4 = 1,
+    SubnetV4 = 2,
+    SubnetV6 = 3,
+    HostV6 = 4,
+}
+
+impl CheckpointKind {
+    fn hosts(bits: u8) -> CheckpointKind {
+        match bits {
+            32 => CheckpointKind::HostV4,
+            _ => CheckpointKind::HostV6,
+        }
+    }
+    fn subnets(bits: u8) -> CheckpointKind {
+        match bits {
+            32 => CheckpointKind::SubnetV4,
+            _ => CheckpointKind::SubnetV6,
+        }
+    }
+}
+
+/// Position of a host or subnet iterator.
+///
+/// A checkpoint holds everything needed to rebuild the iterator, so a long
+/// running scan can store it and continue after a restart.
+/// ```
+/// use ipnetwork::{Checkpoint, HostIterator, Ipv4Network};
+/// let network: Ipv4Network = "1.0.0.0/30".parse().unwrap();
+/// let mut hosts = network.into_hosts();
+/// hosts.next();
+/// let token = hosts.checkpoint().to_bytes();
+///
+/// let checkpoint = Checkpoint::from_bytes(&token).unwrap();
+/// let resumed = HostIterator::resume(&checkpoint).unwrap();
+/// assert_eq!(resumed.count(), 3);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    kind: CheckpointKind,
     current: u128,
-    /// Upper bounds
     max: u128,
-    /// How many addresses should the new network have
-    stepping: u128,
-    /// Cidr of the new network
     cidr: u8,
+    done: bool,
 }
 
-#[derive(Debug)]
-pub struct HostIterator {
-    current: u32,
-    max: u32,
+impl IpNetwork {
+    /// Parses a network of either family in CIDR notation from bytes,
+    /// without allocating
+    /// ```
+    /// use ipnetwork::IpNetwork;
+    /// let network = IpNetwork::parse_ascii(b"192.0.2.0/24").unwrap();
+    /// assert_eq!(network, IpNetwork::V4("192.0.2.0/24".parse().unwrap()));
+    /// ```
+    pub fn parse_ascii(bytes: &[u8]) -> Result<IpNetwork, Error> {
+        match parse::is_ipv6(bytes) {
+            true => Ipv6Network::parse_ascii(bytes).map(IpNetwork::V6),
+            false => Ipv4Network::parse_ascii(bytes).map(IpNetwork::V4),
+        }
+    }
+    /// Parses a network like `parse_ascii`, also accepting IPv4 shorthand
+    /// that leaves out trailing zero octets, see `Ipv4Network::parse_relaxed`
+    pub fn parse_relaxed(bytes: &[u8]) -> Result<IpNetwork, Error> {
+        match parse::is_ipv6(bytes) {
+            true => Ipv6Network::parse_ascii(bytes).map(IpNetwork::V6),
+            false => Ipv4Network::parse_relaxed(bytes).map(IpNetwork::V4),
+        }
+    }
+    /// Writes the network in CIDR notation without allocating
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        match self {
+            IpNetwork::V4(network) => network.write_to(w),
+            IpNetwork::V6(network) => network.write_to(w),
+        }
+    }
+    /// Iterates over every address of the network
+    /// ```
+    /// use ipnetwork::IpNetwork;
+    /// use std::net::IpAddr;
+    /// let network = IpNetwork::V6("2001:db8::/127".parse().unwrap());
+    /// let hosts: Vec<IpAddr> = network.hosts().collect();
+    /// assert_eq!(hosts[1], "2001:db8::1".parse::<IpAddr>().unwrap());
+    /// ```
+    pub fn hosts(&self) -> IpHostIterator {
+        match self {
+            IpNetwork::V4(network) => IpHostIterator::V4(network.into_hosts()),
+            IpNetwork::V6(network) => IpHostIterator::V6(network.into_hosts()),
+        }
+    }
+    /// Returns true when both networks share at least one address
+    pub fn overlaps(&self, other: &IpNetwork) -> bool {
+        match (self, other) {
+            (IpNetwork::V4(a), IpNetwork::V4(b)) => a.first() <= b.last() && b.first() <= a.last(),
+            (IpNetwork::V6(a), IpNetwork::V6(b)) => a.first() <= b.last() && b.first() <= a.last(),
+            _ => false,
+        }
+    }
+    /// Returns true when the address is part of the network. Addresses of
+    /// the other family are never part of it.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self, addr) {
+            (IpNetwork::V4(n), IpAddr::V4(addr)) => n.contains(addr),
+            (IpNetwork::V6(n), IpAddr::V6(addr)) => n.contains(addr),
+            _ => false,
+        }
+    }
+    /// Returns true when `other` lies within this network
+    pub fn is_subnet(&self, other: &IpNetwork) -> bool {
+        match (self, other) {
+            (IpNetwork::V4(a), IpNetwork::V4(b)) => a.is_subnet(b),
+            (IpNetwork::V6(a), IpNetwork::V6(b)) => a.is_subnet(b),
+            _ => false,
+        }
+    }
+    /// Aggregates the networks of both families like `Network::aggregate`,
+    /// IPv4 networks first
+    pub fn aggregate(networks: &[IpNetwork]) -> Vec<IpNetwork> {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        for network in networks {
+            match network {
+                IpNetwork::V4(n) => v4.push(*n),
+                IpNetwork::V6(n) => v6.push(*n),
+            }
+        }
+        let v4 = Ipv4Network::aggregate(&v4).into_iter().map(IpNetwork::V4);
+        let v6 = Ipv6Network::aggregate(&v6).into_iter().map(IpNetwork::V6);
+        v4.chain(v6).collect()
+    }
 }
 
-impl Ipv4Network {
-    pub const MAX_NETMASK: u32 = u32::MAX;
+impl<B: AddressBits> Network<B> {
+    pub const MAX_NETMASK: B = B::MAX;
 
-    /// Creates a new IPv4 Network
-    pub fn new(a: u8, b: u8, c: u8, d: u8, cidr: u8) -> Result<Ipv4Network, Error> {
-        let first = u32::from_be_bytes([a, b, c, d]);
-        match Ipv4Network::is_valid(first, cidr) {
-            true => Ok(Ipv4Network { first, cidr }),
-            false => Err(Error::InvalidNetwork),
+    /// Writes the network in CIDR notation without allocating
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let network: Ipv4Network = "10.0.0.0/8".parse().unwrap();
+    /// let mut out = String::with_capacity(64);
+    /// network.write_to(&mut out).unwrap();
+    /// assert_eq!(out, "10.0.0.0/8");
+    /// ```
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        write!(w, "{}/{}", self.first(), self.cidr)
+    }
+
+    /// Creates a network from its first address and prefix length without
+    /// validating them, for input known to be valid like a snapshot this
+    /// crate wrote. The checks of `new` only run in debug builds, an
+    /// invalid network gives wrong answers instead of an error.
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// let network = Ipv6Network::new_unchecked(0x2001_0db8 << 96, 32);
+    /// assert_eq!(network, "2001:db8::/32".parse().unwrap());
+    /// ```
+    #[inline]
+    pub fn new_unchecked(first: B, cidr: u8) -> Network<B> {
+        debug_assert!(
+            Network::<B>::is_valid(first, cidr),
+            "invalid network {:?}/{}",
+            first,
+            cidr
+        );
+        Network { first, cidr }
+    }
+
+    /// Like `new_unchecked`, from the first address
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// use std::net::Ipv4Addr;
+    /// let network = Ipv4Network::from_parts_unchecked(Ipv4Addr::new(10, 0, 0, 0), 8);
+    /// assert_eq!(network, "10.0.0.0/8".parse().unwrap());
+    /// ```
+    #[inline]
+    pub fn from_parts_unchecked(addr: B::Addr, cidr: u8) -> Network<B> {
+        Network::new_unchecked(B::from_addr(addr), cidr)
+    }
+
+    /// Creates the network an interface with the given address and netmask
+    /// is attached to, host bits of the address are cleared. Fails with
+    /// `InvalidNetmask` when the mask bits are not contiguous.
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// use std::net::Ipv4Addr;
+    /// let network = Ipv4Network::with_netmask(
+    ///     Ipv4Addr::new(192, 168, 1, 10),
+    ///     Ipv4Addr::new(255, 255, 255, 0),
+    /// );
+    /// assert_eq!(network, "192.168.1.0/24".parse());
+    /// ```
+    pub fn with_netmask(addr: B::Addr, netmask: B::Addr) -> Result<Network<B>, Error> {
+        let mask = B::from_addr(netmask);
+        let contiguous = match (!mask).checked_add(B::ONE) {
+            Some(host_count) => host_count & !mask == B::ZERO,
+            None => true,
+        };
+        if !contiguous {
+            return Err(Error::InvalidNetmask);
+        }
+        Ok(Network {
+            first: B::from_addr(addr) & mask,
+            cidr: mask.count_ones() as u8,
+        })
+    }
+
+    /// Creates the network that covers exactly the addresses from `first`
+    /// to `last`. Fails with `RangeNotAligned` when no single network does,
+    /// `summarize_address_range` covers such ranges with several networks.
+    /// ```
+    /// use ipnetwork::{Error, Ipv4Network};
+    /// use std::net::Ipv4Addr;
+    /// let network = Ipv4Network::from_range(Ipv4Addr::new(10, 0, 0, 0), Ipv4Addr::new(10, 0, 3, 255));
+    /// assert_eq!(network, "10.0.0.0/22".parse());
+    /// let network = Ipv4Network::from_range(Ipv4Addr::new(10, 0, 1, 0), Ipv4Addr::new(10, 0, 4, 255));
+    /// assert_eq!(network, Err(Error::RangeNotAligned));
+    /// ```
+    pub fn from_range(first: B::Addr, last: B::Addr) -> Result<Network<B>, Error> {
+        let (first, last) = (B::from_addr(first), B::from_addr(last));
+        if first > last {
+            return Err(Error::InvalidNetwork);
+        }
+        let hostmask = last - first;
+        let power_of_two = match hostmask.checked_add(B::ONE) {
+            Some(size) => size & hostmask == B::ZERO,
+            None => true,
+        };
+        let cidr = B::BITS - hostmask.count_ones() as u8;
+        match power_of_two && Network::<B>::is_valid(first, cidr) {
+            true => Ok(Network { first, cidr }),
+            false => Err(Error::RangeNotAligned),
+        }
+    }
+
+    /// Returns the smallest list of networks covering exactly the addresses
+    /// from `first` to `last`, in address order
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// use std::net::Ipv4Addr;
+    /// let networks = Ipv4Network::summarize_address_range(
+    ///     Ipv4Addr::new(10, 0, 0, 1),
+    ///     Ipv4Addr::new(10, 0, 0, 6),
+    /// );
+    /// let expected: Vec<Ipv4Network> = ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]
+    ///     .iter()
+    ///     .map(|n| n.parse().unwrap())
+    ///     .collect();
+    /// assert_eq!(networks, expected);
+    /// ```
+    pub fn summarize_address_range(first: B::Addr, last: B::Addr) -> Vec<Network<B>> {
+        let last = B::from_addr(last);
+        let mut networks = Vec::new();
+        let mut current = Some(B::from_addr(first)).filter(|first| *first <= last);
+        while let Some(first) = current {
+            let network = (0..=B::BITS)
+                .map(|cidr| Network { first, cidr })
+                .find(|n| Network::<B>::is_valid(first, n.cidr) && n.last_bits() <= last)
+                .expect("a host route always fits");
+            networks.push(network);
+            current = network
+                .last_bits()
+                .checked_add(B::ONE)
+                .filter(|next| *next <= last);
         }
+        networks
+    }
+
+    /// Returns the smallest sorted list of networks covering the same
+    /// addresses as `networks`. Duplicates and networks inside others are
+    /// dropped and adjacent siblings are merged into their parent.
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let networks: Vec<Ipv4Network> = ["10.0.1.0/24", "10.0.0.0/24", "10.0.0.128/25", "10.0.2.0/24"]
+    ///     .iter()
+    ///     .map(|n| n.parse().unwrap())
+    ///     .collect();
+    /// let expected: Vec<Ipv4Network> = ["10.0.0.0/23", "10.0.2.0/24"]
+    ///     .iter()
+    ///     .map(|n| n.parse().unwrap())
+    ///     .collect();
+    /// assert_eq!(Ipv4Network::aggregate(&networks), expected);
+    /// ```
+    pub fn aggregate(networks: &[Network<B>]) -> Vec<Network<B>> {
+        let mut sorted = networks.to_vec();
+        sorted.sort_unstable();
+        let mut aggregated: Vec<Network<B>> = Vec::with_capacity(sorted.len());
+        for network in sorted {
+            if aggregated
+                .last()
+                .is_some_and(|last| last.is_subnet(&network))
+            {
+                continue;
+            }
+            aggregated.push(network);
+            while let [.., low, high] = aggregated[..] {
+                let step = Network::<B>::cidr_to_stepping(low.cidr);
+                let siblings = low.cidr == high.cidr
+                    && low.cidr > 0
+                    && low.first & step == B::ZERO
+                    && high.first == low.first | step;
+                if !siblings {
+                    break;
+                }
+                aggregated.pop();
+                let parent = aggregated.last_mut().expect("two networks were present");
+                parent.cidr -= 1;
+            }
+        }
+        aggregated
+    }
+
+    #[inline(always)]
+    fn cidr_to_hostcount(cidr: u8) -> B {
+        B::ONE << (B::BITS - cidr)
+    }
+
+    /// Bits of an address that lie outside the network part
+    #[inline(always)]
+    fn cidr_to_hostmask(cidr: u8) -> B {
+        B::MAX.checked_shr(u32::from(cidr)).unwrap_or(B::ZERO)
     }
+
+    /// Distance between two networks of the given size, 0 for a /0
     #[inline(always)]
-    fn cidr_to_hostcount(cidr: u8) -> u32 {
-        1 << (32 - cidr)
+    fn cidr_to_stepping(cidr: u8) -> B {
+        B::ONE
+            .checked_shl(u32::from(B::BITS).saturating_sub(u32::from(cidr)))
+            .unwrap_or(B::ZERO)
+    }
+
+    /// Number of addresses in the network.
+    ///
+    /// # Panics
+    ///
+    /// Panics for a /0, whose address count does not fit the address
+    /// type. `checked_hostcount` and `saturating_hostcount` do not.
+    pub fn hostcount(&self) -> B {
+        Network::<B>::cidr_to_hostcount(self.cidr)
     }
 
-    pub fn hostcount(&self) -> u32 {
-        Ipv4Network::cidr_to_hostcount(self.cidr)
+    /// Number of addresses in the network, None for a /0
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let network: Ipv4Network = "10.0.0.0/8".parse().unwrap();
+    /// assert_eq!(network.checked_hostcount(), Some(1 << 24));
+    /// assert_eq!(Ipv4Network::new(0, 0, 0, 0, 0).unwrap().checked_hostcount(), None);
+    /// ```
+    pub fn checked_hostcount(&self) -> Option<B> {
+        B::ONE.checked_shl(u32::from(B::BITS).checked_sub(u32::from(self.cidr))?)
     }
 
-    pub fn into_subnets(&self, new_cidr: u8) -> NetworkV4Iterator {
-        NetworkV4Iterator {
+    /// Number of addresses in the network, the largest value of the
+    /// address type for a /0
+    pub fn saturating_hostcount(&self) -> B {
+        self.checked_hostcount().unwrap_or(B::MAX)
+    }
+
+    /// Number of /`new_cidr` subnets in the network, the length of
+    /// `into_subnets(new_cidr)`. Saturates at `u128::MAX` for the /128
+    /// networks of `::/0`.
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// let network: Ipv6Network = "2001:db8::/48".parse().unwrap();
+    /// assert_eq!(network.count_subnets(64), 65536);
+    /// assert_eq!(network.count_subnets(32), 0);
+    /// ```
+    pub fn count_subnets(&self, new_cidr: u8) -> u128 {
+        if new_cidr < self.cidr || new_cidr > B::BITS {
+            return 0;
+        }
+        1u128
+            .checked_shl(u32::from(new_cidr - self.cidr))
+            .unwrap_or(u128::MAX)
+    }
+
+    /// Iterates over the /`new_cidr` subnets of the network. The iterator is
+    /// empty when `new_cidr` is shorter than the network's own prefix.
+    pub fn into_subnets(&self, new_cidr: u8) -> Subnets<B> {
+        Subnets {
             current: self.first,
-            stepping: Ipv4Network::cidr_to_hostcount(new_cidr),
+            stepping: Network::<B>::cidr_to_stepping(new_cidr),
             cidr: new_cidr,
-            max: self.first + self.hostcount() - 1,
+            max: self.last_bits(),
+            done: new_cidr < self.cidr || new_cidr > B::BITS,
+        }
+    }
+
+    /// Iterates over every /`cidr` network of the address space
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let mut networks = Ipv4Network::all(8);
+    /// assert_eq!(networks.next(), Some("0.0.0.0/8".parse().unwrap()));
+    /// assert_eq!(networks.last(), Some("255.0.0.0/8".parse().unwrap()));
+    /// ```
+    pub fn all(cidr: u8) -> Subnets<B> {
+        Network {
+            first: B::ZERO,
+            cidr: 0,
         }
+        .into_subnets(cidr)
     }
-    pub fn into_hosts(&self) -> HostIterator {
-        HostIterator {
+
+    /// Walks the tree of subnets below the network, splitting each network
+    /// into its two halves up to `max_depth` levels down. The visitor is
+    /// called with every network and its depth, in address order, and
+    /// decides whether to descend, prune or match. Returns the matched
+    /// networks.
+    /// ```
+    /// use ipnetwork::{Ipv4Network, Visit};
+    /// let network: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+    /// let leaves = network.walk(2, |_, depth| match depth {
+    ///     2 => Visit::Match,
+    ///     _ => Visit::Descend,
+    /// });
+    /// assert_eq!(leaves.len(), 4);
+    /// ```
+    pub fn walk<F>(&self, max_depth: u8, mut visitor: F) -> Vec<Network<B>>
+    where
+        F: FnMut(&Network<B>, u8) -> Visit,
+    {
+        let max_cidr = self.cidr.saturating_add(max_depth).min(B::BITS);
+        let mut matched = Vec::new();
+        let mut pending = vec![*self];
+        while let Some(network) = pending.pop() {
+            match visitor(&network, network.cidr - self.cidr) {
+                Visit::Match => matched.push(network),
+                Visit::Prune => {}
+                Visit::Descend if network.cidr < max_cidr => {
+                    let cidr = network.cidr + 1;
+                    let upper = network.first | Network::<B>::cidr_to_stepping(cidr);
+                    pending.push(Network { first: upper, cidr });
+                    pending.push(Network {
+                        first: network.first,
+                        cidr,
+                    });
+                }
+                Visit::Descend => {}
+            }
+        }
+        matched
+    }
+
+    pub fn into_hosts(&self) -> Hosts<B> {
+        Hosts {
             current: self.first,
-            max: self.first + self.hostcount(),
+            max: self.last_bits(),
+            done: false,
+        }
+    }
+    /// Iterates over the addresses that can be assigned to hosts, leaving
+    /// out the network and broadcast address. Point to point /31 (or /127)
+    /// networks and host routes have no such addresses and yield every
+    /// address.
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let network: Ipv4Network = "10.0.0.0/29".parse().unwrap();
+    /// assert_eq!(network.into_usable_hosts().count(), 6);
+    /// let link: Ipv4Network = "10.0.0.0/31".parse().unwrap();
+    /// assert_eq!(link.into_usable_hosts().count(), 2);
+    /// ```
+    pub fn into_usable_hosts(&self) -> Hosts<B> {
+        let mut hosts = self.into_hosts();
+        if self.cidr < B::BITS - 1 {
+            hosts.current = hosts.current + B::ONE;
+            hosts.max = hosts.max - B::ONE;
+        }
+        hosts
+    }
+    pub fn into_hosts_excluding(&self, excluded: &[Network<B>]) -> ExcludingHosts<B> {
+        let mut ranges: Vec<(B, B)> = excluded
+            .iter()
+            .map(|network| (network.first, network.last_bits()))
+            .collect();
+        ranges.sort_unstable();
+        let mut excluded: Vec<(B, B)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match excluded.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => excluded.push((start, end)),
+            }
+        }
+        ExcludingHosts {
+            hosts: self.into_hosts(),
+            back_range: excluded.len(),
+            excluded,
+            next_range: 0,
+        }
+    }
+
+    pub fn first(&self) -> B::Addr {
+        self.first.to_addr()
+    }
+    pub fn last(&self) -> B::Addr {
+        self.last_bits().to_addr()
+    }
+    #[inline(always)]
+    fn last_bits(&self) -> B {
+        self.first | Network::<B>::cidr_to_hostmask(self.cidr)
+    }
+
+    /// Returns the /`new_cidr` supernet containing the network. Fails with
+    /// `CidrMissMatch` when `new_cidr` is longer than the network's prefix,
+    /// use `reprefix` to cut the network down instead.
+    /// ```
+    /// use ipnetwork::{Error, Ipv4Network};
+    /// let network: Ipv4Network = "10.1.2.0/24".parse().unwrap();
+    /// assert_eq!(network.with_prefix(16), "10.1.0.0/16".parse());
+    /// assert_eq!(network.with_prefix(25), Err(Error::CidrMissMatch));
+    /// ```
+    pub fn with_prefix(&self, new_cidr: u8) -> Result<Network<B>, Error> {
+        match new_cidr <= self.cidr {
+            true => self.reprefix(new_cidr),
+            false => Err(Error::CidrMissMatch),
+        }
+    }
+
+    /// Returns the /`new_cidr` network containing the first address of the
+    /// network. When narrowing, this is the first subnet and the rest of
+    /// the network is dropped. Fails with `InvalidNetwork` for prefixes
+    /// longer than the address.
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let network: Ipv4Network = "10.1.2.0/24".parse().unwrap();
+    /// assert_eq!(network.reprefix(26), "10.1.2.0/26".parse());
+    /// assert_eq!(network.reprefix(8), "10.0.0.0/8".parse());
+    /// ```
+    pub fn reprefix(&self, new_cidr: u8) -> Result<Network<B>, Error> {
+        if new_cidr > B::BITS {
+            return Err(Error::InvalidNetwork);
         }
+        Ok(Network {
+            first: self.first & !Network::<B>::cidr_to_hostmask(new_cidr),
+            cidr: new_cidr,
+        })
     }
-    pub fn last(&self) -> Ipv4Addr {
-        Ipv4Addr::from(self.first + self.hostcount() - 1)
+
+    /// Returns the network of the same size right after this one, or None
+    /// at the end of the address space
+    pub fn next_network(&self) -> Option<Network<B>> {
+        let step = B::ONE.checked_shl(u32::from(B::BITS - self.cidr))?;
+        Some(Network {
+            first: self.first.checked_add(step)?,
+            cidr: self.cidr,
+        })
     }
 
-    pub fn first(&self) -> Ipv4Addr {
-        Ipv4Addr::from(self.first)
+    /// Returns the network of the same size right before this one, or None
+    /// at the start of the address space
+    pub fn previous_network(&self) -> Option<Network<B>> {
+        let step = B::ONE.checked_shl(u32::from(B::BITS - self.cidr))?;
+        Some(Network {
+            first: self.first.checked_sub(step)?,
+            cidr: self.cidr,
+        })
     }
-    pub fn contains(&self, ip_addr: &Ipv4Addr) -> bool {
-        let ip_int = u32::from(*ip_addr);
-        ip_int > self.first && ip_int < (self.first + self.hostcount() - 1)
+
+    /// Returns true when the address is part of the network, including the
+    /// first and the last address
+    pub fn contains(&self, ip_addr: &B::Addr) -> bool {
+        let ip_int = B::from_addr(*ip_addr);
+        self.first <= ip_int && ip_int <= self.last_bits()
+    }
+    /// Tells whether the address comes before, inside or after the
+    /// network. Useful to binary search sorted, non overlapping networks.
+    /// ```
+    /// use ipnetwork::{AddressPosition, Ipv4Network};
+    /// use std::net::Ipv4Addr;
+    /// let networks: Vec<Ipv4Network> = ["10.0.0.0/24", "10.0.2.0/24"]
+    ///     .iter()
+    ///     .map(|n| n.parse().unwrap())
+    ///     .collect();
+    /// let addr = Ipv4Addr::new(10, 0, 2, 255);
+    /// let found = networks.binary_search_by(|n| match n.position_of(&addr) {
+    ///     AddressPosition::Before => std::cmp::Ordering::Greater,
+    ///     AddressPosition::Inside => std::cmp::Ordering::Equal,
+    ///     AddressPosition::After => std::cmp::Ordering::Less,
+    /// });
+    /// assert_eq!(found, Ok(1));
+    /// ```
+    pub fn position_of(&self, ip_addr: &B::Addr) -> AddressPosition {
+        let ip_int = B::from_addr(*ip_addr);
+        match ip_int {
+            ip if ip < self.first => AddressPosition::Before,
+            ip if ip > self.last_bits() => AddressPosition::After,
+            _ => AddressPosition::Inside,
+        }
     }
     pub fn is_subnet(&self, other: &Self) -> bool {
         self.first() <= other.first() && other.last() <= self.last()
@@ -124,112 +839,592 @@ impl Ipv4Network {
     pub fn is_supernet(&self, other: &Self) -> bool {
         self.first() >= other.first() && other.last() >= self.last()
     }
-    pub fn netmask(&self) -> Ipv4Addr {
-        let numeric = Ipv4Network::MAX_NETMASK ^ (self.hostcount() - 1);
-        Ipv4Addr::from(numeric)
+    pub fn netmask(&self) -> B::Addr {
+        (!Network::<B>::cidr_to_hostmask(self.cidr)).to_addr()
     }
 
     #[inline(always)]
-    fn is_valid(first: u32, cidr: u8) -> bool {
-        first % Ipv4Network::cidr_to_hostcount(cidr) == 0
+    fn is_valid(first: B, cidr: u8) -> bool {
+        cidr <= B::BITS && first & Network::<B>::cidr_to_hostmask(cidr) == B::ZERO
     }
 }
 
-impl Ipv6Network {
-    pub const MAX_NETMASK: u128 = u128::MAX;
+impl Ipv4Network {
+    /// Creates a new IPv4 Network
+    pub fn new(a: u8, b: u8, c: u8, d: u8, cidr: u8) -> Result<Ipv4Network, Error> {
+        let first = u32::from_be_bytes([a, b, c, d]);
+        match Ipv4Network::is_valid(first, cidr) {
+            true => Ok(Ipv4Network { first, cidr }),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+
+    /// Parses a network in `a.b.c.d/cidr` notation from bytes, without
+    /// allocating
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// let network = Ipv4Network::parse_ascii(b"10.0.0.0/8").unwrap();
+    /// assert_eq!(network, "10.0.0.0/8".parse().unwrap());
+    /// ```
+    pub fn parse_ascii(bytes: &[u8]) -> Result<Ipv4Network, Error> {
+        let (addr, cidr) = parse::split_prefix(bytes).ok_or(Error::NetworkParseError)?;
+        let first = parse::ipv4(addr).ok_or(Error::NetworkParseError)?;
+        let cidr = parse::prefix_len(cidr).ok_or(Error::NetworkParseError)?;
+        match Ipv4Network::is_valid(first, cidr) {
+            true => Ok(Ipv4Network { first, cidr }),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+
+    /// Parses a network like `parse_ascii`, also accepting the shorthand of
+    /// BGP tooling that leaves out trailing zero octets, like `10/8`,
+    /// `172.16/12` or `192.168.1/24`. The prefix length is still needed.
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// assert_eq!(Ipv4Network::parse_relaxed(b"172.16/12"), Ok("172.16.0.0/12".parse().unwrap()));
+    /// assert_eq!(Ipv4Network::parse_relaxed(b"10/8"), Ok("10.0.0.0/8".parse().unwrap()));
+    /// assert!(Ipv4Network::parse_relaxed(b"10").is_err());
+    /// ```
+    pub fn parse_relaxed(bytes: &[u8]) -> Result<Ipv4Network, Error> {
+        let (addr, cidr) = parse::split_prefix(bytes).ok_or(Error::NetworkParseError)?;
+        let first = parse::ipv4_shorthand(addr).ok_or(Error::NetworkParseError)?;
+        let cidr = parse::prefix_len(cidr).ok_or(Error::NetworkParseError)?;
+        match Ipv4Network::is_valid(first, cidr) {
+            true => Ok(Ipv4Network { first, cidr }),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+}
 
+impl Ipv6Network {
     pub fn new(first: u128, cidr: u8) -> Result<Ipv6Network, Error> {
         match Ipv6Network::is_valid(first, cidr) {
             true => Ok(Ipv6Network { first, cidr }),
             false => Err(Error::InvalidNetwork),
         }
     }
-    pub fn first(&self) -> Ipv6Addr {
-        Ipv6Addr::from(self.first)
+
+    /// Parses a network in `address/cidr` notation from bytes, without
+    /// allocating
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// let network = Ipv6Network::parse_ascii(b"2001:db8::/32").unwrap();
+    /// assert_eq!(network, "2001:db8::/32".parse().unwrap());
+    /// ```
+    pub fn parse_ascii(bytes: &[u8]) -> Result<Ipv6Network, Error> {
+        let (addr, cidr) = parse::split_prefix(bytes).ok_or(Error::NetworkParseError)?;
+        let first = parse::ipv6(addr).ok_or(Error::NetworkParseError)?;
+        let cidr = parse::prefix_len(cidr).ok_or(Error::NetworkParseError)?;
+        Ipv6Network::new(first, cidr)
     }
 
-    pub fn last(&self) -> Ipv6Addr {
-        Ipv6Addr::from(self.first + self.hostcount())
+    /// Creates a new IPv6 Network from its first address
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// use std::net::Ipv6Addr;
+    /// let addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0);
+    /// let network = Ipv6Network::new_from_addr(addr, 32).unwrap();
+    /// assert_eq!(network, "2001:db8::/32".parse().unwrap());
+    /// ```
+    pub fn new_from_addr(addr: Ipv6Addr, cidr: u8) -> Result<Ipv6Network, Error> {
+        Ipv6Network::new(u128::from(addr), cidr)
     }
 
-    pub fn hostcount(&self) -> u128 {
-        Ipv6Network::cidr_to_hostcount(self.cidr)
+    /// Creates a new IPv6 Network from the eight 16 bit segments of its
+    /// first address
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// let network = Ipv6Network::from_segments([0xfd00, 0, 0, 0, 0, 0, 0, 0], 8).unwrap();
+    /// assert_eq!(network, "fd00::/8".parse().unwrap());
+    /// ```
+    pub fn from_segments(segments: [u16; 8], cidr: u8) -> Result<Ipv6Network, Error> {
+        let [a, b, c, d, e, f, g, h] = segments;
+        Ipv6Network::new_from_addr(Ipv6Addr::new(a, b, c, d, e, f, g, h), cidr)
     }
-    #[inline(always)]
-    fn cidr_to_hostcount(cidr: u8) -> u128 {
-        1 << (128 - cidr)
+
+    /// Returns the other end of the /127 point to point link `addr` is on
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// use std::net::Ipv6Addr;
+    /// let peer = Ipv6Network::peer_of("2001:db8::1:0".parse().unwrap());
+    /// assert_eq!(peer, "2001:db8::1:1".parse::<Ipv6Addr>().unwrap());
+    /// ```
+    pub fn peer_of(addr: Ipv6Addr) -> Ipv6Addr {
+        Ipv6Addr::from(u128::from(addr) ^ 1)
     }
-    pub fn is_subnet(&self, other: &Self) -> bool {
-        self.first() <= other.first() && other.last() <= self.last()
+
+    /// Returns true for a /127 that can number a point to point link as
+    /// recommended by RFC 6164: neither address may be a reserved
+    /// interface identifier of RFC 5453, the subnet-router anycast address
+    /// or one of the reserved subnet anycast addresses.
+    pub fn is_point_to_point(&self) -> bool {
+        // The reserved ranges of RFC 5453, the first one is the single
+        // subnet-router anycast address
+        const RESERVED: [(u64, u64); 3] = [
+            (0, 0),
+            (0x0200_5eff_fe00_0000, 0x0200_5eff_feff_ffff),
+            (0xfdff_ffff_ffff_ff80, 0xfdff_ffff_ffff_ffff),
+        ];
+        let reserved = |interface_id: u64| {
+            RESERVED
+                .iter()
+                .any(|&(low, high)| low <= interface_id && interface_id <= high)
+        };
+        let interface_id = self.first as u64;
+        self.cidr == 127 && !reserved(interface_id) && !reserved(interface_id | 1)
     }
-    pub fn is_supernet(&self, other: &Self) -> bool {
-        self.first() >= other.first() && other.last() >= self.last()
+
+    /// Iterates over the /127 point to point links of the network, leaving
+    /// out the ones `is_point_to_point` rejects
+    /// ```
+    /// use ipnetwork::Ipv6Network;
+    /// let block: Ipv6Network = "2001:db8::/124".parse().unwrap();
+    /// let mut links = block.point_to_point_links();
+    /// assert_eq!(links.next(), Some("2001:db8::2/127".parse().unwrap()));
+    /// assert_eq!(links.count(), 6);
+    /// ```
+    pub fn point_to_point_links(&self) -> Filter<NetworkV6Iterator, fn(&Ipv6Network) -> bool> {
+        self.into_subnets(127)
+            .filter(Ipv6Network::is_point_to_point as fn(&Ipv6Network) -> bool)
     }
-    #[inline(always)]
-    fn is_valid(first: u128, cidr: u8) -> bool {
-        first % Ipv6Network::cidr_to_hostcount(cidr) == 0
+}
+
+impl Checkpoint {
+    /// Length of the encoded checkpoint
+    pub const LEN: usize = 35;
+
+    /// Encodes the checkpoint as a fixed size token
+    pub fn to_bytes(&self) -> [u8; Checkpoint::LEN] {
+        let mut bytes = [0; Checkpoint::LEN];
+        bytes[0] = self.kind as u8;
+        bytes[1..17].copy_from_slice(&self.current.to_be_bytes());
+        bytes[17..33].copy_from_slice(&self.max.to_be_bytes());
+        bytes[33] = self.cidr;
+        bytes[34] = self.done as u8;
+        bytes
+    }
+
+    /// Decodes a token produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Checkpoint, Error> {
+        if bytes.len() != Checkpoint::LEN {
+            return Err(Error::InvalidCheckpoint);
+        }
+        let kind = match bytes[0] {
+            1 => CheckpointKind::HostV4,
+            2 => CheckpointKind::SubnetV4,
+            3 => CheckpointKind::SubnetV6,
+            4 => CheckpointKind::HostV6,
+            _ => return Err(Error::InvalidCheckpoint),
+        };
+        let mut current = [0; 16];
+        current.copy_from_slice(&bytes[1..17]);
+        let mut max = [0; 16];
+        max.copy_from_slice(&bytes[17..33]);
+        let done = match bytes[34] {
+            0 => false,
+            1 => true,
+            _ => return Err(Error::InvalidCheckpoint),
+        };
+        Ok(Checkpoint {
+            kind,
+            current: u128::from_be_bytes(current),
+            max: u128::from_be_bytes(max),
+            cidr: bytes[33],
+            done,
+        })
+    }
+
+    /// Validates the parts shared by all iterators
+    fn bounds<B: AddressBits>(&self, kind: CheckpointKind) -> Result<(B, B), Error> {
+        if self.kind != kind || self.current > self.max {
+            return Err(Error::InvalidCheckpoint);
+        }
+        match (B::from_u128(self.current), B::from_u128(self.max)) {
+            (Some(current), Some(max)) => Ok((current, max)),
+            _ => Err(Error::InvalidCheckpoint),
+        }
+    }
+}
+
+impl<B: AddressBits> Hosts<B> {
+    /// Returns the current position of the iterator
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            kind: CheckpointKind::hosts(B::BITS),
+            current: self.current.to_u128(),
+            max: self.max.to_u128(),
+            cidr: B::BITS,
+            done: self.done,
+        }
+    }
+
+    /// Rebuilds an iterator from a checkpoint taken with `checkpoint`
+    pub fn resume(checkpoint: &Checkpoint) -> Result<Hosts<B>, Error> {
+        let (current, max) = checkpoint.bounds(CheckpointKind::hosts(B::BITS))?;
+        Ok(Hosts {
+            current,
+            max,
+            done: checkpoint.done,
+        })
+    }
+}
+
+impl<B: AddressBits> Subnets<B> {
+    /// Returns the current position of the iterator
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            kind: CheckpointKind::subnets(B::BITS),
+            current: self.current.to_u128(),
+            max: self.max.to_u128(),
+            cidr: self.cidr,
+            done: self.done,
+        }
+    }
+
+    /// Rebuilds an iterator from a checkpoint taken with `checkpoint`.
+    /// Exhausted iterators, like the empty ones of `into_subnets` with a
+    /// prefix shorter than the network's, are resumed with any prefix.
+    pub fn resume(checkpoint: &Checkpoint) -> Result<Subnets<B>, Error> {
+        let (current, max) = checkpoint.bounds(CheckpointKind::subnets(B::BITS))?;
+        if !checkpoint.done && !Network::is_valid(current, checkpoint.cidr) {
+            return Err(Error::InvalidCheckpoint);
+        }
+        Ok(Subnets {
+            current,
+            max,
+            stepping: Network::<B>::cidr_to_stepping(checkpoint.cidr),
+            cidr: checkpoint.cidr,
+            done: checkpoint.done,
+        })
     }
 }
 
-impl Iterator for NetworkV4Iterator {
-    type Item = Ipv4Network;
-    fn next(&mut self) -> Option<Ipv4Network> {
-        if self.current < self.max {
-            self.current += self.stepping;
-            let bytes = self.current.to_be_bytes();
-            match Ipv4Network::new(bytes[0], bytes[1], bytes[2], bytes[3], self.cidr) {
-                Ok(n) => Some(n),
-                Err(_) => None,
+/// Size hint for an iterator with `remaining` items left, None for more
+/// than `u128::MAX`
+fn exact_size_hint(remaining: Option<u128>) -> (usize, Option<usize>) {
+    match remaining.map(usize::try_from) {
+        Some(Ok(remaining)) => (remaining, Some(remaining)),
+        _ => (usize::MAX, None),
+    }
+}
+
+impl<B: AddressBits> Hosts<B> {
+    /// Jumps ahead so the next address returned is `addr`. Addresses the
+    /// iterator has already passed are not returned again: the iterator is
+    /// left as is when `addr` comes before the next address, and exhausted
+    /// when `addr` is after the last one. Returns true when the next
+    /// address is `addr`.
+    /// ```
+    /// use ipnetwork::Ipv4Network;
+    /// use std::net::Ipv4Addr;
+    /// let network: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+    /// let mut hosts = network.into_hosts();
+    /// assert!(hosts.advance_to(Ipv4Addr::new(10, 0, 0, 200)));
+    /// assert_eq!(hosts.next(), Some(Ipv4Addr::new(10, 0, 0, 200)));
+    /// assert_eq!(hosts.len(), 55);
+    /// ```
+    pub fn advance_to(&mut self, addr: B::Addr) -> bool {
+        let addr = B::from_addr(addr);
+        if self.done || addr < self.current {
+            return false;
+        }
+        match addr <= self.max {
+            true => self.current = addr,
+            false => self.done = true,
+        }
+        !self.done
+    }
+
+    /// Addresses left, None when all 2^128 IPv6 addresses are left
+    fn remaining(&self) -> Option<u128> {
+        match self.done {
+            true => Some(0),
+            false => (self.max - self.current).to_u128().checked_add(1),
+        }
+    }
+}
+
+impl<B: AddressBits> Subnets<B> {
+    /// Networks left, None when all 2^128 IPv6 host routes are left
+    fn remaining(&self) -> Option<u128> {
+        if self.done || self.current > self.max {
+            return Some(0);
+        }
+        match self.stepping.to_u128() {
+            0 => Some(1),
+            step => ((self.max - self.current).to_u128() / step).checked_add(1),
+        }
+    }
+}
+
+impl<B: AddressBits> Iterator for Hosts<B> {
+    type Item = B::Addr;
+    fn next(&mut self) -> Option<B::Addr> {
+        if self.done {
+            return None;
+        }
+        let addr = self.current.to_addr();
+        match self.current < self.max {
+            true => self.current = self.current + B::ONE,
+            false => self.done = true,
+        }
+        Some(addr)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+
+    fn nth(&mut self, n: usize) -> Option<B::Addr> {
+        match B::from_u128(n as u128) {
+            Some(n) if !self.done && self.max - self.current >= n => {
+                self.current = self.current + n;
+                self.next()
+            }
+            _ => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl<B: AddressBits> DoubleEndedIterator for Hosts<B> {
+    fn next_back(&mut self) -> Option<B::Addr> {
+        if self.done {
+            return None;
+        }
+        let addr = self.max.to_addr();
+        match self.current < self.max {
+            true => self.max = self.max - B::ONE,
+            false => self.done = true,
+        }
+        Some(addr)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<B::Addr> {
+        match B::from_u128(n as u128) {
+            Some(n) if !self.done && self.max - self.current >= n => {
+                self.max = self.max - n;
+                self.next_back()
+            }
+            _ => {
+                self.done = true;
+                None
             }
-        } else {
-            None
+        }
+    }
+}
+
+impl Iterator for IpHostIterator {
+    type Item = IpAddr;
+    fn next(&mut self) -> Option<IpAddr> {
+        match self {
+            IpHostIterator::V4(hosts) => hosts.next().map(IpAddr::V4),
+            IpHostIterator::V6(hosts) => hosts.next().map(IpAddr::V6),
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.current as usize, Some(self.max as usize))
+        match self {
+            IpHostIterator::V4(hosts) => hosts.size_hint(),
+            IpHostIterator::V6(hosts) => hosts.size_hint(),
+        }
     }
 }
 
-impl Iterator for NetworkV6Iterator {
-    type Item = Ipv6Network;
+impl DoubleEndedIterator for IpHostIterator {
+    fn next_back(&mut self) -> Option<IpAddr> {
+        match self {
+            IpHostIterator::V4(hosts) => hosts.next_back().map(IpAddr::V4),
+            IpHostIterator::V6(hosts) => hosts.next_back().map(IpAddr::V6),
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Ipv6Network> {
-        if self.current < self.max {
-            self.current += self.stepping;
-            let network = Ipv6Network::new(self.current, self.cidr);
-            match network {
-                Ok(n) => Some(n),
-                Err(_) => None,
+impl<B: AddressBits> Iterator for ExcludingHosts<B> {
+    type Item = B::Addr;
+    fn next(&mut self) -> Option<B::Addr> {
+        while !self.hosts.done {
+            let current = self.hosts.current;
+            while self.next_range < self.excluded.len()
+                && self.excluded[self.next_range].1 < current
+            {
+                self.next_range += 1;
+            }
+            match self.excluded.get(self.next_range) {
+                Some(&(start, end)) if start <= current => {
+                    if end >= self.hosts.max {
+                        self.hosts.done = true;
+                    } else {
+                        self.hosts.current = end + B::ONE;
+                    }
+                }
+                _ => return self.hosts.next(),
             }
-        } else {
-            None
         }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.hosts.size_hint().1)
     }
 }
-impl Ord for Ipv6Network {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let order = self.first().cmp(&other.first());
-        match order {
-            Ordering::Equal => self.cidr.cmp(&other.cidr),
-            _ => order,
+
+impl<B: AddressBits> DoubleEndedIterator for ExcludingHosts<B> {
+    fn next_back(&mut self) -> Option<B::Addr> {
+        while !self.hosts.done {
+            let current = self.hosts.max;
+            while self.back_range > 0 && self.excluded[self.back_range - 1].0 > current {
+                self.back_range -= 1;
+            }
+            match self.back_range.checked_sub(1).map(|i| self.excluded[i]) {
+                Some((start, end)) if end >= current => {
+                    if start <= self.hosts.current {
+                        self.hosts.done = true;
+                    } else {
+                        self.hosts.max = start - B::ONE;
+                    }
+                }
+                _ => return self.hosts.next_back(),
+            }
         }
+        None
     }
 }
 
-impl PartialOrd for Ipv6Network {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// Product of the two values as a (high, low) pair, which orders like it
+fn widening_mul(a: u128, b: u64) -> (u128, u128) {
+    let b = u128::from(b);
+    let low = (a & u128::from(u64::MAX)) * b;
+    let high = (a >> 64) * b;
+    let (sum, carry) = low.overflowing_add(high << 64);
+    ((high >> 64) + u128::from(carry), sum)
+}
+
+impl<B: AddressBits> InterleavedHosts<B> {
+    pub fn new(networks: &[Network<B>], mode: Interleave) -> InterleavedHosts<B> {
+        let hosts: Vec<Hosts<B>> = networks.iter().map(|n| n.into_hosts()).collect();
+        let totals = hosts
+            .iter()
+            .map(|h| h.remaining().unwrap_or(u128::MAX))
+            .collect();
+        InterleavedHosts {
+            returned: vec![0; hosts.len()],
+            hosts,
+            totals,
+            mode,
+            position: 0,
+        }
+    }
+
+    /// Index of the network with the smallest share of its addresses returned
+    fn least_progressed(&self) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (index, hosts) in self.hosts.iter().enumerate() {
+            if hosts.done {
+                continue;
+            }
+            best = match best {
+                Some(b)
+                    if widening_mul(self.totals[index], self.returned[b])
+                        <= widening_mul(self.totals[b], self.returned[index]) =>
+                {
+                    Some(b)
+                }
+                _ => Some(index),
+            };
+        }
+        best
+    }
+}
+
+impl<B: AddressBits> Iterator for InterleavedHosts<B> {
+    type Item = B::Addr;
+    fn next(&mut self) -> Option<B::Addr> {
+        let index = match self.mode {
+            Interleave::RoundRobin => {
+                let count = self.hosts.len();
+                let index = (0..count)
+                    .map(|offset| (self.position + offset) % count)
+                    .find(|&i| !self.hosts[i].done)?;
+                self.position = (index + 1) % count;
+                index
+            }
+            Interleave::Proportional => self.least_progressed()?,
+        };
+        self.returned[index] += 1;
+        self.hosts[index].next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .hosts
+            .iter()
+            .try_fold(0u128, |sum, hosts| sum.checked_add(hosts.remaining()?));
+        exact_size_hint(remaining)
     }
 }
-impl PartialEq for Ipv6Network {
-    fn eq(&self, other: &Self) -> bool {
-        self.first == other.first && self.cidr == other.cidr
+
+impl<B: AddressBits> Iterator for Subnets<B> {
+    type Item = Network<B>;
+    fn next(&mut self) -> Option<Network<B>> {
+        if self.done || self.current > self.max {
+            return None;
+        }
+        let network = Network {
+            first: self.current,
+            cidr: self.cidr,
+        };
+        match self.stepping {
+            step if step != B::ZERO && self.max - self.current >= step => {
+                self.current = self.current + step
+            }
+            _ => self.done = true,
+        }
+        Some(network)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        exact_size_hint(self.remaining())
+    }
+}
+
+/// Every IPv4 host and subnet count fits in a 64 bit usize
+#[cfg(target_pointer_width = "64")]
+impl ExactSizeIterator for HostIterator {}
+#[cfg(target_pointer_width = "64")]
+impl ExactSizeIterator for NetworkV4Iterator {}
+
+impl<B: AddressBits> FusedIterator for Hosts<B> {}
+impl<B: AddressBits> FusedIterator for Subnets<B> {}
+impl<B: AddressBits> FusedIterator for ExcludingHosts<B> {}
+impl FusedIterator for IpHostIterator {}
+impl<B: AddressBits> FusedIterator for InterleavedHosts<B> {}
+
+impl<B: AddressBits> DoubleEndedIterator for Subnets<B> {
+    fn next_back(&mut self) -> Option<Network<B>> {
+        if self.done || self.current > self.max {
+            return None;
+        }
+        let first = match self.stepping {
+            step if step != B::ZERO => self.max - (step - B::ONE),
+            _ => self.current,
+        };
+        match first > self.current {
+            true => self.max = first - B::ONE,
+            false => self.done = true,
+        }
+        Some(Network {
+            first,
+            cidr: self.cidr,
+        })
     }
 }
-impl Ord for Ipv4Network {
+
+impl<B: AddressBits> Ord for Network<B> {
     fn cmp(&self, other: &Self) -> Ordering {
-        let order = self.first().cmp(&other.first());
+        let order = self.first.cmp(&other.first);
         match order {
             Ordering::Equal => self.cidr.cmp(&other.cidr),
             _ => order,
@@ -237,42 +1432,53 @@ impl Ord for Ipv4Network {
     }
 }
 
-impl PartialOrd for Ipv4Network {
+impl<B: AddressBits> PartialOrd for Network<B> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
-impl PartialEq for Ipv4Network {
-    fn eq(&self, other: &Self) -> bool {
-        self.first == other.first && self.cidr == other.cidr
+impl<B: AddressBits> fmt::Display for Network<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+/// Parses a network of either family like `IpNetwork::parse_ascii`,
+/// `diagnostic::diagnose_str` explains the failures instead
+/// ```
+/// use ipnetwork::{Error, IpNetwork};
+/// assert_eq!("10.0.0.1/8".parse::<IpNetwork>(), Err(Error::InvalidNetwork));
+/// ```
+impl FromStr for IpNetwork {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<IpNetwork, Self::Err> {
+        IpNetwork::parse_ascii(s.as_bytes())
     }
 }
 impl FromStr for Ipv4Network {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Ipv4Network, Self::Err> {
-        let parts: Vec<&str> = s.split('/').collect();
-        match parts.len() {
-            2 => {
-                let ip_first: Ipv4Addr = match parts[0].parse() {
-                    Ok(ip_addr) => ip_addr,
-                    Err(_) => return Err(Self::Err::NetworkParseError),
-                };
-                let cidr: u8 = match parts[1].parse() {
-                    Ok(cidr) => cidr,
-                    Err(_) => return Err(Self::Err::NetworkParseError),
-                };
-                let ip_tuple = u32::from(ip_first).to_be_bytes();
-                Ipv4Network::new(ip_tuple[0], ip_tuple[1], ip_tuple[2], ip_tuple[3], cidr)
-            }
-            _ => Err(Self::Err::NetworkParseError),
-        }
+        Ipv4Network::parse_ascii(s.as_bytes())
+    }
+}
+impl FromStr for Ipv6Network {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Ipv6Network, Self::Err> {
+        Ipv6Network::parse_ascii(s.as_bytes())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::Ipv4Addr;
     use std::str::FromStr;
     #[test]
     fn new_network() {
@@ -304,6 +1510,204 @@ mod tests {
     fn contains_addr() {
         let network = Ipv4Network::new(1, 1, 1, 0, 24).unwrap();
         assert!(network.contains(&Ipv4Addr::new(1, 1, 1, 1)));
+        assert!(network.contains(&Ipv4Addr::new(1, 1, 1, 0)));
+        assert!(network.contains(&Ipv4Addr::new(1, 1, 1, 255)));
+        assert!(!network.contains(&Ipv4Addr::new(1, 1, 2, 0)));
+        let top = Ipv4Network::new(255, 255, 255, 0, 24).unwrap();
+        assert!(top.contains(&Ipv4Addr::new(255, 255, 255, 255)));
+    }
+    #[test]
+    fn from_netmask() {
+        let addr = Ipv4Addr::new(10, 1, 2, 3);
+        let network = |mask| Ipv4Network::with_netmask(addr, mask);
+        assert_eq!(
+            network(Ipv4Addr::new(255, 255, 254, 0)),
+            Ipv4Network::new(10, 1, 2, 0, 23)
+        );
+        assert_eq!(
+            network(Ipv4Addr::new(0, 0, 0, 0)),
+            Ipv4Network::new(0, 0, 0, 0, 0)
+        );
+        assert_eq!(network(Ipv4Addr::new(255, 255, 255, 255)).unwrap().cidr, 32);
+        assert_eq!(
+            network(Ipv4Addr::new(255, 0, 255, 0)),
+            Err(Error::InvalidNetmask)
+        );
+        assert_eq!(
+            network(Ipv4Addr::new(0, 0, 0, 255)),
+            Err(Error::InvalidNetmask)
+        );
+        let v6 = Ipv6Network::with_netmask(
+            "2001:db8::1".parse().unwrap(),
+            "ffff:ffff:ffff:ffff::".parse().unwrap(),
+        );
+        assert_eq!(v6, "2001:db8::/64".parse());
+    }
+    #[test]
+    fn from_address_range() {
+        let v4 = |s: &str| Ipv4Addr::from_str(s).unwrap();
+        assert_eq!(
+            Ipv4Network::from_range(v4("0.0.0.0"), v4("255.255.255.255")),
+            Ipv4Network::new(0, 0, 0, 0, 0)
+        );
+        assert_eq!(
+            Ipv4Network::from_range(v4("10.0.0.7"), v4("10.0.0.7")),
+            Ipv4Network::new(10, 0, 0, 7, 32)
+        );
+        assert_eq!(
+            Ipv4Network::from_range(v4("10.0.0.2"), v4("10.0.0.5")),
+            Err(Error::RangeNotAligned)
+        );
+        assert_eq!(
+            Ipv4Network::from_range(v4("10.0.0.2"), v4("10.0.0.1")),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            Ipv4Network::summarize_address_range(v4("0.0.0.0"), v4("255.255.255.255")),
+            vec![Ipv4Network::new(0, 0, 0, 0, 0).unwrap()]
+        );
+        assert_eq!(
+            Ipv4Network::summarize_address_range(v4("255.255.255.254"), v4("255.255.255.255")),
+            vec![Ipv4Network::new(255, 255, 255, 254, 31).unwrap()]
+        );
+        assert!(Ipv4Network::summarize_address_range(v4("10.0.0.2"), v4("10.0.0.1")).is_empty());
+        let v6 = Ipv6Network::summarize_address_range(Ipv6Addr::from(1), Ipv6Addr::from(u128::MAX));
+        assert_eq!(v6.len(), 128);
+        assert_eq!(v6[127], Ipv6Network::new(1 << 127, 1).unwrap());
+    }
+    #[test]
+    fn aggregate_networks() {
+        let v4 = |list: &[&str]| -> Vec<Ipv4Network> {
+            list.iter().map(|n| n.parse().unwrap()).collect()
+        };
+        assert_eq!(
+            Ipv4Network::aggregate(&v4(&[
+                "10.0.0.3/32",
+                "10.0.0.0/31",
+                "10.0.0.2/32",
+                "10.0.0.2/31"
+            ])),
+            v4(&["10.0.0.0/30"])
+        );
+        assert_eq!(
+            Ipv4Network::aggregate(&v4(&["0.0.0.0/1", "128.0.0.0/1", "10.0.0.0/8"])),
+            v4(&["0.0.0.0/0"])
+        );
+        assert_eq!(
+            Ipv4Network::aggregate(&v4(&["10.0.1.0/24", "10.0.2.0/24"])),
+            v4(&["10.0.1.0/24", "10.0.2.0/24"])
+        );
+        assert!(Ipv4Network::aggregate(&[]).is_empty());
+        let mixed = [
+            IpNetwork::V6("2001:db8::/33".parse().unwrap()),
+            IpNetwork::V4("192.0.2.0/24".parse().unwrap()),
+            IpNetwork::V6("2001:db8:8000::/33".parse().unwrap()),
+        ];
+        assert_eq!(
+            IpNetwork::aggregate(&mixed),
+            vec![mixed[1], IpNetwork::V6("2001:db8::/32".parse().unwrap())]
+        );
+    }
+    #[test]
+    fn address_position() {
+        let network = Ipv4Network::new(10, 0, 0, 0, 31).unwrap();
+        let position = |d| network.position_of(&Ipv4Addr::new(10, 0, 0, d));
+        assert_eq!(position(0), AddressPosition::Inside);
+        assert_eq!(position(1), AddressPosition::Inside);
+        assert_eq!(position(2), AddressPosition::After);
+        let all = Ipv6Network::new(0, 0).unwrap();
+        assert_eq!(
+            all.position_of(&Ipv6Addr::from(u128::MAX)),
+            AddressPosition::Inside
+        );
+        let zero = Ipv4Network::new(0, 0, 0, 1, 32).unwrap();
+        assert_eq!(
+            zero.position_of(&Ipv4Addr::new(0, 0, 0, 0)),
+            AddressPosition::Before
+        );
+    }
+    #[test]
+    fn next_and_previous_network() {
+        let network = Ipv4Network::from_str("10.0.1.0/24").unwrap();
+        assert_eq!(
+            network.next_network(),
+            Some(Ipv4Network::from_str("10.0.2.0/24").unwrap())
+        );
+        assert_eq!(
+            network.previous_network(),
+            Some(Ipv4Network::from_str("10.0.0.0/24").unwrap())
+        );
+        let top = Ipv4Network::from_str("255.255.255.0/24").unwrap();
+        assert_eq!(top.next_network(), None);
+        assert_eq!(top.last(), Ipv4Addr::new(255, 255, 255, 255));
+        let bottom = Ipv4Network::from_str("0.0.0.0/1").unwrap();
+        assert_eq!(bottom.previous_network(), None);
+        assert_eq!(bottom.next_network().unwrap().next_network(), None);
+    }
+    #[test]
+    fn new_network_v6() {
+        let addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0);
+        assert_eq!(
+            Ipv6Network::new_from_addr(addr, 64),
+            Ipv6Network::from_segments(addr.segments(), 64)
+        );
+        assert_eq!(
+            Ipv6Network::new_from_addr(addr, 48),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            Ipv6Network::from_segments([0; 8], 129),
+            Err(Error::InvalidNetwork)
+        );
+    }
+    #[test]
+    fn point_to_point_v6() {
+        let link = Ipv6Network::from_str("2001:db8::1:0/127").unwrap();
+        assert!(link.is_point_to_point());
+        let peer = Ipv6Network::peer_of(link.last());
+        assert_eq!(peer, link.first());
+        assert!(!Ipv6Network::from_str("2001:db8::/127")
+            .unwrap()
+            .is_point_to_point());
+        assert!(!Ipv6Network::from_str("2001:db8::fdff:ffff:ffff:ff80/127")
+            .unwrap()
+            .is_point_to_point());
+        assert!(!Ipv6Network::from_str("2001:db8::1:0/126")
+            .unwrap()
+            .is_point_to_point());
+        let top = Ipv6Network::from_str("2001:db8::fdff:ffff:ffff:ff00/120").unwrap();
+        assert_eq!(top.point_to_point_links().count(), 64);
+        let boundaries = [
+            ("2001:db8::200:5eff:fdff:fffe/127", true),
+            ("2001:db8::200:5eff:fe00:0/127", false),
+            ("2001:db8::200:5eff:fe00:5212/127", false),
+            ("2001:db8::200:5eff:feff:fffe/127", false),
+            ("2001:db8::200:5eff:ff00:0/127", true),
+            ("2001:db8::fdff:ffff:ffff:ff7e/127", true),
+            ("2001:db8::fdff:ffff:ffff:fffe/127", false),
+            ("2001:db8::fe00:0:0:0/127", true),
+            ("2001:db8::ffff:ffff:ffff:fffe/127", true),
+        ];
+        for (link, expected) in boundaries.iter() {
+            let link = Ipv6Network::from_str(link).unwrap();
+            assert_eq!(link.is_point_to_point(), *expected, "{}", link);
+        }
+    }
+    #[test]
+    fn next_and_previous_network_v6() {
+        let network = Ipv6Network::new(0x2001_0db8 << 96, 32).unwrap();
+        let next = network.next_network().unwrap();
+        assert_eq!(next.first(), "2001:db9::".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(next.previous_network(), Some(network));
+        assert_eq!(
+            network.last(),
+            "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"
+                .parse::<Ipv6Addr>()
+                .unwrap()
+        );
+        let top = Ipv6Network::new(u128::MAX, 128).unwrap();
+        assert_eq!(top.next_network(), None);
+        assert_eq!(Ipv6Network::new(0, 64).unwrap().previous_network(), None);
     }
     #[test]
     fn iterate() {
@@ -312,6 +1716,452 @@ mod tests {
         assert_eq!(test.stepping, 128);
         let test2: Vec<Ipv4Network> = test.collect();
         assert_eq!(test2.len(), 2);
+        assert_eq!(test2[0], Ipv4Network::new(1, 1, 1, 0, 25).unwrap());
+        assert_eq!(test2[1], Ipv4Network::new(1, 1, 1, 128, 25).unwrap());
+    }
+    #[test]
+    fn iterate_hosts() {
+        let network = Ipv4Network::from_str("255.255.255.252/30").unwrap();
+        let hosts: Vec<Ipv4Addr> = network.into_hosts().collect();
+        assert_eq!(hosts.len(), 4);
+        assert_eq!(hosts[3], Ipv4Addr::new(255, 255, 255, 255));
+    }
+    #[test]
+    fn iterate_hosts_v6() {
+        let network = Ipv6Network::from_str("2001:db8::fffc/126").unwrap();
+        let mut hosts = network.into_hosts();
+        assert_eq!(hosts.next(), Some("2001:db8::fffc".parse().unwrap()));
+        let mut resumed = HostV6Iterator::resume(&hosts.checkpoint()).unwrap();
+        assert_eq!(resumed.next_back(), Some("2001:db8::ffff".parse().unwrap()));
+        assert_eq!(
+            HostIterator::resume(&hosts.checkpoint()).unwrap_err(),
+            Error::InvalidCheckpoint
+        );
+    }
+    #[test]
+    fn hostile_input_does_not_panic() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        let alphabet = b"0123456789abcdefABCDEF.:/ %x";
+        for _ in 0..20_000 {
+            let len = (next() % 48) as usize;
+            let text: Vec<u8> = (0..len)
+                .map(|_| alphabet[(next() % alphabet.len() as u64) as usize])
+                .collect();
+            let _ = IpNetwork::parse_ascii(&text);
+            let _ = parse::ip_addr(&text);
+        }
+        for _ in 0..2_000 {
+            let mut token = [0u8; Checkpoint::LEN];
+            token[0] = (next() % 4) as u8 + 1;
+            let narrow = next() % 2 == 0;
+            let (a, b) = match narrow {
+                true => (u128::from(next() as u32), u128::from(next() as u32)),
+                false => (
+                    u128::from(next()) << 64 | u128::from(next()),
+                    u128::from(next()) << 64 | u128::from(next()),
+                ),
+            };
+            let (current, max) = (a.min(b), a.max(b));
+            token[1..17].copy_from_slice(&current.to_be_bytes());
+            token[17..33].copy_from_slice(&max.to_be_bytes());
+            token[33] = (next() % 130) as u8;
+            token[34] = (next() % 2) as u8;
+            let checkpoint = Checkpoint::from_bytes(&token).unwrap();
+            let steps = (next() % 1000) as usize;
+            if let Ok(mut hosts) = HostIterator::resume(&checkpoint) {
+                hosts.size_hint();
+                hosts.nth(steps);
+                hosts.nth_back(steps);
+                hosts.next_back();
+                hosts.advance_to(Ipv4Addr::from(next() as u32));
+            }
+            if let Ok(mut hosts) = HostV6Iterator::resume(&checkpoint) {
+                hosts.size_hint();
+                hosts.nth(steps);
+                hosts.nth_back(steps);
+                hosts.advance_to(Ipv6Addr::from(current.wrapping_add(u128::from(next()))));
+            }
+            if let Ok(mut subnets) = NetworkV4Iterator::resume(&checkpoint) {
+                subnets.size_hint();
+                subnets.nth(steps);
+                subnets.nth_back(steps);
+                subnets.next();
+            }
+            if let Ok(mut subnets) = NetworkV6Iterator::resume(&checkpoint) {
+                subnets.size_hint();
+                subnets.nth(steps);
+                subnets.nth_back(steps);
+                subnets.next_back();
+            }
+        }
+        for cidr in 0..=32u8 {
+            for first in [0u32, u32::MAX].iter() {
+                let network = Ipv4Network {
+                    first: first & !Ipv4Network::cidr_to_hostmask(cidr),
+                    cidr,
+                };
+                network.saturating_hostcount();
+                network.next_network();
+                network.previous_network();
+                network.count_subnets(32);
+                network.into_usable_hosts().next_back();
+                network.into_subnets(cidr.saturating_add(1)).next_back();
+                let _ = network.reprefix(cidr.wrapping_sub(1));
+            }
+        }
+        for cidr in 0..=128u8 {
+            let network = Ipv6Network {
+                first: !Ipv6Network::cidr_to_hostmask(cidr),
+                cidr,
+            };
+            network.saturating_hostcount();
+            network.next_network();
+            network.into_hosts().nth(usize::MAX);
+            network.into_subnets(128).size_hint();
+        }
+    }
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalid network")]
+    fn unchecked_is_checked_in_debug() {
+        Ipv4Network::new_unchecked(1, 8);
+    }
+    #[test]
+    fn checked_hostcounts() {
+        let all = Ipv6Network::new(0, 0).unwrap();
+        assert_eq!(all.checked_hostcount(), None);
+        assert_eq!(all.saturating_hostcount(), u128::MAX);
+        let half = Ipv6Network::new(0, 1).unwrap();
+        assert_eq!(half.checked_hostcount(), Some(1 << 127));
+        assert_eq!(half.saturating_hostcount(), half.hostcount());
+        let host = Ipv4Network::new(10, 0, 0, 1, 32).unwrap();
+        assert_eq!(host.checked_hostcount(), Some(1));
+    }
+    #[test]
+    fn iterate_from_both_ends() {
+        let network = Ipv4Network::new(255, 255, 255, 252, 30).unwrap();
+        let hosts: Vec<Ipv4Addr> = network.into_hosts().rev().collect();
+        assert_eq!(hosts[0], Ipv4Addr::new(255, 255, 255, 255));
+        assert_eq!(hosts[3], Ipv4Addr::new(255, 255, 255, 252));
+        let mut hosts = network.into_hosts();
+        assert_eq!(hosts.next_back(), Some(Ipv4Addr::new(255, 255, 255, 255)));
+        assert_eq!(hosts.next(), Some(Ipv4Addr::new(255, 255, 255, 252)));
+        assert_eq!(hosts.next_back(), Some(Ipv4Addr::new(255, 255, 255, 254)));
+        assert_eq!(hosts.next(), Some(Ipv4Addr::new(255, 255, 255, 253)));
+        assert_eq!(hosts.next_back(), None);
+        assert_eq!(hosts.next(), None);
+
+        let subnets: Vec<Ipv4Network> = Ipv4Network::all(1).rev().collect();
+        assert_eq!(
+            subnets,
+            vec![
+                Ipv4Network::new(128, 0, 0, 0, 1).unwrap(),
+                Ipv4Network::new(0, 0, 0, 0, 1).unwrap()
+            ]
+        );
+        assert_eq!(Ipv6Network::all(0).next_back(), Ipv6Network::new(0, 0).ok());
+        let mut subnets = Ipv4Network::new(10, 0, 0, 0, 24).unwrap().into_subnets(26);
+        assert_eq!(
+            subnets.next_back(),
+            Ipv4Network::new(10, 0, 0, 192, 26).ok()
+        );
+        assert_eq!(subnets.next(), Ipv4Network::new(10, 0, 0, 0, 26).ok());
+        assert_eq!(subnets.count(), 2);
+
+        let excluded = [
+            Ipv4Network::new(10, 0, 0, 4, 30).unwrap(),
+            Ipv4Network::new(10, 0, 0, 0, 29).unwrap(),
+            Ipv4Network::new(10, 0, 0, 14, 31).unwrap(),
+        ];
+        let network = Ipv4Network::new(10, 0, 0, 0, 28).unwrap();
+        let forward: Vec<Ipv4Addr> = network.into_hosts_excluding(&excluded).collect();
+        let mut backward: Vec<Ipv4Addr> = network.into_hosts_excluding(&excluded).rev().collect();
+        backward.reverse();
+        assert_eq!(forward.len(), 6);
+        assert_eq!(forward, backward);
+    }
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn exact_sizes() {
+        let network = Ipv4Network::new(10, 0, 0, 0, 24).unwrap();
+        let mut hosts = network.into_hosts();
+        assert_eq!(hosts.len(), 256);
+        hosts.next();
+        hosts.next_back();
+        assert_eq!(hosts.len(), 254);
+        let mut subnets = network.into_subnets(26);
+        subnets.next();
+        assert_eq!(subnets.len(), 3);
+        assert_eq!(network.into_subnets(23).len(), 0);
+        assert_eq!(Ipv4Network::all(0).len(), 1);
+        assert_eq!(Ipv4Network::all(32).len(), 1 << 32);
+        let half = 1 << 63;
+        assert_eq!(Ipv6Network::all(63).size_hint(), (half, Some(half)));
+        assert_eq!(Ipv6Network::all(128).size_hint(), (usize::MAX, None));
+        let mut ip_hosts = IpNetwork::V6(Ipv6Network::new(0, 0).unwrap()).hosts();
+        assert_eq!(ip_hosts.size_hint(), (usize::MAX, None));
+        ip_hosts.next();
+        assert_eq!(ip_hosts.size_hint(), (usize::MAX, None));
+        let a = Ipv4Network::new(10, 0, 0, 0, 30).unwrap();
+        let b = Ipv4Network::new(10, 0, 1, 0, 31).unwrap();
+        let mut interleaved = InterleavedHostIterator::new(&[a, b], Interleave::RoundRobin);
+        interleaved.next();
+        assert_eq!(interleaved.size_hint(), (5, Some(5)));
+        let excluding = network.into_hosts_excluding(&[a]);
+        assert_eq!(excluding.size_hint(), (0, Some(256)));
+    }
+    #[test]
+    fn advance_hosts() {
+        let network = Ipv4Network::new(10, 0, 0, 0, 30).unwrap();
+        let mut hosts = network.into_hosts();
+        assert!(hosts.advance_to(Ipv4Addr::new(10, 0, 0, 2)));
+        assert!(!hosts.advance_to(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(hosts.next(), Some(Ipv4Addr::new(10, 0, 0, 2)));
+        assert!(!hosts.advance_to(Ipv4Addr::new(10, 0, 0, 4)));
+        assert_eq!(hosts.next(), None);
+
+        let mut hosts = network.into_hosts();
+        assert_eq!(hosts.nth(3), Some(Ipv4Addr::new(10, 0, 0, 3)));
+        assert_eq!(hosts.next(), None);
+        let mut hosts = network.into_hosts();
+        assert_eq!(hosts.nth_back(1), Some(Ipv4Addr::new(10, 0, 0, 2)));
+        assert_eq!(hosts.nth(2), None);
+        assert_eq!(hosts.next_back(), None);
+        let mut all = Ipv6Network::new(0, 0).unwrap().into_hosts();
+        assert_eq!(
+            all.nth(usize::MAX),
+            Some(Ipv6Addr::from(usize::MAX as u128))
+        );
+    }
+    #[test]
+    fn iterate_ip_hosts() {
+        let v4 = IpNetwork::V4(Ipv4Network::new(10, 0, 0, 0, 30).unwrap());
+        let last = v4.hosts().next_back();
+        assert_eq!(last, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))));
+        let v6 = IpNetwork::V6(Ipv6Network::new(0, 126).unwrap());
+        assert_eq!(v6.hosts().count(), 4);
+    }
+    #[test]
+    fn iterate_usable_hosts() {
+        let network = Ipv4Network::from_str("255.255.255.252/30").unwrap();
+        let hosts: Vec<Ipv4Addr> = network.into_usable_hosts().collect();
+        assert_eq!(
+            hosts,
+            vec![
+                Ipv4Addr::new(255, 255, 255, 253),
+                Ipv4Addr::new(255, 255, 255, 254)
+            ]
+        );
+        let host = Ipv4Network::new(10, 0, 0, 1, 32).unwrap();
+        assert_eq!(
+            host.into_usable_hosts().collect::<Vec<_>>(),
+            vec![host.first()]
+        );
+        let mut all = Ipv4Network::new(0, 0, 0, 0, 0).unwrap().into_usable_hosts();
+        assert_eq!(all.next(), Some(Ipv4Addr::new(0, 0, 0, 1)));
+    }
+    #[test]
+    fn iterate_hosts_excluding() {
+        let network = Ipv4Network::from_str("10.0.0.0/29").unwrap();
+        let excluded = [
+            Ipv4Network::from_str("10.0.0.6/31").unwrap(),
+            Ipv4Network::from_str("10.0.0.0/30").unwrap(),
+            Ipv4Network::from_str("10.0.0.2/31").unwrap(),
+            Ipv4Network::from_str("192.168.0.0/16").unwrap(),
+        ];
+        let hosts: Vec<Ipv4Addr> = network.into_hosts_excluding(&excluded).collect();
+        assert_eq!(
+            hosts,
+            vec![Ipv4Addr::new(10, 0, 0, 4), Ipv4Addr::new(10, 0, 0, 5)]
+        );
+        let everything = Ipv4Network::from_str("10.0.0.0/8").unwrap();
+        assert_eq!(network.into_hosts_excluding(&[everything]).next(), None);
+    }
+    #[test]
+    fn interleave_round_robin() {
+        let a = Ipv4Network::from_str("10.0.0.0/31").unwrap();
+        let b = Ipv4Network::from_str("10.1.0.0/30").unwrap();
+        let hosts: Vec<Ipv4Addr> =
+            InterleavedHostIterator::new(&[a, b], Interleave::RoundRobin).collect();
+        let expected: Vec<Ipv4Addr> = ["10.0.0.0", "10.1.0.0", "10.0.0.1", "10.1.0.1"]
+            .iter()
+            .chain(["10.1.0.2", "10.1.0.3"].iter())
+            .map(|a| a.parse().unwrap())
+            .collect();
+        assert_eq!(hosts, expected);
+    }
+    #[test]
+    fn interleave_proportional() {
+        let a = Ipv4Network::from_str("10.0.0.0/31").unwrap();
+        let b = Ipv4Network::from_str("10.1.0.0/30").unwrap();
+        let hosts: Vec<Ipv4Addr> =
+            InterleavedHostIterator::new(&[a, b], Interleave::Proportional).collect();
+        let from_a: Vec<usize> = hosts
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.octets()[1] == 0)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(hosts.len(), 6);
+        assert_eq!(from_a, vec![0, 3]);
+        assert_eq!(
+            InterleavedHostIterator::new(&[], Interleave::Proportional).next(),
+            None
+        );
+        let all = Ipv6Network::from_str("::/0").unwrap();
+        let c = Ipv6Network::from_str("2001:db8::/126").unwrap();
+        let hosts: Vec<Ipv6Addr> =
+            InterleavedHostV6Iterator::new(&[c, all], Interleave::Proportional)
+                .take(6)
+                .collect();
+        let from_c = hosts.iter().filter(|h| c.contains(h)).count();
+        assert_eq!((hosts[0], from_c), (c.first(), 1));
+        let hosts: Vec<Ipv6Addr> =
+            InterleavedHostV6Iterator::new(&[c, all], Interleave::RoundRobin)
+                .take(4)
+                .collect();
+        assert_eq!(hosts[1], Ipv6Addr::UNSPECIFIED);
+        assert_eq!(hosts[2], "2001:db8::1".parse::<Ipv6Addr>().unwrap());
+    }
+    #[test]
+    fn change_prefix() {
+        let network = Ipv6Network::from_str("2001:db8:ffff::/48").unwrap();
+        assert_eq!(network.with_prefix(48), Ok(network));
+        assert_eq!(network.with_prefix(0), Ipv6Network::new(0, 0));
+        assert_eq!(
+            network.with_prefix(33),
+            Ipv6Network::from_str("2001:db8:8000::/33")
+        );
+        assert_eq!(network.with_prefix(64), Err(Error::CidrMissMatch));
+        assert_eq!(
+            network.reprefix(128),
+            Ipv6Network::from_str("2001:db8:ffff::/128")
+        );
+        assert_eq!(network.reprefix(129), Err(Error::InvalidNetwork));
+    }
+    #[test]
+    fn count_subnets() {
+        let network = Ipv4Network::new(10, 0, 0, 0, 8).unwrap();
+        assert_eq!(network.count_subnets(8), 1);
+        assert_eq!(network.count_subnets(24), 65536);
+        assert_eq!(network.count_subnets(33), 0);
+        assert_eq!(
+            network.count_subnets(30),
+            network.into_subnets(30).count() as u128
+        );
+        assert_eq!(
+            Ipv4Network::new(0, 0, 0, 0, 0).unwrap().count_subnets(32),
+            1 << 32
+        );
+        let all = Ipv6Network::new(0, 0).unwrap();
+        assert_eq!(all.count_subnets(127), 1 << 127);
+        assert_eq!(all.count_subnets(128), u128::MAX);
+    }
+    #[test]
+    fn iterate_all() {
+        assert_eq!(Ipv4Network::all(0).count(), 1);
+        assert_eq!(Ipv4Network::all(4).count(), 16);
+        let mut networks = Ipv6Network::all(0);
+        assert_eq!(networks.next(), Some(Ipv6Network::new(0, 0).unwrap()));
+        assert_eq!(networks.next(), None);
+        let v6: Vec<Ipv6Network> = Ipv6Network::all(2).collect();
+        assert_eq!(v6.len(), 4);
+        assert_eq!(v6[3], Ipv6Network::new(3 << 126, 2).unwrap());
+    }
+    #[test]
+    fn iterate_invalid_prefix() {
+        let network = Ipv4Network::from_str("10.0.0.0/24").unwrap();
+        assert_eq!(network.into_subnets(16).next(), None);
+        assert_eq!(network.into_subnets(33).next(), None);
+        assert_eq!(network.into_subnets(24).count(), 1);
+        assert_eq!(network.into_subnets(32).count(), 256);
+        assert_eq!(
+            Err(Error::InvalidNetwork),
+            Ipv4Network::from_str("10.0.0.0/33")
+        );
+    }
+    #[test]
+    fn walk_prunes() {
+        let network = Ipv4Network::from_str("10.0.0.0/24").unwrap();
+        let blocked = Ipv4Network::from_str("10.0.0.64/26").unwrap();
+        let mut visited = 0;
+        let leaves = network.walk(2, |n, depth| {
+            visited += 1;
+            if n.is_subnet(&blocked) && n != &blocked {
+                Visit::Descend
+            } else if n == &blocked {
+                Visit::Prune
+            } else if depth == 2 {
+                Visit::Match
+            } else {
+                Visit::Descend
+            }
+        });
+        let expected: Vec<Ipv4Network> = ["10.0.0.0/26", "10.0.0.128/26", "10.0.0.192/26"]
+            .iter()
+            .map(|n| n.parse().unwrap())
+            .collect();
+        assert_eq!(leaves, expected);
+        assert_eq!(visited, 7);
+    }
+    #[test]
+    fn walk_stops_at_host_routes() {
+        let network = Ipv6Network::new(0, 127).unwrap();
+        let leaves = network.walk(8, |_, _| Visit::Descend);
+        assert!(leaves.is_empty());
+        let leaves = network.walk(8, |n, _| match n.cidr {
+            128 => Visit::Match,
+            _ => Visit::Descend,
+        });
+        assert_eq!(leaves.len(), 2);
+    }
+    #[test]
+    fn resume_subnets() {
+        let network = Ipv4Network::from_str("10.0.0.0/24").unwrap();
+        let mut subnets = network.into_subnets(26);
+        subnets.next();
+        let token = subnets.checkpoint().to_bytes();
+        let resumed = NetworkV4Iterator::resume(&Checkpoint::from_bytes(&token).unwrap());
+        assert_eq!(
+            resumed.unwrap().collect::<Vec<_>>(),
+            subnets.collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn resume_empty_subnets() {
+        let network = Ipv4Network::from_str("10.0.1.0/24").unwrap();
+        for cidr in [16, 33].iter() {
+            let subnets = network.into_subnets(*cidr);
+            let token = subnets.checkpoint().to_bytes();
+            let resumed =
+                NetworkV4Iterator::resume(&Checkpoint::from_bytes(&token).unwrap()).unwrap();
+            assert_eq!(resumed.checkpoint().to_bytes(), token);
+            assert_eq!(resumed.count(), 0);
+        }
+        let network = Ipv6Network::from_str("2001:db8::/32").unwrap();
+        let checkpoint = network.into_subnets(129).checkpoint();
+        let resumed = NetworkV6Iterator::resume(&checkpoint).unwrap();
+        assert_eq!(resumed.checkpoint(), checkpoint);
+        assert_eq!(resumed.count(), 0);
+    }
+    #[test]
+    fn resume_wrong_kind() {
+        let network = Ipv4Network::from_str("10.0.0.0/24").unwrap();
+        let checkpoint = network.into_subnets(26).checkpoint();
+        assert_eq!(
+            Err(Error::InvalidCheckpoint),
+            HostIterator::resume(&checkpoint).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::InvalidCheckpoint),
+            Checkpoint::from_bytes(&[0; 3]).map(|_| ())
+        );
     }
     #[test]
     fn test_from_string() {
@@ -330,6 +2180,60 @@ mod tests {
         assert_eq!(Err(Error::NetworkParseError), res)
     }
     #[test]
+    fn test_from_string_v6() {
+        let network = Ipv6Network::from_str("fd00::/8").unwrap();
+        assert_eq!(network, Ipv6Network::new(0xfd << 120, 8).unwrap());
+        assert_eq!(network.netmask(), "ff00::".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(
+            Ipv6Network::from_str("fd00::1/8"),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            Ipv6Network::from_str("fd00::"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            Ipv6Network::from_str("1.1.1.0/24"),
+            Err(Error::NetworkParseError)
+        );
+    }
+    #[test]
+    fn test_from_string_malformed() {
+        for input in ["1.1.1.0/24/1", "1.1.1.0/", "/24", "1.1.1.0/x", "1.1.1/24"].iter() {
+            assert_eq!(Err(Error::NetworkParseError), Ipv4Network::from_str(input));
+        }
+        assert_eq!(
+            Err(Error::InvalidNetwork),
+            Ipv4Network::from_str("1.1.1.1/24")
+        );
+        assert_eq!(
+            Ipv4Network::parse_ascii(b"0.0.0.0/0"),
+            Ok(Ipv4Network { first: 0, cidr: 0 })
+        );
+    }
+    #[test]
+    fn ip_network_order_and_hash() {
+        use std::collections::HashSet;
+        let v4 = IpNetwork::V4(Ipv4Network::from_str("255.0.0.0/8").unwrap());
+        let v6 = IpNetwork::V6(Ipv6Network::from_str("::/128").unwrap());
+        assert!(v4 < v6);
+        let mut seen = HashSet::new();
+        assert!(seen.insert(v4));
+        assert!(seen.insert(v6));
+        assert!(!seen.insert(IpNetwork::V4(Ipv4Network::new(255, 0, 0, 0, 8).unwrap())));
+    }
+    #[test]
+    fn test_display() {
+        let network = Ipv4Network::from_str("192.168.0.0/16").unwrap();
+        assert_eq!(network.to_string(), "192.168.0.0/16");
+        let v6 = IpNetwork::V6(Ipv6Network::new(0x2001_0db8 << 96, 32).unwrap());
+        assert_eq!(v6.to_string(), "2001:db8::/32");
+        let mut buffer = String::new();
+        IpNetwork::V4(network).write_to(&mut buffer).unwrap();
+        v6.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer, "192.168.0.0/162001:db8::/32");
+    }
+    #[test]
     fn test_subnet() {
         let supernet = Ipv4Network::from_str("1.0.0.0/22").unwrap();
         let subnet = Ipv4Network::from_str("1.0.1.0/24").unwrap();
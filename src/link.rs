@@ -0,0 +1,70 @@
+//! Whether addresses share a link, given the prefix length of the link
+use crate::hashing::aggregation_key;
+use crate::IpNetwork;
+use std::net::IpAddr;
+
+/// Returns true when both addresses lie in the same /`prefix` network.
+/// The prefix applies to both families and is cut to the address width.
+/// Addresses of different families never share a subnet, IPv4-mapped IPv6
+/// addresses are compared as IPv4 addresses.
+/// ```
+/// use ipnetwork::link::same_subnet;
+/// let a = "192.0.2.10".parse().unwrap();
+/// assert!(same_subnet(a, "192.0.2.200".parse().unwrap(), 24));
+/// assert!(!same_subnet(a, "192.0.2.200".parse().unwrap(), 25));
+/// assert!(!same_subnet(a, "::ffff:198.51.100.1".parse().unwrap(), 16));
+/// ```
+pub fn same_subnet(a: IpAddr, b: IpAddr, prefix: u8) -> bool {
+    aggregation_key(a, prefix, prefix) == aggregation_key(b, prefix, prefix)
+}
+
+/// Returns true when `addr` can be reached directly from an interface
+/// configured with an address in `interface_net`, without a router. All
+/// addresses of the network count, including its first and last.
+/// ```
+/// use ipnetwork::link::is_on_link;
+/// use ipnetwork::IpNetwork;
+/// let interface = IpNetwork::V6("2001:db8:1::/64".parse().unwrap());
+/// assert!(is_on_link("2001:db8:1::77".parse().unwrap(), &interface));
+/// assert!(!is_on_link("2001:db8:2::77".parse().unwrap(), &interface));
+/// ```
+pub fn is_on_link(addr: IpAddr, interface_net: &IpNetwork) -> bool {
+    let addr = match addr {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+        v4 => v4,
+    };
+    interface_net.contains(&addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn subnets() {
+        assert!(same_subnet(addr("10.0.0.1"), addr("10.255.0.1"), 8));
+        assert!(same_subnet(addr("10.0.0.1"), addr("192.0.2.1"), 0));
+        assert!(!same_subnet(addr("10.0.0.1"), addr("10.0.0.2"), 32));
+        assert!(same_subnet(addr("10.0.0.1"), addr("10.0.0.1"), 200));
+        assert!(same_subnet(addr("10.0.0.1"), addr("::ffff:10.0.0.2"), 30));
+        assert!(!same_subnet(addr("0.0.0.0"), addr("::"), 0));
+        assert!(same_subnet(addr("fe80::1"), addr("fe80::2:3"), 64));
+        assert!(!same_subnet(addr("fe80::1"), addr("fe80:0:0:1::1"), 64));
+    }
+
+    #[test]
+    fn on_link() {
+        let interface = IpNetwork::V4("192.0.2.0/31".parse().unwrap());
+        assert!(is_on_link(addr("192.0.2.0"), &interface));
+        assert!(is_on_link(addr("::ffff:192.0.2.1"), &interface));
+        assert!(!is_on_link(addr("192.0.2.2"), &interface));
+        assert!(!is_on_link(addr("::c000:200"), &interface));
+        let host = IpNetwork::V4("192.0.2.9/32".parse().unwrap());
+        assert!(is_on_link(addr("192.0.2.9"), &host));
+        assert!(!is_on_link(addr("192.0.2.8"), &host));
+    }
+}
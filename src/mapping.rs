@@ -0,0 +1,337 @@
+//! Mapping of Address and Port (MAP-E, RFC 7597 and MAP-T, RFC 7599).
+//! A Basic Mapping Rule shares IPv4 addresses between customer edges by
+//! giving each one a set of ports, both are encoded in the embedded
+//! address (EA) bits of its delegated IPv6 prefix.
+use crate::{Error, Ipv4Network, Ipv6Network};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Ports of a shared IPv4 address given to one customer edge. The port
+/// set identifier (PSID) sits `offset` bits from the left of the port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortSet {
+    offset: u8,
+    len: u8,
+    psid: u16,
+}
+
+impl PortSet {
+    pub fn psid(&self) -> u16 {
+        self.psid
+    }
+
+    /// Length of the PSID in bits, 0 when the address is not shared
+    pub fn psid_len(&self) -> u8 {
+        self.len
+    }
+
+    /// Bits right of the PSID, each block of the set holds 2^`bits` ports
+    fn block_bits(&self) -> u32 {
+        16 - u32::from(self.offset) - u32::from(self.len)
+    }
+
+    /// Values of the bits left of the PSID. With an offset, the ports
+    /// where they are all zero (0 to 1023 for the default offset of 6)
+    /// belong to no customer edge.
+    fn leading(&self) -> std::ops::Range<u32> {
+        (self.offset > 0) as u32..1 << self.offset
+    }
+
+    /// Returns true when the port is in the set
+    pub fn contains(&self, port: u16) -> bool {
+        if self.len == 0 {
+            return true;
+        }
+        let port = u32::from(port);
+        let leading = port >> (16 - u32::from(self.offset));
+        let psid = (port >> self.block_bits()) & ((1 << self.len) - 1);
+        self.leading().contains(&leading) && psid == u32::from(self.psid)
+    }
+
+    /// Number of ports in the set
+    pub fn count(&self) -> u32 {
+        match self.len {
+            0 => 1 << 16,
+            _ => self.leading().len() as u32 * (1 << self.block_bits()),
+        }
+    }
+
+    /// Returns the contiguous blocks of the set as inclusive ranges, lowest
+    /// first
+    /// ```
+    /// use ipnetwork::mapping::MapRule;
+    /// let rule = MapRule::new("2001:db8::/40".parse().unwrap(), "192.0.2.0/24".parse().unwrap(), 16, 6).unwrap();
+    /// let ce = rule.customer("2001:db8:12:3400::".parse().unwrap()).unwrap();
+    /// let blocks = ce.port_set.ranges();
+    /// assert_eq!(blocks.len(), 63);
+    /// assert_eq!(blocks[0], (1232, 1235));
+    /// assert_eq!(blocks[62], (64720, 64723));
+    /// ```
+    pub fn ranges(&self) -> Vec<(u16, u16)> {
+        if self.len == 0 {
+            return vec![(0, u16::MAX)];
+        }
+        let block = self.block_bits();
+        let psid = u32::from(self.psid) << block;
+        self.leading()
+            .map(|leading| {
+                let first = leading
+                    .checked_shl(16 - u32::from(self.offset))
+                    .unwrap_or(0)
+                    | psid;
+                (first as u16, (first | ((1 << block) - 1)) as u16)
+            })
+            .collect()
+    }
+}
+
+/// Addresses and ports of one customer edge of a MAP domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapCustomer {
+    /// The delegated end-user prefix
+    pub ipv6_prefix: Ipv6Network,
+    /// The shared IPv4 address as a /32, or the IPv4 prefix of the customer
+    /// edge when the EA bits hold fewer bits than the IPv4 suffix
+    pub ipv4: Ipv4Network,
+    pub port_set: PortSet,
+}
+
+impl MapCustomer {
+    /// Returns the MAP IPv6 address of the customer edge, in the first /64
+    /// of its prefix with the interface identifier of RFC 7597 section 6
+    pub fn ipv6_address(&self) -> Ipv6Addr {
+        let interface_id = u128::from(self.ipv4.first) << 16 | u128::from(self.port_set.psid);
+        Ipv6Addr::from(self.ipv6_prefix.first | interface_id)
+    }
+}
+
+/// Basic Mapping Rule of a MAP domain
+/// ```
+/// use ipnetwork::mapping::MapRule;
+/// // example 1 of RFC 7597 appendix A
+/// let rule = MapRule::new("2001:db8::/40".parse().unwrap(), "192.0.2.0/24".parse().unwrap(), 16, 6).unwrap();
+/// let ce = rule.customer("2001:db8:12:3400::".parse().unwrap()).unwrap();
+/// assert_eq!(ce.ipv6_prefix.to_string(), "2001:db8:12:3400::/56");
+/// assert_eq!(ce.ipv4.to_string(), "192.0.2.18/32");
+/// assert_eq!(ce.port_set.psid(), 0x34);
+/// assert_eq!(ce.ipv6_address(), "2001:db8:12:3400:0:c000:212:34".parse::<std::net::Ipv6Addr>().unwrap());
+/// assert_eq!(rule.customer_for("192.0.2.18".parse().unwrap(), 1232), Some(ce));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapRule {
+    ipv6_prefix: Ipv6Network,
+    ipv4_prefix: Ipv4Network,
+    ea_len: u8,
+    psid_offset: u8,
+}
+
+impl MapRule {
+    /// Creates a rule from the rule IPv6 prefix, the rule IPv4 prefix, the
+    /// length of the EA bits and the PSID offset (6 in most deployments).
+    /// Fails with `InvalidNetwork` when the end-user prefixes would be
+    /// longer than /64 or the PSID does not fit in a port.
+    pub fn new(
+        ipv6_prefix: Ipv6Network,
+        ipv4_prefix: Ipv4Network,
+        ea_len: u8,
+        psid_offset: u8,
+    ) -> Result<MapRule, Error> {
+        let rule = MapRule {
+            ipv6_prefix,
+            ipv4_prefix,
+            ea_len,
+            psid_offset,
+        };
+        match ea_len <= 48
+            && u32::from(ipv6_prefix.cidr) + u32::from(ea_len) <= 64
+            && u32::from(psid_offset) + u32::from(rule.psid_len()) <= 16
+        {
+            true => Ok(rule),
+            false => Err(Error::InvalidNetwork),
+        }
+    }
+
+    pub fn ipv6_prefix(&self) -> Ipv6Network {
+        self.ipv6_prefix
+    }
+
+    pub fn ipv4_prefix(&self) -> Ipv4Network {
+        self.ipv4_prefix
+    }
+
+    pub fn ea_len(&self) -> u8 {
+        self.ea_len
+    }
+
+    pub fn psid_offset(&self) -> u8 {
+        self.psid_offset
+    }
+
+    /// Bits of the IPv4 address carried in the EA bits
+    fn suffix_len(&self) -> u8 {
+        (32 - self.ipv4_prefix.cidr).min(self.ea_len)
+    }
+
+    /// Length of the PSID, 0 when every customer edge gets whole addresses
+    pub fn psid_len(&self) -> u8 {
+        self.ea_len.saturating_sub(32 - self.ipv4_prefix.cidr)
+    }
+
+    /// Length of the prefixes delegated to the customer edges
+    pub fn end_user_len(&self) -> u8 {
+        self.ipv6_prefix.cidr + self.ea_len
+    }
+
+    /// Builds the customer edge with the EA bits
+    fn customer_with_ea(&self, ea: u64) -> MapCustomer {
+        let psid_len = self.psid_len();
+        let suffix = (ea >> psid_len) as u32;
+        let ipv4_len = self.ipv4_prefix.cidr + self.suffix_len();
+        let ipv4 = Ipv4Network {
+            first: self.ipv4_prefix.first
+                | suffix.checked_shl(32 - u32::from(ipv4_len)).unwrap_or(0),
+            cidr: ipv4_len,
+        };
+        let cidr = self.end_user_len();
+        MapCustomer {
+            ipv6_prefix: Ipv6Network {
+                first: self.ipv6_prefix.first
+                    | u128::from(ea)
+                        .checked_shl(128 - u32::from(cidr))
+                        .unwrap_or(0),
+                cidr,
+            },
+            ipv4,
+            port_set: PortSet {
+                offset: self.psid_offset,
+                len: psid_len,
+                psid: (ea & ((1 << psid_len) - 1)) as u16,
+            },
+        }
+    }
+
+    /// Returns the customer edge whose end-user prefix holds the address,
+    /// None when it is outside the rule IPv6 prefix
+    pub fn customer(&self, addr: Ipv6Addr) -> Option<MapCustomer> {
+        if !self.ipv6_prefix.contains(&addr) {
+            return None;
+        }
+        let ea = u128::from(addr)
+            .checked_shr(128 - u32::from(self.end_user_len()))
+            .unwrap_or(0) as u64
+            & ((1 << self.ea_len) - 1);
+        Some(self.customer_with_ea(ea))
+    }
+
+    /// Returns the customer edge given the IPv4 address and port, as the
+    /// border relay does for traffic from the IPv4 side. None when the
+    /// address is outside the rule IPv4 prefix or the port belongs to no
+    /// port set.
+    pub fn customer_for(&self, addr: Ipv4Addr, port: u16) -> Option<MapCustomer> {
+        if !self.ipv4_prefix.contains(&addr) {
+            return None;
+        }
+        let hostmask = Ipv4Network::cidr_to_hostmask(self.ipv4_prefix.cidr);
+        let suffix = (u32::from(addr) & hostmask)
+            .checked_shr(32 - u32::from(self.ipv4_prefix.cidr) - u32::from(self.suffix_len()))
+            .unwrap_or(0);
+        let psid_len = self.psid_len();
+        let psid = match psid_len {
+            0 => 0,
+            _ => {
+                let block = 16 - u32::from(self.psid_offset) - u32::from(psid_len);
+                (u32::from(port) >> block) & ((1 << psid_len) - 1)
+            }
+        };
+        let customer = self.customer_with_ea(u64::from(suffix) << psid_len | u64::from(psid));
+        match customer.port_set.contains(port) {
+            true => Some(customer),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(ipv6: &str, ipv4: &str, ea_len: u8, offset: u8) -> MapRule {
+        MapRule::new(ipv6.parse().unwrap(), ipv4.parse().unwrap(), ea_len, offset).unwrap()
+    }
+
+    #[test]
+    fn shared_addresses() {
+        let rule = rule("2001:db8::/40", "192.0.2.0/24", 16, 6);
+        assert_eq!((rule.psid_len(), rule.end_user_len()), (8, 56));
+        let ce = rule
+            .customer("2001:db8:12:34ff::1".parse().unwrap())
+            .unwrap();
+        assert_eq!(ce.port_set.count(), 63 * 4);
+        assert_eq!(ce.port_set.ranges()[1], (2256, 2259));
+        assert!(ce.port_set.contains(2259));
+        assert!(!ce.port_set.contains(2260));
+        assert!(!ce.port_set.contains(208));
+        // every port of the address is in exactly one set
+        let addr: Ipv4Addr = "192.0.2.18".parse().unwrap();
+        for port in [1024u16, 1232, 4000, 65535].iter() {
+            let owner = rule.customer_for(addr, *port).unwrap();
+            assert!(owner.port_set.contains(*port));
+            assert_eq!(owner.ipv4.first(), addr);
+            assert_eq!(rule.customer(owner.ipv6_address()), Some(owner));
+        }
+        assert_eq!(rule.customer_for(addr, 1023), None);
+        assert_eq!(rule.customer_for("192.0.3.1".parse().unwrap(), 2000), None);
+        assert_eq!(rule.customer("2001:db9::".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn without_offset() {
+        // contiguous port blocks, as in MAP-T deployments with offset 0
+        let rule = rule("2001:db8:ffff::/48", "198.51.100.0/24", 12, 0);
+        let ce = rule
+            .customer_for("198.51.100.7".parse().unwrap(), 0x3456)
+            .unwrap();
+        assert_eq!(ce.port_set.psid(), 3);
+        assert_eq!(ce.port_set.ranges(), vec![(0x3000, 0x3fff)]);
+        assert_eq!(ce.ipv6_prefix.to_string(), "2001:db8:ffff:730::/60");
+    }
+
+    #[test]
+    fn whole_addresses_and_prefixes() {
+        // the EA bits hold the whole IPv4 suffix, no ports are shared
+        let full = rule("2001:db8::/32", "10.0.0.0/8", 24, 6);
+        let ce = full.customer_for("10.1.2.3".parse().unwrap(), 22).unwrap();
+        assert_eq!(ce.ipv4.to_string(), "10.1.2.3/32");
+        assert_eq!(ce.port_set.count(), 65536);
+        assert_eq!(ce.port_set.ranges(), vec![(0, 65535)]);
+        assert_eq!(ce.ipv6_prefix.to_string(), "2001:db8:102:300::/56");
+        // fewer EA bits than the IPv4 suffix, each edge gets a prefix
+        let short = rule("2001:db8::/32", "10.0.0.0/8", 16, 6);
+        let ce = short.customer("2001:db8:102::".parse().unwrap()).unwrap();
+        assert_eq!(ce.ipv4.to_string(), "10.1.2.0/24");
+        assert_eq!(
+            short.customer_for("10.1.2.200".parse().unwrap(), 80),
+            Some(ce)
+        );
+        // a rule without EA bits over the whole IPv6 space, one edge
+        let single = rule("::/0", "192.0.2.0/24", 0, 6);
+        assert_eq!(single.end_user_len(), 0);
+        let ce = single.customer("2001:db8::1".parse().unwrap()).unwrap();
+        assert_eq!(ce.ipv6_prefix.to_string(), "::/0");
+        assert_eq!(ce.ipv4.to_string(), "192.0.2.0/24");
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let ipv6: Ipv6Network = "2001:db8::/40".parse().unwrap();
+        let ipv4: Ipv4Network = "192.0.2.0/24".parse().unwrap();
+        assert_eq!(MapRule::new(ipv6, ipv4, 32, 6), Err(Error::InvalidNetwork));
+        assert_eq!(MapRule::new(ipv6, ipv4, 20, 6), Err(Error::InvalidNetwork));
+        let ipv6: Ipv6Network = "2001::/16".parse().unwrap();
+        assert_eq!(
+            MapRule::new(ipv6, ipv4, 24, 0).map(|r| r.psid_len()),
+            Ok(16)
+        );
+        assert_eq!(MapRule::new(ipv6, ipv4, 24, 1), Err(Error::InvalidNetwork));
+        assert_eq!(MapRule::new(ipv6, ipv4, 49, 0), Err(Error::InvalidNetwork));
+    }
+}
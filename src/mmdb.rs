@@ -0,0 +1,696 @@
+//! Reader for MaxMind DB files (MMDB), the binary format geolocation and
+//! ASN databases ship in. Needs the `mmdb` feature.
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+/// Marks the start of the metadata section at the end of the file
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+
+/// Nesting allowed for maps, arrays and pointers, so hostile files cannot
+/// exhaust the stack
+const MAX_DEPTH: usize = 64;
+
+/// A value of the data section
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Double(f64),
+    Bytes(Vec<u8>),
+    /// Unsigned integers of any width
+    Uint(u128),
+    Int(i32),
+    /// Entries in the order of the file
+    Map(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    Bool(bool),
+    Float(f32),
+}
+
+impl Value {
+    /// Returns the value of the key when this is a map
+    /// ```
+    /// use ipnetwork::mmdb::Value;
+    /// let country = Value::Map(vec![("iso_code".to_string(), Value::String("SE".to_string()))]);
+    /// assert_eq!(country.get("iso_code").and_then(Value::as_str), Some("SE"));
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follows the keys through nested maps
+    pub fn path(&self, keys: &[&str]) -> Option<&Value> {
+        keys.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Value::Uint(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Fields of the metadata every database has
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub node_count: u32,
+    /// Bits per search tree record, 24, 28 or 32
+    pub record_size: u16,
+    /// 4 or 6, IPv6 databases also hold IPv4 networks
+    pub ip_version: u16,
+    pub database_type: String,
+    /// Seconds since the Unix epoch
+    pub build_epoch: u64,
+    /// The whole metadata map
+    pub values: Value,
+}
+
+/// Decodes values of a section, pointers are relative to its start
+struct Decoder<'a> {
+    section: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.section.get(offset..end))
+            .ok_or(Error::NetworkParseError)
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> Result<u128, Error> {
+        let bytes = self.bytes(offset, len)?;
+        match len <= 16 {
+            true => Ok(bytes.iter().fold(0, |n, &byte| n << 8 | u128::from(byte))),
+            false => Err(Error::NetworkParseError),
+        }
+    }
+
+    /// Decodes the value at the offset, returns it with the offset after it
+    fn decode(&self, offset: usize, depth: usize) -> Result<(Value, usize), Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::NetworkParseError);
+        }
+        let control = self.bytes(offset, 1)?[0];
+        let mut offset = offset + 1;
+        let mut kind = control >> 5;
+        if kind == 1 {
+            let high = usize::from(control & 0x07);
+            let size = usize::from(control >> 3 & 0x03);
+            let low = self.uint(offset, size + 1)? as usize;
+            let target = match size {
+                0 => high << 8 | low,
+                1 => (high << 16 | low) + 2048,
+                2 => (high << 24 | low) + 526_336,
+                _ => low,
+            };
+            let (value, _) = self.decode(target, depth + 1)?;
+            return Ok((value, offset + size + 1));
+        }
+        if kind == 0 {
+            kind = self.bytes(offset, 1)?[0].saturating_add(7);
+            offset += 1;
+        }
+        let mut size = usize::from(control & 0x1f);
+        if size >= 29 {
+            let extra = size - 28;
+            let base = [29, 285, 65_821][extra - 1];
+            size = base + self.uint(offset, extra)? as usize;
+            offset += extra;
+        }
+        let value = match kind {
+            2 => {
+                let bytes = self.bytes(offset, size)?;
+                let s = std::str::from_utf8(bytes).map_err(|_| Error::NetworkParseError)?;
+                offset += size;
+                Value::String(s.to_string())
+            }
+            3 if size == 8 => {
+                offset += 8;
+                Value::Double(f64::from_bits(self.uint(offset - 8, 8)? as u64))
+            }
+            4 => {
+                offset += size;
+                Value::Bytes(self.bytes(offset - size, size)?.to_vec())
+            }
+            5 | 6 | 9 | 10 => {
+                let max = match kind {
+                    5 => 2,
+                    6 => 4,
+                    9 => 8,
+                    _ => 16,
+                };
+                if size > max {
+                    return Err(Error::NetworkParseError);
+                }
+                offset += size;
+                Value::Uint(self.uint(offset - size, size)?)
+            }
+            7 => {
+                let mut entries = Vec::with_capacity(size.min(64));
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset, depth + 1)?;
+                    let key = match key {
+                        Value::String(key) => key,
+                        _ => return Err(Error::NetworkParseError),
+                    };
+                    let (value, next) = self.decode(next, depth + 1)?;
+                    entries.push((key, value));
+                    offset = next;
+                }
+                Value::Map(entries)
+            }
+            8 if size <= 4 => {
+                offset += size;
+                Value::Int(self.uint(offset - size, size)? as u32 as i32)
+            }
+            11 => {
+                let mut values = Vec::with_capacity(size.min(64));
+                for _ in 0..size {
+                    let (value, next) = self.decode(offset, depth + 1)?;
+                    values.push(value);
+                    offset = next;
+                }
+                Value::Array(values)
+            }
+            14 if size <= 1 => Value::Bool(size == 1),
+            15 if size == 4 => {
+                offset += 4;
+                Value::Float(f32::from_bits(self.uint(offset - 4, 4)? as u32))
+            }
+            _ => return Err(Error::NetworkParseError),
+        };
+        Ok((value, offset))
+    }
+}
+
+/// A loaded database
+/// ```
+/// use ipnetwork::mmdb::Reader;
+/// use ipnetwork::Error;
+/// assert_eq!(Reader::from_bytes(b"not a database".to_vec()).unwrap_err(), Error::NetworkParseError);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Reader {
+    data: Vec<u8>,
+    metadata: Metadata,
+    /// Start of the data section
+    data_start: usize,
+    /// Node at the end of the path of ::/96, where the IPv4 networks of
+    /// IPv6 databases start
+    ipv4_start: u32,
+    /// Depth of `ipv4_start`
+    ipv4_depth: u8,
+}
+
+impl Reader {
+    /// Loads a database from its bytes. Fails with `NetworkParseError`
+    /// when the bytes are not a database this reader understands.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Reader, Error> {
+        let marker = data
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .ok_or(Error::NetworkParseError)?;
+        let decoder = Decoder {
+            section: &data[marker + METADATA_MARKER.len()..],
+        };
+        let (values, _) = decoder.decode(0, 0)?;
+        let field = |key| values.get(key).and_then(Value::as_u128);
+        let node_count = field("node_count").ok_or(Error::NetworkParseError)?;
+        let record_size = field("record_size").ok_or(Error::NetworkParseError)?;
+        let ip_version = field("ip_version").ok_or(Error::NetworkParseError)?;
+        let metadata = Metadata {
+            node_count: u32::try_from(node_count).map_err(|_| Error::NetworkParseError)?,
+            record_size: match record_size {
+                24 | 28 | 32 => record_size as u16,
+                _ => return Err(Error::NetworkParseError),
+            },
+            ip_version: match ip_version {
+                4 | 6 => ip_version as u16,
+                _ => return Err(Error::NetworkParseError),
+            },
+            database_type: values
+                .get("database_type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            build_epoch: field("build_epoch").unwrap_or(0) as u64,
+            values: values.clone(),
+        };
+        let tree_size = metadata.node_count as usize * usize::from(metadata.record_size) / 4;
+        let data_start = tree_size + 16;
+        if data_start > marker {
+            return Err(Error::NetworkParseError);
+        }
+        let mut reader = Reader {
+            data,
+            metadata,
+            data_start,
+            ipv4_start: 0,
+            ipv4_depth: 0,
+        };
+        if reader.metadata.ip_version == 6 {
+            let mut node = 0;
+            while reader.ipv4_depth < 96 && node < reader.metadata.node_count {
+                node = reader.record(node, false)?;
+                reader.ipv4_depth += 1;
+            }
+            reader.ipv4_start = node;
+        }
+        Ok(reader)
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Record of a node for the bit
+    fn record(&self, node: u32, bit: bool) -> Result<u32, Error> {
+        let size = usize::from(self.metadata.record_size) / 4;
+        let start = node as usize * size;
+        let bytes = self
+            .data
+            .get(start..start + size)
+            .ok_or(Error::NetworkParseError)?;
+        let be = |bytes: &[u8]| bytes.iter().fold(0, |n, &b| n << 8 | u32::from(b));
+        Ok(match (self.metadata.record_size, bit) {
+            (24, false) => be(&bytes[..3]),
+            (24, true) => be(&bytes[3..]),
+            (28, false) => u32::from(bytes[3] >> 4) << 24 | be(&bytes[..3]),
+            (28, true) => u32::from(bytes[3] & 0x0f) << 24 | be(&bytes[4..]),
+            (_, false) => be(&bytes[..4]),
+            (_, true) => be(&bytes[4..]),
+        })
+    }
+
+    /// Value a record points to, None for the empty record
+    fn value(&self, record: u32) -> Result<Option<Value>, Error> {
+        let node_count = self.metadata.node_count;
+        if record == node_count {
+            return Ok(None);
+        }
+        let offset = (record - node_count)
+            .checked_sub(16)
+            .ok_or(Error::NetworkParseError)?;
+        let decoder = Decoder {
+            section: &self.data[self.data_start..],
+        };
+        Ok(Some(decoder.decode(offset as usize, 0)?.0))
+    }
+
+    /// Follows the bits of `bits`, the top `width` of them, from the node
+    /// through at most `max_depth` nodes. Returns the record reached and
+    /// the number of bits used.
+    fn find(&self, start: u32, bits: u128, max_depth: u8) -> Result<(u32, u8), Error> {
+        let mut node = start;
+        let mut depth = 0;
+        while node < self.metadata.node_count {
+            if depth == max_depth {
+                return Ok((node, depth));
+            }
+            node = self.record(node, bits >> (127 - depth) & 1 == 1)?;
+            depth += 1;
+        }
+        Ok((node, depth))
+    }
+
+    /// Start node, address bits aligned to the top and width of the
+    /// family of the address. None for IPv6 in IPv4 databases.
+    fn root(&self, addr: &IpAddr) -> Option<(u32, u128, u8)> {
+        match (addr, self.metadata.ip_version) {
+            (IpAddr::V4(v4), 4) => Some((0, u128::from(u32::from(*v4)) << 96, 32)),
+            (IpAddr::V4(v4), _) => Some((self.ipv4_start, u128::from(u32::from(*v4)) << 96, 32)),
+            (IpAddr::V6(v6), 6) => Some((0, u128::from(*v6), 128)),
+            (IpAddr::V6(_), _) => None,
+        }
+    }
+
+    /// Returns the network of the record holding the address, with its
+    /// value. None when the database has no record for it.
+    pub fn lookup(&self, addr: &IpAddr) -> Result<Option<(IpNetwork, Value)>, Error> {
+        let (start, bits, width) = match self.root(addr) {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let (record, depth) = self.find(start, bits, width)?;
+        if record < self.metadata.node_count {
+            return Err(Error::NetworkParseError);
+        }
+        Ok(self
+            .value(record)?
+            .map(|value| (network(addr, bits, depth), value)))
+    }
+
+    /// Returns the record holding the whole network, None when the
+    /// network has no record or is split across several
+    pub fn lookup_network(&self, network: &IpNetwork) -> Result<Option<(IpNetwork, Value)>, Error> {
+        let (addr, cidr) = match network {
+            IpNetwork::V4(n) => (IpAddr::V4(n.first()), n.cidr),
+            IpNetwork::V6(n) => (IpAddr::V6(n.first()), n.cidr),
+        };
+        let (start, bits, _) = match self.root(&addr) {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let (record, depth) = self.find(start, bits, cidr)?;
+        if record < self.metadata.node_count {
+            return Ok(None);
+        }
+        Ok(self
+            .value(record)?
+            .map(|value| (self::network(&addr, bits, depth), value)))
+    }
+
+    /// Returns every network with a record and its value, sorted. The
+    /// IPv4 networks of an IPv6 database are returned as IPv4 networks and
+    /// their aliases, like `::ffff:0:0/96` and `2002::/16`, are skipped.
+    pub fn networks(&self) -> Result<Vec<(IpNetwork, Value)>, Error> {
+        let width = match self.metadata.ip_version {
+            4 => 32,
+            _ => 128,
+        };
+        let mut found = Vec::new();
+        let mut stack = vec![(0u32, 0u128, 0u8)];
+        let mut visited = 0;
+        while let Some((node, bits, depth)) = stack.pop() {
+            if node >= self.metadata.node_count {
+                if let Some(value) = self.value(node)? {
+                    let network = match (width, depth) {
+                        (32, _) => IpNetwork::V4(Ipv4Network {
+                            first: (bits >> 96) as u32,
+                            cidr: depth,
+                        }),
+                        (_, 96..=128) if bits >> 32 == 0 => IpNetwork::V4(Ipv4Network {
+                            first: bits as u32,
+                            cidr: depth - 96,
+                        }),
+                        _ => IpNetwork::V6(Ipv6Network {
+                            first: bits,
+                            cidr: depth,
+                        }),
+                    };
+                    found.push((network, value));
+                }
+                continue;
+            }
+            if depth == width {
+                return Err(Error::NetworkParseError);
+            }
+            let aliased =
+                width == 128 && node == self.ipv4_start && (bits, depth) != (0, self.ipv4_depth);
+            if aliased {
+                continue;
+            }
+            // Every node is reached once, more visits mean the tree loops
+            visited += 1;
+            if visited > self.metadata.node_count {
+                return Err(Error::NetworkParseError);
+            }
+            let top = bits | 1 << (127 - depth);
+            let (low, high) = (self.record(node, false)?, self.record(node, true)?);
+            stack.push((high, top, depth + 1));
+            stack.push((low, bits, depth + 1));
+        }
+        Ok(found)
+    }
+}
+
+/// The /`depth` network of the address, `bits` holds it aligned to the top
+fn network(addr: &IpAddr, bits: u128, depth: u8) -> IpNetwork {
+    let mask = u128::MAX.checked_shr(u32::from(depth)).unwrap_or(0);
+    match addr {
+        IpAddr::V4(_) => IpNetwork::V4(Ipv4Network {
+            first: ((bits & !mask) >> 96) as u32,
+            cidr: depth,
+        }),
+        IpAddr::V6(_) => IpNetwork::V6(Ipv6Network {
+            first: bits & !mask,
+            cidr: depth,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Control byte and size bytes of a value
+    fn header(kind: u8, size: usize, out: &mut Vec<u8>) {
+        let (first, extended) = match kind {
+            1..=7 => (kind << 5, None),
+            _ => (0, Some(kind - 7)),
+        };
+        match size {
+            0..=28 => out.push(first | size as u8),
+            _ => {
+                out.push(first | 29);
+            }
+        }
+        out.extend(extended);
+        if size > 28 {
+            out.push((size - 29) as u8);
+        }
+    }
+
+    fn string(s: &str, out: &mut Vec<u8>) {
+        header(2, s.len(), out);
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    fn uint(n: u32, out: &mut Vec<u8>) {
+        let bytes = n.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        header(6, 4 - skip, out);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+
+    /// Search tree being written, nodes have two children that are either
+    /// empty, a node or the offset of a value
+    type Tree = Vec<[Option<Result<usize, usize>>; 2]>;
+
+    /// Child node for the bit, created when missing. A child value is
+    /// pushed down to both children of the new node.
+    fn child(nodes: &mut Tree, node: usize, bit: usize) -> usize {
+        match nodes[node][bit] {
+            Some(Ok(child)) => child,
+            covering => {
+                nodes.push([covering, covering]);
+                nodes[node][bit] = Some(Ok(nodes.len() - 1));
+                nodes.len() - 1
+            }
+        }
+    }
+
+    fn insert(nodes: &mut Tree, bits: u128, cidr: u8, value: usize) {
+        let mut node = 0;
+        for depth in 0..cidr - 1 {
+            node = child(nodes, node, (bits >> (127 - depth) & 1) as usize);
+        }
+        nodes[node][(bits >> (128 - cidr) & 1) as usize] = Some(Err(value));
+    }
+
+    /// Builds a database of the networks with 24 or 28 bit records, values
+    /// are maps with a `name` string. IPv4 networks of IPv6 databases are
+    /// also written behind `::ffff:0:0/96`.
+    fn database(ip_version: u16, record_size: u16, networks: &[(&str, &str)]) -> Vec<u8> {
+        let width = match ip_version {
+            4 => 32,
+            _ => 128,
+        };
+        let mut nodes: Tree = vec![[None, None]];
+        let mut data = Vec::new();
+        let mut offsets = BTreeMap::new();
+        for (network, name) in networks {
+            let value = *offsets.entry(*name).or_insert_with(|| {
+                let offset = data.len();
+                header(7, 1, &mut data);
+                string("name", &mut data);
+                string(name, &mut data);
+                offset
+            });
+            let network: IpNetwork = IpNetwork::parse_ascii(network.as_bytes()).unwrap();
+            match (network, width) {
+                (IpNetwork::V4(n), 32) => {
+                    insert(&mut nodes, u128::from(n.first) << 96, n.cidr, value)
+                }
+                (IpNetwork::V4(n), _) => {
+                    insert(&mut nodes, u128::from(n.first), n.cidr + 96, value)
+                }
+                (IpNetwork::V6(n), _) => insert(&mut nodes, n.first, n.cidr, value),
+            }
+        }
+        if width == 128 {
+            // ::ffff:0:0/96 leads to the node of ::/96, like in real databases
+            let ipv4 = (0..96).fold(0, |node, _| match nodes[node][0] {
+                Some(Ok(child)) => child,
+                _ => panic!("no IPv4 networks"),
+            });
+            let mut node = 0;
+            for depth in 0..95u8 {
+                node = child(&mut nodes, node, usize::from((80..96).contains(&depth)));
+            }
+            nodes[node][1] = Some(Ok(ipv4));
+        }
+        let node_count = nodes.len();
+        let mut out = Vec::new();
+        for children in nodes.iter() {
+            let [low, high] = children.map(|child| match child {
+                None => node_count as u32,
+                Some(Ok(node)) => node as u32,
+                Some(Err(offset)) => (node_count + 16 + offset) as u32,
+            });
+            match record_size {
+                24 => {
+                    out.extend_from_slice(&low.to_be_bytes()[1..]);
+                    out.extend_from_slice(&high.to_be_bytes()[1..]);
+                }
+                _ => {
+                    out.extend_from_slice(&low.to_be_bytes()[1..]);
+                    out.push(((low >> 24) as u8) << 4 | (high >> 24) as u8 & 0x0f);
+                    out.extend_from_slice(&high.to_be_bytes()[1..]);
+                }
+            }
+        }
+        out.extend_from_slice(&[0; 16]);
+        out.extend_from_slice(&data);
+        out.extend_from_slice(METADATA_MARKER);
+        header(7, 5, &mut out);
+        string("node_count", &mut out);
+        uint(node_count as u32, &mut out);
+        string("record_size", &mut out);
+        uint(u32::from(record_size), &mut out);
+        string("ip_version", &mut out);
+        uint(u32::from(ip_version), &mut out);
+        string("database_type", &mut out);
+        string("Test-City", &mut out);
+        string("build_epoch", &mut out);
+        uint(1_700_000_000, &mut out);
+        out
+    }
+
+    fn name(reader: &Reader, addr: &str) -> Option<(String, String)> {
+        reader
+            .lookup(&addr.parse().unwrap())
+            .unwrap()
+            .map(|(network, value)| {
+                let name = value.get("name").and_then(Value::as_str).unwrap();
+                (network.to_string(), name.to_string())
+            })
+    }
+
+    fn pair(network: &str, name: &str) -> Option<(String, String)> {
+        Some((network.to_string(), name.to_string()))
+    }
+
+    const NETWORKS: [(&str, &str); 4] = [
+        ("10.0.0.0/8", "private"),
+        ("192.0.2.0/24", "documentation"),
+        ("192.0.2.128/25", "upper half"),
+        ("2001:db8::/32", "v6 documentation"),
+    ];
+
+    #[test]
+    fn lookups() {
+        for record_size in [24, 28].iter() {
+            let reader = Reader::from_bytes(database(6, *record_size, &NETWORKS)).unwrap();
+            assert_eq!(reader.metadata().database_type, "Test-City");
+            assert_eq!(reader.metadata().build_epoch, 1_700_000_000);
+            assert_eq!(name(&reader, "10.1.2.3"), pair("10.0.0.0/8", "private"));
+            assert_eq!(
+                name(&reader, "192.0.2.1"),
+                pair("192.0.2.0/25", "documentation")
+            );
+            assert_eq!(
+                name(&reader, "192.0.2.200"),
+                pair("192.0.2.128/25", "upper half")
+            );
+            assert_eq!(
+                name(&reader, "::ffff:10.0.0.1"),
+                pair("::ffff:10.0.0.0/104", "private")
+            );
+            assert_eq!(
+                name(&reader, "2001:db8::1"),
+                pair("2001:db8::/32", "v6 documentation")
+            );
+            assert_eq!(name(&reader, "198.51.100.1"), None);
+            assert_eq!(name(&reader, "2001:db9::1"), None);
+            let lookup = |s: &str| {
+                reader
+                    .lookup_network(&IpNetwork::parse_ascii(s.as_bytes()).unwrap())
+                    .unwrap()
+                    .map(|(network, _)| network.to_string())
+            };
+            assert_eq!(lookup("10.20.0.0/16"), Some("10.0.0.0/8".to_string()));
+            assert_eq!(lookup("192.0.2.0/24"), None);
+            assert_eq!(lookup("2001:db8:1::/48"), Some("2001:db8::/32".to_string()));
+        }
+        let reader = Reader::from_bytes(database(4, 24, &NETWORKS[..3])).unwrap();
+        assert_eq!(
+            name(&reader, "192.0.2.1"),
+            pair("192.0.2.0/25", "documentation")
+        );
+        assert_eq!(name(&reader, "2001:db8::1"), None);
+    }
+
+    #[test]
+    fn export_networks() {
+        let reader = Reader::from_bytes(database(6, 28, &NETWORKS)).unwrap();
+        let networks: Vec<String> = reader
+            .networks()
+            .unwrap()
+            .iter()
+            .map(|(network, value)| {
+                format!(
+                    "{} {}",
+                    network,
+                    value.path(&["name"]).and_then(Value::as_str).unwrap()
+                )
+            })
+            .collect();
+        assert_eq!(
+            networks,
+            vec![
+                "10.0.0.0/8 private",
+                "192.0.2.0/25 documentation",
+                "192.0.2.128/25 upper half",
+                "2001:db8::/32 v6 documentation",
+            ]
+        );
+    }
+
+    #[test]
+    fn hostile_input() {
+        let valid = database(6, 24, &NETWORKS);
+        let mut seed = 0x1234_5678u32;
+        for _ in 0..2000 {
+            let mut bytes = valid.clone();
+            for _ in 0..4 {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let at = seed as usize % bytes.len();
+                bytes[at] = (seed >> 24) as u8;
+            }
+            if let Ok(reader) = Reader::from_bytes(bytes) {
+                let _ = reader.networks();
+                let _ = reader.lookup(&"192.0.2.1".parse().unwrap());
+            }
+        }
+        // metadata that is a pointer to itself
+        let mut pointer_loop = METADATA_MARKER.to_vec();
+        pointer_loop.extend_from_slice(&[0x20, 0x00]);
+        assert!(Reader::from_bytes(pointer_loop).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! IPv6-to-IPv6 network prefix translation (NPTv6, RFC 6296). Addresses
+//! are translated between an inside and an outside prefix without
+//! changing the checksums of the transport headers.
+use crate::{Error, Ipv6Network};
+use std::net::Ipv6Addr;
+
+/// One's complement sum of two 16 bit words
+fn add(a: u16, b: u16) -> u16 {
+    let sum = u32::from(a) + u32::from(b);
+    ((sum & 0xffff) + (sum >> 16)) as u16
+}
+
+/// One's complement sum of the words of the address
+fn sum(addr: u128) -> u16 {
+    (0..8).fold(0, |total, word| add(total, (addr >> (16 * word)) as u16))
+}
+
+/// Translation between an inside and an outside prefix of the same length,
+/// /64 or shorter
+/// ```
+/// use ipnetwork::nptv6::Nptv6;
+/// use std::net::Ipv6Addr;
+/// // the example of RFC 6296 appendix B
+/// let npt = Nptv6::new("fd01:203:405::/48".parse().unwrap(), "2001:db8:1::/48".parse().unwrap()).unwrap();
+/// let inside: Ipv6Addr = "fd01:203:405:1::1234".parse().unwrap();
+/// let outside = npt.to_outside(inside).unwrap();
+/// assert_eq!(outside, "2001:db8:1:d550::1234".parse::<Ipv6Addr>().unwrap());
+/// assert_eq!(npt.to_inside(outside), Some(inside));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Nptv6 {
+    inside: Ipv6Network,
+    outside: Ipv6Network,
+    /// Added to the adjusted word when translating to the outside
+    adjustment: u16,
+}
+
+impl Nptv6 {
+    /// Fails with `CidrMissMatch` when the prefixes differ in length and
+    /// with `InvalidNetwork` when they are longer than /64
+    pub fn new(inside: Ipv6Network, outside: Ipv6Network) -> Result<Nptv6, Error> {
+        if inside.cidr != outside.cidr {
+            return Err(Error::CidrMissMatch);
+        }
+        if inside.cidr > 64 {
+            return Err(Error::InvalidNetwork);
+        }
+        Ok(Nptv6 {
+            inside,
+            outside,
+            adjustment: add(sum(inside.first), !sum(outside.first)),
+        })
+    }
+
+    pub fn inside(&self) -> Ipv6Network {
+        self.inside
+    }
+
+    pub fn outside(&self) -> Ipv6Network {
+        self.outside
+    }
+
+    /// The checksum neutral adjustment added to one word of the address
+    /// when translating to the outside, it is subtracted on the way back
+    pub fn adjustment(&self) -> u16 {
+        self.adjustment
+    }
+
+    /// Index from the right of the word taking the adjustment: the subnet
+    /// word for prefixes up to /48, otherwise the first word of the
+    /// interface identifier that is not 0xffff. None when the address
+    /// cannot be translated, as that word is 0xffff.
+    fn adjusted_word(&self, addr: u128) -> Option<u32> {
+        let word = |index: u32| (addr >> (16 * index)) as u16;
+        match self.inside.cidr <= 48 {
+            true => Some(4).filter(|&index| word(index) != 0xffff),
+            false => (0..4).rev().find(|&index| word(index) != 0xffff),
+        }
+    }
+
+    fn translate(
+        &self,
+        addr: Ipv6Addr,
+        from: &Ipv6Network,
+        to: &Ipv6Network,
+        adjustment: u16,
+    ) -> Option<Ipv6Addr> {
+        if !from.contains(&addr) {
+            return None;
+        }
+        let addr = u128::from(addr);
+        let index = self.adjusted_word(addr)?;
+        let hostmask = Ipv6Network::cidr_to_hostmask(to.cidr);
+        let translated = to.first | (addr & hostmask);
+        let word = match add((translated >> (16 * index)) as u16, adjustment) {
+            0xffff => 0,
+            word => word,
+        };
+        let cleared = translated & !(0xffff << (16 * index));
+        Some(Ipv6Addr::from(cleared | u128::from(word) << (16 * index)))
+    }
+
+    /// Translates an address of the inside prefix, None for addresses
+    /// outside it and for the addresses RFC 6296 does not translate
+    pub fn to_outside(&self, addr: Ipv6Addr) -> Option<Ipv6Addr> {
+        self.translate(addr, &self.inside, &self.outside, self.adjustment)
+    }
+
+    /// Translates an address of the outside prefix back to the inside
+    pub fn to_inside(&self, addr: Ipv6Addr) -> Option<Ipv6Addr> {
+        self.translate(addr, &self.outside, &self.inside, !self.adjustment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Ipv6Addr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn checksum_neutral() {
+        let pairs = [
+            ("fd01:203:405::/48", "2001:db8:1::/48"),
+            ("fd00:1:2:3::/64", "2001:db8:ffff:ff00::/64"),
+            ("fd00:abcd::/32", "2001:db8::/32"),
+            ("fd00:1:2:300::/56", "2001:db8:0:ff00::/56"),
+        ];
+        let hosts = [
+            "::1",
+            "::0:0:1:ffff",
+            "::ffff:ffff:ffff:1",
+            "::1234:5678:9abc:def0",
+        ];
+        for (inside, outside) in pairs.iter() {
+            let npt = Nptv6::new(inside.parse().unwrap(), outside.parse().unwrap()).unwrap();
+            for host in hosts.iter() {
+                let hostmask = Ipv6Network::cidr_to_hostmask(npt.inside.cidr);
+                let inside = Ipv6Addr::from(npt.inside.first | (u128::from(addr(host)) & hostmask));
+                let outside = npt.to_outside(inside).unwrap();
+                assert!(npt.outside.contains(&outside));
+                // the one's complement sum of the address does not change
+                assert_eq!(
+                    sum(u128::from(outside)) % 0xffff,
+                    sum(u128::from(inside)) % 0xffff
+                );
+                assert_eq!(npt.to_inside(outside), Some(inside));
+            }
+        }
+    }
+
+    #[test]
+    fn untranslated() {
+        let npt = Nptv6::new(
+            "fd01:203:405::/48".parse().unwrap(),
+            "2001:db8:1::/48".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(npt.to_outside(addr("fd01:203:405:ffff::1")), None);
+        assert_eq!(npt.to_outside(addr("fd01:203:406::1")), None);
+        assert_eq!(npt.to_inside(addr("fd01:203:405::1")), None);
+        let long = Nptv6::new(
+            "fd00:0:0:1::/64".parse().unwrap(),
+            "2001:db8:0:1::/64".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            long.to_outside(addr("fd00:0:0:1:ffff:ffff:ffff:ffff")),
+            None
+        );
+        assert_eq!(
+            Nptv6::new(
+                "fd00::/48".parse().unwrap(),
+                "2001:db8::/32".parse().unwrap()
+            ),
+            Err(Error::CidrMissMatch)
+        );
+        assert_eq!(
+            Nptv6::new(
+                "fd00::/80".parse().unwrap(),
+                "2001:db8::/80".parse().unwrap()
+            ),
+            Err(Error::InvalidNetwork)
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Allocation free parsing of addresses and prefixes from raw bytes, for
+//! buffers that are not known to be UTF-8
+use crate::Error;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Parses a decimal number of at most `max_digits` digits
+#[inline]
+fn decimal(bytes: &[u8], max_digits: usize) -> Option<u32> {
+    if bytes.is_empty() || bytes.len() > max_digits {
+        return None;
+    }
+    let mut value = 0u32;
+    for &byte in bytes {
+        let digit = byte.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        value = value * 10 + u32::from(digit);
+    }
+    Some(value)
+}
+
+/// Every byte of a lane set to one
+const LANES: u128 = u128::MAX / 0xff;
+const HIGH: u128 = LANES * 0x80;
+
+/// Sets the high bit of every byte of `x` that is zero
+#[inline(always)]
+fn zero_bytes(x: u128) -> u128 {
+    let low = LANES * 0x7f;
+    !(((x & low) + low) | x | low)
+}
+
+/// Value of a decimal octet of one to three digits, None for leading
+/// zeros and values above 255
+#[inline(always)]
+fn octet_digits(digits: &[u8]) -> Option<u32> {
+    let value = match *digits {
+        [a] => u32::from(a - b'0'),
+        [b'0', ..] => return None,
+        [a, b] => u32::from(a - b'0') * 10 + u32::from(b - b'0'),
+        [a, b, c] => u32::from(a - b'0') * 100 + u32::from(b - b'0') * 10 + u32::from(c - b'0'),
+        _ => return None,
+    };
+    match value <= 255 {
+        true => Some(value),
+        false => None,
+    }
+}
+
+/// Parses a dotted quad IPv4 address. Like `Ipv4Addr::from_str`, octets
+/// with leading zeros are rejected.
+///
+/// The whole address fits in 16 bytes, so it is classified in one u128:
+/// digits and dots are found for all bytes at once (SWAR) and only the
+/// octets are read byte by byte.
+pub(crate) fn ipv4(bytes: &[u8]) -> Option<u32> {
+    if bytes.len() < 7 || bytes.len() > 15 {
+        return None;
+    }
+    let mut lanes = [0u8; 16];
+    lanes[..bytes.len()].copy_from_slice(bytes);
+    let x = u128::from_le_bytes(lanes);
+    let used = HIGH & ((1 << (8 * bytes.len())) - 1);
+    if x & HIGH != 0 {
+        return None;
+    }
+    // With the high bits clear, adding to each byte cannot carry over
+    let above_slash = x + LANES * (0x80 - u128::from(b'0'));
+    let above_nine = x + LANES * (0x80 - u128::from(b'9') - 1);
+    let digits = above_slash & !above_nine & HIGH;
+    let mut dots = zero_bytes(x ^ (LANES * u128::from(b'.'))) & used;
+    if (digits | dots) & used != used || dots.count_ones() != 3 {
+        return None;
+    }
+    let mut addr = 0u32;
+    let mut start = 0;
+    for _ in 0..3 {
+        let dot = (dots.trailing_zeros() / 8) as usize;
+        dots &= dots - 1;
+        addr = (addr << 8) | octet_digits(&bytes[start..dot])?;
+        start = dot + 1;
+    }
+    Some((addr << 8) | octet_digits(&bytes[start..])?)
+}
+
+/// Values of the hexadecimal digits, 0xff for other bytes
+const HEX: [u8; 256] = {
+    let mut table = [0xff; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        table[byte] = match byte as u8 {
+            digit @ b'0'..=b'9' => digit - b'0',
+            digit @ b'a'..=b'f' => digit - b'a' + 10,
+            digit @ b'A'..=b'F' => digit - b'A' + 10,
+            _ => 0xff,
+        };
+        byte += 1;
+    }
+    table
+};
+
+/// Parses an IPv6 address in any of the RFC 4291 text forms, accepting
+/// the same input as `Ipv6Addr::from_str`. The input is scanned once,
+/// groups are read through a table of hexadecimal digits.
+pub(crate) fn ipv6(bytes: &[u8]) -> Option<u128> {
+    let mut groups = [0u16; 8];
+    let mut count = 0;
+    // Number of groups in front of the `::`
+    let mut gap = None;
+    let mut i = 0;
+    if bytes.starts_with(b"::") {
+        gap = Some(0);
+        i = 2;
+    }
+    while gap != Some(count) || i < bytes.len() {
+        let start = i;
+        let mut group = 0u16;
+        while i < bytes.len() && i - start < 5 {
+            match HEX[usize::from(bytes[i])] {
+                0xff => break,
+                digit => group = (group << 4) | u16::from(digit),
+            }
+            i += 1;
+        }
+        // A dotted quad may stand for the last two groups
+        if bytes.get(i) == Some(&b'.') {
+            if count > 6 {
+                return None;
+            }
+            let addr = ipv4(&bytes[start..])?;
+            groups[count] = (addr >> 16) as u16;
+            groups[count + 1] = addr as u16;
+            count += 2;
+            break;
+        }
+        if i == start || i - start > 4 || count == 8 {
+            return None;
+        }
+        groups[count] = group;
+        count += 1;
+        match bytes.get(i) {
+            None => break,
+            Some(b':') => i += 1,
+            Some(_) => return None,
+        }
+        match bytes.get(i) {
+            Some(b':') if gap.is_none() => {
+                gap = Some(count);
+                i += 1;
+            }
+            Some(b':') | None => return None,
+            Some(_) => {}
+        }
+    }
+    let groups = match gap {
+        None if count == 8 => groups,
+        // The gap stands for at least one group
+        Some(at) if count < 8 => {
+            let mut spread = [0u16; 8];
+            spread[..at].copy_from_slice(&groups[..at]);
+            spread[8 - (count - at)..].copy_from_slice(&groups[at..count]);
+            spread
+        }
+        _ => return None,
+    };
+    Some(
+        groups
+            .iter()
+            .fold(0u128, |addr, &group| (addr << 16) | u128::from(group)),
+    )
+}
+
+/// Parses a dotted quad IPv4 address from bytes
+/// ```
+/// use ipnetwork::parse::ipv4_addr;
+/// use std::net::Ipv4Addr;
+/// assert_eq!(ipv4_addr(b"192.0.2.1"), Ok(Ipv4Addr::new(192, 0, 2, 1)));
+/// ```
+pub fn ipv4_addr(bytes: &[u8]) -> Result<Ipv4Addr, Error> {
+    ipv4(bytes)
+        .map(Ipv4Addr::from)
+        .ok_or(Error::NetworkParseError)
+}
+
+/// Parses an IPv4 address that may leave out trailing octets, like the
+/// `172.16` of `172.16/12`. The missing octets are zero.
+pub(crate) fn ipv4_shorthand(bytes: &[u8]) -> Option<u32> {
+    let mut addr = 0u32;
+    let mut octets = 0;
+    for digits in bytes.split(|&b| b == b'.') {
+        if octets == 4 || digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        addr = addr << 8 | octet_digits(digits)?;
+        octets += 1;
+    }
+    addr.checked_shl(8 * (4 - octets))
+}
+
+/// Parses an IPv6 address from bytes
+/// ```
+/// use ipnetwork::parse::ipv6_addr;
+/// use std::net::Ipv6Addr;
+/// assert_eq!(ipv6_addr(b"2001:db8::1"), Ok(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+/// ```
+pub fn ipv6_addr(bytes: &[u8]) -> Result<Ipv6Addr, Error> {
+    ipv6(bytes)
+        .map(Ipv6Addr::from)
+        .ok_or(Error::NetworkParseError)
+}
+
+/// Returns true when the text is of an IPv6 address, a colon comes
+/// before any dot then
+#[inline]
+pub(crate) fn is_ipv6(bytes: &[u8]) -> bool {
+    bytes.iter().find(|&&b| b == b'.' || b == b':') == Some(&b':')
+}
+
+/// Parses an address of either family from bytes
+pub fn ip_addr(bytes: &[u8]) -> Result<IpAddr, Error> {
+    match is_ipv6(bytes) {
+        true => ipv6_addr(bytes).map(IpAddr::V6),
+        false => ipv4_addr(bytes).map(IpAddr::V4),
+    }
+}
+
+/// Parses the prefix length behind the slash
+pub(crate) fn prefix_len(bytes: &[u8]) -> Option<u8> {
+    match decimal(bytes, 3)? {
+        value if value <= 255 => Some(value as u8),
+        _ => None,
+    }
+}
+
+/// Parses a decimal octet, leading zeros allowed
+pub(crate) fn octet(bytes: &[u8]) -> Option<u8> {
+    prefix_len(bytes)
+}
+
+/// Splits `address/prefix` at the slash, which is searched from the end
+/// as the prefix is short
+pub(crate) fn split_prefix(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let slash = bytes.iter().rposition(|&b| b == b'/')?;
+    Some((&bytes[..slash], &bytes[slash + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_matches_std() {
+        let inputs = [
+            "1.2.3.4",
+            "0.0.0.0",
+            "255.255.255.255",
+            "256.0.0.0",
+            "01.2.3.4",
+            "1.2.3",
+            "1.2.3.4.5",
+            "1..2.3",
+            "1.2.3.4 ",
+            "",
+            "1000.1.1.1",
+            "a.b.c.d",
+            "123.231.132.213",
+            "0.0.0.00",
+            "1.2.3.04",
+            "255.255.255.256",
+            "1.2.3.4/",
+            "1.2.3.:",
+            "...1.2",
+            "1.2.3.4.",
+            ".1.2.3",
+            "1.2.3.4\0",
+        ];
+        for input in inputs.iter() {
+            let expected = input.parse::<Ipv4Addr>().ok().map(u32::from);
+            assert_eq!(ipv4(input.as_bytes()), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn random_input_matches_std() {
+        let alphabet = b"0123456789abcdefF.:/x\xff";
+        let mut seed = 0x853c_49e6_748f_ea9bu64;
+        for _ in 0..200_000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let len = (seed % 17) as usize;
+            let input: Vec<u8> = (0..len)
+                .map(|i| alphabet[((seed >> (4 + 3 * i)) % alphabet.len() as u64) as usize])
+                .collect();
+            let text = String::from_utf8_lossy(&input);
+            let expected = text.parse::<Ipv4Addr>().ok().map(u32::from);
+            assert_eq!(ipv4(&input), expected, "{:?}", text);
+            let expected = text.parse::<Ipv6Addr>().ok().map(u128::from);
+            assert_eq!(ipv6(&input), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn ipv6_matches_std() {
+        let inputs = [
+            "::",
+            "::1",
+            "1::",
+            "1:2:3:4:5:6:7:8",
+            "1:2:3:4:5:6:7::",
+            "::2:3:4:5:6:7:8",
+            "1:2:3:4:5:6::8",
+            "1:2:3:4:5:6:7:8::",
+            "1:2:3:4:5:6:7",
+            "1:2:3:4:5:6:7:8:9",
+            "2001:DB8::ABCD",
+            "2001:db8::12345",
+            "2001:db8:::1",
+            "2001::db8::1",
+            ":1::2",
+            "1::2:",
+            "::ffff:192.0.2.1",
+            "1:2:3:4:5:6:1.2.3.4",
+            "1:2:3:4:5:6:7:1.2.3.4",
+            "::1.2.3.4:5",
+            "1.2.3.4::",
+            "::01.2.3.4",
+            "::1.2.3",
+            "fe80::1%eth0",
+            "",
+            ":",
+            ":::",
+            "g::1",
+            "0000:0000::00:0",
+            "+1::",
+        ];
+        for input in inputs.iter() {
+            let expected = input.parse::<Ipv6Addr>().ok().map(u128::from);
+            assert_eq!(ipv6(input.as_bytes()), expected, "{}", input);
+        }
+        assert_eq!(ipv6(b"::\xff"), None);
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(
+            ip_addr(b"10.0.0.1"),
+            Ok(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+        assert_eq!(ip_addr(b"::1"), Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(ip_addr(b"10.0.0.1\n"), Err(Error::NetworkParseError));
+        assert_eq!(ipv4_addr(b"::1"), Err(Error::NetworkParseError));
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(prefix_len(b"24"), Some(24));
+        assert_eq!(prefix_len(b"255"), Some(255));
+        assert_eq!(prefix_len(b"256"), None);
+        assert_eq!(prefix_len(b"+8"), None);
+        assert_eq!(prefix_len(b""), None);
+        assert_eq!(
+            split_prefix(b"1.2.3.4/8"),
+            Some((&b"1.2.3.4"[..], &b"8"[..]))
+        );
+        assert_eq!(split_prefix(b"1.2.3.4"), None);
+    }
+
+    #[test]
+    fn ipv4_shorthands() {
+        let parsed = |s: &str| ipv4_shorthand(s.as_bytes()).map(Ipv4Addr::from);
+        assert_eq!(parsed("10"), Some(Ipv4Addr::new(10, 0, 0, 0)));
+        assert_eq!(parsed("172.16"), Some(Ipv4Addr::new(172, 16, 0, 0)));
+        assert_eq!(parsed("192.168.1"), Some(Ipv4Addr::new(192, 168, 1, 0)));
+        assert_eq!(parsed("192.0.2.1"), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        for bad in [
+            "",
+            ".",
+            "10.",
+            ".10",
+            "10..1",
+            "256",
+            "010",
+            "1.2.3.4.5",
+            "1.x",
+            "+1",
+        ]
+        .iter()
+        {
+            assert_eq!(parsed(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Address plans declared as named roles, instantiated per parent prefix
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::fmt::Write;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// A network carved out of a parent for one role of a plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub parent: IpNetwork,
+    pub role: String,
+    pub network: IpNetwork,
+}
+
+/// Ordered list of named roles and the prefix length each one needs.
+///
+/// Instantiating the template carves the roles out of a parent network in
+/// the declared order, each at the next address aligned to its size.
+/// ```
+/// use ipnetwork::plan::PlanTemplate;
+/// use ipnetwork::IpNetwork;
+/// let site: PlanTemplate = "mgmt /28, users /24, voice /25".parse().unwrap();
+/// let parent = IpNetwork::V4("10.1.0.0/22".parse().unwrap());
+/// let plan = site.instantiate(&parent).unwrap();
+/// assert_eq!(plan[1].network, IpNetwork::V4("10.1.1.0/24".parse().unwrap()));
+/// assert_eq!(plan[2].network, IpNetwork::V4("10.1.2.0/25".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanTemplate {
+    roles: Vec<(String, u8)>,
+}
+
+impl PlanTemplate {
+    pub fn new(roles: &[(&str, u8)]) -> PlanTemplate {
+        PlanTemplate {
+            roles: roles
+                .iter()
+                .map(|(name, prefix)| (name.to_string(), *prefix))
+                .collect(),
+        }
+    }
+
+    /// Allocates every role inside `parent`. Fails with
+    /// `NotEnoughAddresses` when the roles do not fit and with
+    /// `InvalidNetwork` when a role is larger than the parent.
+    pub fn instantiate(&self, parent: &IpNetwork) -> Result<Vec<Allocation>, Error> {
+        let (first, last, parent_prefix, bits) = match parent {
+            IpNetwork::V4(n) => (
+                u128::from(n.first),
+                u128::from(u32::from(n.last())),
+                n.cidr,
+                32,
+            ),
+            IpNetwork::V6(n) => (n.first, u128::from(n.last()), n.cidr, 128),
+        };
+        let mut next = Some(first);
+        let mut allocations = Vec::with_capacity(self.roles.len());
+        for (role, prefix) in &self.roles {
+            if *prefix < parent_prefix || *prefix > bits {
+                return Err(Error::InvalidNetwork);
+            }
+            let host_mask = (u128::MAX >> (128 - bits))
+                .checked_shr(u32::from(*prefix))
+                .unwrap_or(0);
+            let start = match next.and_then(|n| n.checked_add(host_mask)) {
+                Some(aligned) => aligned & !host_mask,
+                None => return Err(Error::NotEnoughAddresses),
+            };
+            let end = start | host_mask;
+            if end > last {
+                return Err(Error::NotEnoughAddresses);
+            }
+            let network = match bits {
+                32 => IpNetwork::V4(Ipv4Network {
+                    first: start as u32,
+                    cidr: *prefix,
+                }),
+                _ => IpNetwork::V6(Ipv6Network {
+                    first: start,
+                    cidr: *prefix,
+                }),
+            };
+            allocations.push(Allocation {
+                parent: *parent,
+                role: role.clone(),
+                network,
+            });
+            next = end.checked_add(1);
+        }
+        Ok(allocations)
+    }
+
+    /// Instantiates the template once for every parent
+    pub fn instantiate_all(&self, parents: &[IpNetwork]) -> Result<Vec<Allocation>, Error> {
+        let mut allocations = Vec::new();
+        for parent in parents {
+            allocations.extend(self.instantiate(parent)?);
+        }
+        Ok(allocations)
+    }
+}
+
+impl FromStr for PlanTemplate {
+    type Err = Error;
+
+    /// Parses a comma separated list of `name /prefix` roles
+    fn from_str(s: &str) -> Result<PlanTemplate, Self::Err> {
+        let mut roles = Vec::new();
+        for role in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let mut parts = role.splitn(2, '/');
+            let name = parts.next().map(str::trim).unwrap_or_default();
+            let prefix = match parts.next().map(|p| p.trim().parse::<u8>()) {
+                Some(Ok(prefix)) if !name.is_empty() => prefix,
+                _ => return Err(Error::NetworkParseError),
+            };
+            roles.push((name.to_string(), prefix));
+        }
+        Ok(PlanTemplate { roles })
+    }
+}
+
+/// Network of the tree rendered by `render_tree`
+struct Node<'a> {
+    network: IpNetwork,
+    name: &'a str,
+    children: Vec<Node<'a>>,
+}
+
+fn prefix_len(network: &IpNetwork) -> (u8, u8) {
+    match network {
+        IpNetwork::V4(n) => (n.cidr, 32),
+        IpNetwork::V6(n) => (n.cidr, 128),
+    }
+}
+
+/// Address count of a network, as a power of two once it gets long
+fn size(network: &IpNetwork) -> String {
+    let (cidr, bits) = prefix_len(network);
+    match bits - cidr {
+        host_bits if host_bits <= 32 => (1u64 << host_bits).to_string(),
+        host_bits => format!("2^{}", host_bits),
+    }
+}
+
+fn range(network: &IpNetwork) -> (u128, u128) {
+    match network {
+        IpNetwork::V4(n) => (u128::from(n.first), u128::from(n.last_bits())),
+        IpNetwork::V6(n) => (n.first, n.last_bits()),
+    }
+}
+
+/// Networks covering the addresses from `first` to `last` in the family
+/// of `like`
+fn summarize(like: &IpNetwork, first: u128, last: u128) -> Vec<IpNetwork> {
+    match like {
+        IpNetwork::V4(_) => Ipv4Network::summarize_address_range(
+            Ipv4Addr::from(first as u32),
+            Ipv4Addr::from(last as u32),
+        )
+        .into_iter()
+        .map(IpNetwork::V4)
+        .collect(),
+        IpNetwork::V6(_) => {
+            Ipv6Network::summarize_address_range(Ipv6Addr::from(first), Ipv6Addr::from(last))
+                .into_iter()
+                .map(IpNetwork::V6)
+                .collect()
+        }
+    }
+}
+
+/// Networks covering the addresses of `node` that none of its children
+/// use
+fn gaps(node: &Node) -> Vec<IpNetwork> {
+    let (first, last) = range(&node.network);
+    let mut gaps = Vec::new();
+    let mut next = Some(first);
+    for child in &node.children {
+        let (child_first, child_last) = range(&child.network);
+        match next {
+            Some(from) if from < child_first => {
+                gaps.extend(summarize(&node.network, from, child_first - 1))
+            }
+            _ => {}
+        }
+        next = child_last.checked_add(1);
+    }
+    if let Some(from) = next.filter(|from| *from <= last) {
+        gaps.extend(summarize(&node.network, from, last));
+    }
+    gaps
+}
+
+fn render_children(out: &mut String, node: &Node, indent: &str) {
+    let mut lines: Vec<(IpNetwork, Option<&Node>)> = node
+        .children
+        .iter()
+        .map(|child| (child.network, Some(child)))
+        .collect();
+    lines.extend(gaps(node).into_iter().map(|gap| (gap, None)));
+    lines.sort_by_key(|(network, _)| *network);
+    for (index, (network, child)) in lines.iter().enumerate() {
+        let last = index + 1 == lines.len();
+        let (branch, continuation) = match last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        let name = child.map_or("free", |child| child.name);
+        writeln!(
+            out,
+            "{}{}{} {} ({})",
+            indent,
+            branch,
+            network,
+            name,
+            size(network)
+        )
+        .unwrap();
+        if let Some(child) = child.filter(|child| !child.children.is_empty()) {
+            render_children(out, child, &format!("{}{}", indent, continuation));
+        }
+    }
+}
+
+/// Renders named allocations as a tree under `parent`, with the address
+/// count of every network. Allocations inside other allocations are shown
+/// below them, and the space left free in the parent and in allocations
+/// with children is listed as `free` networks. Fails with
+/// `InvalidNetwork` when an allocation is not inside the parent.
+/// ```
+/// use ipnetwork::plan::render_tree;
+/// use ipnetwork::IpNetwork;
+/// let v4 = |s: &str| IpNetwork::V4(s.parse().unwrap());
+/// let tree = render_tree(
+///     &v4("10.0.0.0/22"),
+///     &[("voice", v4("10.0.2.0/23")), ("users", v4("10.0.0.0/24")), ("printers", v4("10.0.0.0/25"))],
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     tree,
+///     "10.0.0.0/22 (1024)\n\
+///      ├── 10.0.0.0/24 users (256)\n\
+///      │   ├── 10.0.0.0/25 printers (128)\n\
+///      │   └── 10.0.0.128/25 free (128)\n\
+///      ├── 10.0.1.0/24 free (256)\n\
+///      └── 10.0.2.0/23 voice (512)\n"
+/// );
+/// ```
+pub fn render_tree(parent: &IpNetwork, allocations: &[(&str, IpNetwork)]) -> Result<String, Error> {
+    let mut sorted = allocations.to_vec();
+    if sorted.iter().any(|(_, network)| !parent.is_subnet(network)) {
+        return Err(Error::InvalidNetwork);
+    }
+    // Containing networks sort before the networks inside them
+    sorted.sort_by_key(|(name, network)| (*network, *name));
+    let mut root = Node {
+        network: *parent,
+        name: "",
+        children: Vec::new(),
+    };
+    // Path from the root to the node the next allocation is compared to
+    let mut path: Vec<Node> = Vec::new();
+    for (name, network) in sorted {
+        while path
+            .last()
+            .is_some_and(|node| !node.network.is_subnet(&network))
+        {
+            let done = path.pop().expect("path is not empty");
+            path.last_mut().unwrap_or(&mut root).children.push(done);
+        }
+        path.push(Node {
+            network,
+            name,
+            children: Vec::new(),
+        });
+    }
+    while let Some(done) = path.pop() {
+        path.last_mut().unwrap_or(&mut root).children.push(done);
+    }
+    let mut out = String::new();
+    writeln!(out, "{} ({})", parent, size(parent)).unwrap();
+    render_children(&mut out, &root, "");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> IpNetwork {
+        IpNetwork::V4(s.parse().unwrap())
+    }
+
+    #[test]
+    fn instantiate_keeps_order() {
+        let template = PlanTemplate::new(&[("voice", 25), ("mgmt", 28), ("users", 24)]);
+        let plan = template.instantiate(&v4("10.0.0.0/23")).unwrap();
+        let networks: Vec<IpNetwork> = plan.iter().map(|a| a.network).collect();
+        assert_eq!(
+            networks,
+            vec![v4("10.0.0.0/25"), v4("10.0.0.128/28"), v4("10.0.1.0/24")]
+        );
+        assert_eq!(plan[1].role, "mgmt");
+        assert_eq!(
+            template.instantiate(&v4("10.0.0.0/24")),
+            Err(Error::NotEnoughAddresses)
+        );
+    }
+
+    #[test]
+    fn instantiate_all() {
+        let template: PlanTemplate = "p2p /31, loopback /32".parse().unwrap();
+        let parents = [v4("10.0.0.0/30"), v4("255.255.255.252/30")];
+        let plan = template.instantiate_all(&parents).unwrap();
+        assert_eq!(plan.len(), 4);
+        assert_eq!(plan[3].network, v4("255.255.255.254/32"));
+        assert_eq!(plan[3].parent, parents[1]);
+        let v6 = IpNetwork::V6(Ipv6Network::new(0x2001_0db8 << 96, 48).unwrap());
+        let lan = PlanTemplate::new(&[("loopback", 128), ("lan2", 64)]);
+        let plan = lan.instantiate(&v6).unwrap();
+        assert_eq!(
+            plan[1].network,
+            IpNetwork::V6(Ipv6Network::new((0x2001_0db8 << 96) | (1 << 64), 64).unwrap())
+        );
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert_eq!(
+            "users 24".parse::<PlanTemplate>(),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!("/24".parse::<PlanTemplate>(), Err(Error::NetworkParseError));
+        let template = PlanTemplate::new(&[("big", 16)]);
+        assert_eq!(
+            template.instantiate(&v4("10.0.0.0/24")),
+            Err(Error::InvalidNetwork)
+        );
+    }
+
+    #[test]
+    fn render_trees() {
+        let parent = v4("10.0.0.0/24");
+        assert_eq!(
+            render_tree(&parent, &[]).unwrap(),
+            "10.0.0.0/24 (256)\n└── 10.0.0.0/24 free (256)\n"
+        );
+        let tree = render_tree(
+            &parent,
+            &[
+                ("all", v4("10.0.0.0/24")),
+                ("b", v4("10.0.0.192/26")),
+                ("a", v4("10.0.0.64/26")),
+                ("host", v4("10.0.0.65/32")),
+            ],
+        )
+        .unwrap();
+        let expected = "10.0.0.0/24 (256)\n\
+                        └── 10.0.0.0/24 all (256)\n\
+                        \x20   ├── 10.0.0.0/26 free (64)\n\
+                        \x20   ├── 10.0.0.64/26 a (64)\n\
+                        \x20   │   ├── 10.0.0.64/32 free (1)\n\
+                        \x20   │   ├── 10.0.0.65/32 host (1)\n\
+                        \x20   │   ├── 10.0.0.66/31 free (2)\n\
+                        \x20   │   ├── 10.0.0.68/30 free (4)\n\
+                        \x20   │   ├── 10.0.0.72/29 free (8)\n\
+                        \x20   │   ├── 10.0.0.80/28 free (16)\n\
+                        \x20   │   └── 10.0.0.96/27 free (32)\n\
+                        \x20   ├── 10.0.0.128/26 free (64)\n\
+                        \x20   └── 10.0.0.192/26 b (64)\n";
+        assert_eq!(tree, expected);
+        let v6 = IpNetwork::V6("2001:db8::/32".parse().unwrap());
+        let lan = IpNetwork::V6("2001:db8:ffff:ffff::/64".parse().unwrap());
+        let tree = render_tree(&v6, &[("lan", lan)]).unwrap();
+        assert!(tree.starts_with("2001:db8::/32 (2^96)\n"));
+        assert!(tree.ends_with("└── 2001:db8:ffff:ffff::/64 lan (2^64)\n"));
+        assert_eq!(
+            render_tree(&parent, &[("out", v4("10.0.1.0/24"))]),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            render_tree(&parent, &[("v6", lan)]),
+            Err(Error::InvalidNetwork)
+        );
+    }
+}
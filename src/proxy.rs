@@ -0,0 +1,140 @@
+//! Client addresses of requests that passed through trusted reverse
+//! proxies, from the `X-Forwarded-For` header
+use crate::IpNetwork;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// Networks of the proxies whose `X-Forwarded-For` entries are believed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNetwork>,
+}
+
+/// Reads an address of the chain. Proxies may add a port, as in
+/// `192.0.2.1:8080` or `[2001:db8::1]:443`.
+fn hop(entry: &str) -> Option<IpAddr> {
+    let entry = entry.trim();
+    if let Ok(addr) = IpAddr::from_str(entry) {
+        return Some(addr);
+    }
+    if let Ok(addr) = SocketAddr::from_str(entry) {
+        return Some(addr.ip());
+    }
+    let inner = entry.strip_prefix('[')?.strip_suffix(']')?;
+    IpAddr::from_str(inner).ok()
+}
+
+/// IPv4 clients of dual stack sockets show up as IPv4-mapped addresses
+fn unmap(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => addr,
+        },
+        v4 => v4,
+    }
+}
+
+impl TrustedProxies {
+    pub fn new(networks: &[IpNetwork]) -> TrustedProxies {
+        TrustedProxies {
+            networks: networks.to_vec(),
+        }
+    }
+
+    /// Returns true when the address belongs to a trusted proxy
+    pub fn is_trusted(&self, addr: &IpAddr) -> bool {
+        let addr = unmap(*addr);
+        self.networks.iter().any(|network| network.contains(&addr))
+    }
+
+    /// Returns the address of the client that sent the request. `peer` is
+    /// the address the request was received from and `forwarded_for` the
+    /// values of the `X-Forwarded-For` headers in the order they appear.
+    ///
+    /// The chain is walked from the right, the first address not belonging
+    /// to a trusted proxy is the client. Entries left of it were written by
+    /// the client and are not looked at. When every hop is trusted the
+    /// leftmost one is returned. Returns None when a trusted proxy passed
+    /// an entry that is not an address, as no client can be trusted then.
+    /// ```
+    /// use ipnetwork::proxy::TrustedProxies;
+    /// use ipnetwork::IpNetwork;
+    /// use std::net::IpAddr;
+    /// let proxies = TrustedProxies::new(&[IpNetwork::V4("10.0.0.0/8".parse().unwrap())]);
+    /// let peer: IpAddr = "10.0.0.1".parse().unwrap();
+    /// let client = proxies.client_address(peer, &["1.1.1.1, 198.51.100.7, 10.1.2.3"]);
+    /// assert_eq!(client, Some("198.51.100.7".parse().unwrap()));
+    /// ```
+    pub fn client_address(&self, peer: IpAddr, forwarded_for: &[&str]) -> Option<IpAddr> {
+        let peer = unmap(peer);
+        if !self.is_trusted(&peer) {
+            return Some(peer);
+        }
+        let mut client = peer;
+        let entries = forwarded_for
+            .iter()
+            .flat_map(|header| header.split(','))
+            .filter(|entry| !entry.trim().is_empty());
+        for entry in entries.rev() {
+            client = unmap(hop(entry)?);
+            if !self.is_trusted(&client) {
+                break;
+            }
+        }
+        Some(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(&[
+            IpNetwork::V4("10.0.0.0/8".parse().unwrap()),
+            IpNetwork::V6("fd00::/8".parse().unwrap()),
+        ])
+    }
+
+    #[test]
+    fn walk_chain() {
+        let proxies = proxies();
+        let peer = addr("10.0.0.1");
+        assert_eq!(
+            proxies.client_address(addr("203.0.113.9"), &["198.51.100.7"]),
+            Some(addr("203.0.113.9"))
+        );
+        assert_eq!(proxies.client_address(peer, &[]), Some(peer));
+        assert_eq!(
+            proxies.client_address(peer, &["192.0.2.1, 10.2.0.1", "fd00::1,10.3.0.1"]),
+            Some(addr("192.0.2.1"))
+        );
+        assert_eq!(
+            proxies.client_address(peer, &["not-an-address, 2001:db8::5"]),
+            Some(addr("2001:db8::5"))
+        );
+        assert_eq!(
+            proxies.client_address(peer, &["10.9.9.9, 10.8.8.8"]),
+            Some(addr("10.9.9.9"))
+        );
+        assert_eq!(proxies.client_address(peer, &["192.0.2.1, unknown"]), None);
+        assert_eq!(
+            proxies.client_address(addr("::ffff:10.0.0.1"), &["::ffff:192.0.2.1"]),
+            Some(addr("192.0.2.1"))
+        );
+    }
+
+    #[test]
+    fn entries_with_ports() {
+        assert_eq!(hop(" 192.0.2.1:8080"), Some(addr("192.0.2.1")));
+        assert_eq!(hop("[2001:db8::1]:443"), Some(addr("2001:db8::1")));
+        assert_eq!(hop("[2001:db8::1]"), Some(addr("2001:db8::1")));
+        assert_eq!(hop("2001:db8::1"), Some(addr("2001:db8::1")));
+        assert_eq!(hop("_hidden"), None);
+    }
+}
@@ -0,0 +1,429 @@
+//! The network functions of Python's `ipaddress` module, with the same
+//! input, results and edge cases, for tools ported from Python whose
+//! output is diffed against the original.
+//!
+//! Python reports bad input through exceptions, these map to `Error`:
+//!
+//! - `ValueError` for text that is no network: `NetworkParseError`
+//! - `ValueError` for host bits under `strict`, and for prefix lengths
+//!   or networks that do not fit the call: `InvalidNetwork`
+//! - `TypeError` for networks of different families: `CidrMissMatch`
+//!
+//! Python accepts a scope id on IPv6 network addresses since 3.9, this
+//! module does not.
+use crate::bits::AddressBits;
+use crate::{parse, Error, IpNetwork, Network};
+use std::cmp::Ordering;
+
+/// Reads the digits of a prefix length, any number of them, leading
+/// zeros allowed
+fn prefix_digits(bytes: &[u8], bits: u8) -> Option<u8> {
+    if bytes.is_empty() || !bytes.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let value = bytes.iter().fold(0u32, |value, digit| {
+        value
+            .saturating_mul(10)
+            .saturating_add(u32::from(digit - b'0'))
+    });
+    match value <= u32::from(bits) {
+        true => Some(value as u8),
+        false => None,
+    }
+}
+
+/// Prefix length of an IPv4 netmask, or of a hostmask when it is none
+fn ipv4_mask(bytes: &[u8]) -> Option<u8> {
+    let mask = parse::ipv4(bytes)?;
+    [mask, !mask]
+        .iter()
+        .find(|mask| mask.leading_ones() + mask.trailing_zeros() == 32)
+        .map(|mask| mask.leading_ones() as u8)
+}
+
+/// Builds the network, clearing the host bits unless `strict`
+fn network<B: AddressBits>(first: B, cidr: u8, strict: bool) -> Result<Network<B>, Error> {
+    let network = Network::new_unchecked(first, B::BITS).reprefix(cidr)?;
+    match strict && network.first != first {
+        true => Err(Error::InvalidNetwork),
+        false => Ok(network),
+    }
+}
+
+/// Parses a network like `ipaddress.ip_network(address, strict)`. An
+/// address without prefix is a /32 or /128, IPv4 networks also take a
+/// netmask or hostmask as prefix. With `strict`, host bits are an error,
+/// without they are cleared.
+/// ```
+/// use ipnetwork::python::ip_network;
+/// use ipnetwork::{Error, IpNetwork};
+/// let network = |s: &str| s.parse::<IpNetwork>().unwrap();
+/// assert_eq!(ip_network("192.0.2.0/255.255.255.0", true), Ok(network("192.0.2.0/24")));
+/// assert_eq!(ip_network("192.0.2.0/0.0.0.255", true), Ok(network("192.0.2.0/24")));
+/// assert_eq!(ip_network("192.0.2.1/24", true), Err(Error::InvalidNetwork));
+/// assert_eq!(ip_network("192.0.2.1/24", false), Ok(network("192.0.2.0/24")));
+/// assert_eq!(ip_network("2001:db8::", true), Ok(network("2001:db8::/128")));
+/// ```
+pub fn ip_network(address: &str, strict: bool) -> Result<IpNetwork, Error> {
+    let bytes = address.as_bytes();
+    let (addr, prefix) = match parse::split_prefix(bytes) {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (bytes, None),
+    };
+    if addr.contains(&b'/') {
+        return Err(Error::NetworkParseError);
+    }
+    match parse::is_ipv6(addr) {
+        true => {
+            let first = parse::ipv6(addr).ok_or(Error::NetworkParseError)?;
+            let cidr = match prefix {
+                Some(prefix) => prefix_digits(prefix, 128).ok_or(Error::NetworkParseError)?,
+                None => 128,
+            };
+            network(first, cidr, strict).map(IpNetwork::V6)
+        }
+        false => {
+            let first = parse::ipv4(addr).ok_or(Error::NetworkParseError)?;
+            let cidr = match prefix {
+                Some(prefix) => prefix_digits(prefix, 32)
+                    .or_else(|| ipv4_mask(prefix))
+                    .ok_or(Error::NetworkParseError)?,
+                None => 32,
+            };
+            network(first, cidr, strict).map(IpNetwork::V4)
+        }
+    }
+}
+
+/// Prefix length of the subnets `subnets` iterates over
+fn subnet_prefix<B: AddressBits>(
+    network: &Network<B>,
+    prefixlen_diff: u8,
+    new_prefix: Option<u8>,
+) -> Result<u8, Error> {
+    if network.cidr == B::BITS {
+        return Ok(B::BITS);
+    }
+    let new_cidr = match new_prefix {
+        Some(new_prefix) if new_prefix < network.cidr || prefixlen_diff != 1 => {
+            return Err(Error::InvalidNetwork)
+        }
+        Some(new_prefix) => u16::from(new_prefix),
+        None => u16::from(network.cidr) + u16::from(prefixlen_diff),
+    };
+    match new_cidr <= u16::from(B::BITS) {
+        true => Ok(new_cidr as u8),
+        false => Err(Error::InvalidNetwork),
+    }
+}
+
+/// Iterates over the subnets like `network.subnets(prefixlen_diff,
+/// new_prefix)`, where Python defaults `prefixlen_diff` to 1. A /32 or
+/// /128 is its only subnet, whatever the arguments. Fails with
+/// `InvalidNetwork` when `new_prefix` is shorter than the prefix, is given
+/// along with a `prefixlen_diff` other than 1, or the subnets would be
+/// longer than the address.
+/// ```
+/// use ipnetwork::python::subnets;
+/// use ipnetwork::{Error, IpNetwork};
+/// let network: IpNetwork = "192.0.2.0/24".parse().unwrap();
+/// let halves: Vec<String> = subnets(&network, 1, None).unwrap().map(|n| n.to_string()).collect();
+/// assert_eq!(halves, ["192.0.2.0/25", "192.0.2.128/25"]);
+/// assert_eq!(subnets(&network, 2, None).unwrap().count(), 4);
+/// assert_eq!(subnets(&network, 1, Some(26)).unwrap().count(), 4);
+/// assert_eq!(subnets(&network, 2, Some(26)).err(), Some(Error::InvalidNetwork));
+/// assert_eq!(subnets(&network, 9, None).err(), Some(Error::InvalidNetwork));
+/// ```
+pub fn subnets(
+    network: &IpNetwork,
+    prefixlen_diff: u8,
+    new_prefix: Option<u8>,
+) -> Result<impl Iterator<Item = IpNetwork>, Error> {
+    let (v4, v6) = match network {
+        IpNetwork::V4(n) => (
+            Some(n.into_subnets(subnet_prefix(n, prefixlen_diff, new_prefix)?)),
+            None,
+        ),
+        IpNetwork::V6(n) => (
+            None,
+            Some(n.into_subnets(subnet_prefix(n, prefixlen_diff, new_prefix)?)),
+        ),
+    };
+    Ok(v4
+        .into_iter()
+        .flatten()
+        .map(IpNetwork::V4)
+        .chain(v6.into_iter().flatten().map(IpNetwork::V6)))
+}
+
+fn supernet_of<B: AddressBits>(
+    network: &Network<B>,
+    prefixlen_diff: u8,
+    new_prefix: Option<u8>,
+) -> Result<Network<B>, Error> {
+    if network.cidr == 0 {
+        return Ok(*network);
+    }
+    let new_cidr = match new_prefix {
+        Some(new_prefix) if new_prefix > network.cidr || prefixlen_diff != 1 => {
+            return Err(Error::InvalidNetwork)
+        }
+        Some(new_prefix) => new_prefix,
+        None => network
+            .cidr
+            .checked_sub(prefixlen_diff)
+            .ok_or(Error::InvalidNetwork)?,
+    };
+    network.reprefix(new_cidr)
+}
+
+/// Returns the supernet like `network.supernet(prefixlen_diff,
+/// new_prefix)`, where Python defaults `prefixlen_diff` to 1. A /0 is its
+/// own supernet. Fails with `InvalidNetwork` when `new_prefix` is longer
+/// than the prefix, is given along with a `prefixlen_diff` other than 1,
+/// or `prefixlen_diff` is more than the prefix length.
+/// ```
+/// use ipnetwork::python::supernet;
+/// use ipnetwork::{Error, IpNetwork};
+/// let network = |s: &str| s.parse::<IpNetwork>().unwrap();
+/// assert_eq!(supernet(&network("192.0.2.0/24"), 1, None), Ok(network("192.0.2.0/23")));
+/// assert_eq!(supernet(&network("192.0.2.0/24"), 1, Some(20)), Ok(network("192.0.0.0/20")));
+/// assert_eq!(supernet(&network("::/0"), 3, None), Ok(network("::/0")));
+/// assert_eq!(supernet(&network("192.0.2.0/24"), 25, None), Err(Error::InvalidNetwork));
+/// ```
+pub fn supernet(
+    network: &IpNetwork,
+    prefixlen_diff: u8,
+    new_prefix: Option<u8>,
+) -> Result<IpNetwork, Error> {
+    match network {
+        IpNetwork::V4(n) => supernet_of(n, prefixlen_diff, new_prefix).map(IpNetwork::V4),
+        IpNetwork::V6(n) => supernet_of(n, prefixlen_diff, new_prefix).map(IpNetwork::V6),
+    }
+}
+
+fn halves<B: AddressBits>(network: &Network<B>) -> (Network<B>, Network<B>) {
+    let low = Network::new_unchecked(network.first, network.cidr + 1);
+    let high = Network::new_unchecked(
+        network.first | Network::<B>::cidr_to_stepping(network.cidr + 1),
+        network.cidr + 1,
+    );
+    (low, high)
+}
+
+fn exclude<B: AddressBits>(
+    network: &Network<B>,
+    other: &Network<B>,
+) -> Result<Vec<Network<B>>, Error> {
+    if !network.is_subnet(other) {
+        return Err(Error::InvalidNetwork);
+    }
+    let mut remaining = Vec::new();
+    if network == other {
+        return Ok(remaining);
+    }
+    let (mut s1, mut s2) = halves(network);
+    while s1 != *other && s2 != *other {
+        match s1.is_subnet(other) {
+            true => {
+                remaining.push(s2);
+                (s1, s2) = halves(&s1);
+            }
+            false => {
+                remaining.push(s1);
+                (s1, s2) = halves(&s2);
+            }
+        }
+    }
+    remaining.push(match s1 == *other {
+        true => s2,
+        false => s1,
+    });
+    Ok(remaining)
+}
+
+/// Returns the rest of the network without `other`, like
+/// `network.address_exclude(other)` and in the order Python yields it.
+/// Fails with `CidrMissMatch` for networks of different families and
+/// with `InvalidNetwork` when `other` is not within the network.
+/// ```
+/// use ipnetwork::python::address_exclude;
+/// use ipnetwork::IpNetwork;
+/// let network = |s: &str| s.parse::<IpNetwork>().unwrap();
+/// let rest = address_exclude(&network("192.0.2.0/28"), &network("192.0.2.1/32")).unwrap();
+/// let rest: Vec<String> = rest.iter().map(|n| n.to_string()).collect();
+/// assert_eq!(rest, ["192.0.2.8/29", "192.0.2.4/30", "192.0.2.2/31", "192.0.2.0/32"]);
+/// ```
+pub fn address_exclude(network: &IpNetwork, other: &IpNetwork) -> Result<Vec<IpNetwork>, Error> {
+    match (network, other) {
+        (IpNetwork::V4(n), IpNetwork::V4(o)) => {
+            Ok(exclude(n, o)?.into_iter().map(IpNetwork::V4).collect())
+        }
+        (IpNetwork::V6(n), IpNetwork::V6(o)) => {
+            Ok(exclude(n, o)?.into_iter().map(IpNetwork::V6).collect())
+        }
+        _ => Err(Error::CidrMissMatch),
+    }
+}
+
+/// Returns true when the networks share an address, like
+/// `a.overlaps(b)`. Networks of different families never overlap.
+pub fn overlaps(a: &IpNetwork, b: &IpNetwork) -> bool {
+    a.overlaps(b)
+}
+
+/// Orders the networks like `a.compare_networks(b)`: by network address,
+/// then by netmask, so a shorter prefix comes first. Python's -1, 0 and 1
+/// are `Less`, `Equal` and `Greater`. Fails with `CidrMissMatch` for
+/// networks of different families.
+/// ```
+/// use ipnetwork::python::compare_networks;
+/// use ipnetwork::IpNetwork;
+/// use std::cmp::Ordering;
+/// let network = |s: &str| s.parse::<IpNetwork>().unwrap();
+/// assert_eq!(compare_networks(&network("192.0.2.1/32"), &network("192.0.2.2/32")), Ok(Ordering::Less));
+/// assert_eq!(compare_networks(&network("192.0.2.0/24"), &network("192.0.2.0/25")), Ok(Ordering::Less));
+/// ```
+pub fn compare_networks(a: &IpNetwork, b: &IpNetwork) -> Result<Ordering, Error> {
+    let compare = |a: (u128, u8), b: (u128, u8)| a.cmp(&b);
+    match (a, b) {
+        (IpNetwork::V4(a), IpNetwork::V4(b)) => Ok(compare(
+            (u128::from(a.first), a.cidr),
+            (u128::from(b.first), b.cidr),
+        )),
+        (IpNetwork::V6(a), IpNetwork::V6(b)) => Ok(compare((a.first, a.cidr), (b.first, b.cidr))),
+        _ => Err(Error::CidrMissMatch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_like_ip_network() {
+        let accepted = [
+            ("10.0.0.0/8", "10.0.0.0/8"),
+            ("10.0.0.0/008", "10.0.0.0/8"),
+            ("10.0.0.0/255.0.0.0", "10.0.0.0/8"),
+            ("10.0.0.0/0.255.255.255", "10.0.0.0/8"),
+            ("0.0.0.0/0.0.0.0", "0.0.0.0/0"),
+            ("10.0.0.1/255.255.255.255", "10.0.0.1/32"),
+            ("10.0.0.1", "10.0.0.1/32"),
+            ("::ffff:10.0.0.0/104", "::ffff:10.0.0.0/104"),
+            ("2001:db8::/0032", "2001:db8::/32"),
+            ("::", "::/128"),
+        ];
+        for (input, expected) in accepted.iter() {
+            assert_eq!(ip_network(input, true), Ok(network(expected)), "{}", input);
+        }
+        let rejected = [
+            "10.0.0.0/33",
+            "10.0.0.0/",
+            "10.0.0.0/+8",
+            "10.0.0.0/ 8",
+            "10.0.0.0/8/8",
+            "10.0.0.0/255.0.255.0",
+            "010.0.0.0/8",
+            "10.0.0/8",
+            "2001:db8::/129",
+            "2001:db8::/ffff::",
+            "fe80::%eth0/64",
+            "",
+        ];
+        for input in rejected.iter() {
+            assert_eq!(
+                ip_network(input, false),
+                Err(Error::NetworkParseError),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(ip_network("10.0.0.1/8", true), Err(Error::InvalidNetwork));
+        assert_eq!(
+            ip_network("2001:db8::1/64", false),
+            Ok(network("2001:db8::/64"))
+        );
+    }
+
+    #[test]
+    fn resize_like_python() {
+        let host = network("10.0.0.1/32");
+        let only: Vec<IpNetwork> = subnets(&host, 5, Some(8)).unwrap().collect();
+        assert_eq!(only, vec![host]);
+        let net = network("10.0.0.0/8");
+        assert_eq!(
+            subnets(&net, 0, None).unwrap().collect::<Vec<_>>(),
+            vec![net]
+        );
+        assert_eq!(subnets(&net, 1, Some(7)).err(), Some(Error::InvalidNetwork));
+        assert_eq!(subnets(&net, 24, None).unwrap().count(), 1 << 24);
+        assert_eq!(subnets(&net, 25, None).err(), Some(Error::InvalidNetwork));
+        let v6 = network("2001:db8::/127");
+        let halves: Vec<IpNetwork> = subnets(&v6, 1, None).unwrap().collect();
+        assert_eq!(
+            halves,
+            vec![network("2001:db8::/128"), network("2001:db8::1/128")]
+        );
+        assert_eq!(supernet(&net, 8, None), Ok(network("0.0.0.0/0")));
+        assert_eq!(supernet(&net, 9, None), Err(Error::InvalidNetwork));
+        assert_eq!(supernet(&net, 1, Some(9)), Err(Error::InvalidNetwork));
+        assert_eq!(supernet(&net, 2, Some(4)), Err(Error::InvalidNetwork));
+        assert_eq!(
+            supernet(&network("0.0.0.0/0"), 1, Some(4)),
+            Ok(network("0.0.0.0/0"))
+        );
+    }
+
+    #[test]
+    fn exclude_like_python() {
+        // list(ip_network('10.0.0.0/24').address_exclude(ip_network('10.0.0.64/26')))
+        let rest = address_exclude(&network("10.0.0.0/24"), &network("10.0.0.64/26")).unwrap();
+        assert_eq!(rest, vec![network("10.0.0.128/25"), network("10.0.0.0/26")]);
+        let rest =
+            address_exclude(&network("2001:db8::/126"), &network("2001:db8::3/128")).unwrap();
+        assert_eq!(
+            rest,
+            vec![network("2001:db8::/127"), network("2001:db8::2/128")]
+        );
+        let whole = network("0.0.0.0/0");
+        assert_eq!(address_exclude(&whole, &whole), Ok(vec![]));
+        assert_eq!(
+            address_exclude(&whole, &network("0.0.0.0/32"))
+                .unwrap()
+                .len(),
+            32
+        );
+        assert_eq!(
+            address_exclude(&network("10.0.0.0/24"), &network("10.0.0.0/8")),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            address_exclude(&whole, &network("::/0")),
+            Err(Error::CidrMissMatch)
+        );
+    }
+
+    #[test]
+    fn compare_like_python() {
+        let pairs = [
+            ("192.0.2.1/32", "192.0.2.0/32", Ordering::Greater),
+            ("192.0.2.0/32", "192.0.2.0/32", Ordering::Equal),
+            ("10.0.0.0/8", "9.0.0.0/32", Ordering::Greater),
+            ("2001:db8::/32", "2001:db8::/48", Ordering::Less),
+        ];
+        for (a, b, expected) in pairs.iter() {
+            assert_eq!(compare_networks(&network(a), &network(b)), Ok(*expected));
+        }
+        assert_eq!(
+            compare_networks(&network("::/0"), &network("0.0.0.0/0")),
+            Err(Error::CidrMissMatch)
+        );
+        assert!(overlaps(&network("10.0.0.0/8"), &network("10.1.0.0/16")));
+        assert!(!overlaps(&network("::/0"), &network("0.0.0.0/0")));
+    }
+}
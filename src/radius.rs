@@ -0,0 +1,297 @@
+//! Codecs for the RADIUS attributes that carry routes and delegated
+//! prefixes (RFC 2865, RFC 3162 and RFC 4818)
+use crate::{parse, Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const FRAMED_ROUTE: u8 = 22;
+pub const FRAMED_IPV6_PREFIX: u8 = 97;
+pub const FRAMED_IPV6_ROUTE: u8 = 99;
+pub const DELEGATED_IPV6_PREFIX: u8 = 123;
+
+/// Longest value an attribute can carry
+const MAX_VALUE_LEN: usize = 253;
+
+/// Route of a Framed-Route or Framed-IPv6-Route attribute, written as text
+/// like `192.0.2.0/24 192.0.2.1 1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FramedRoute {
+    pub network: IpNetwork,
+    /// None when the route goes to the address of the user, written as the
+    /// unspecified address
+    pub next_hop: Option<IpAddr>,
+    /// Metrics in their order, RFC 2865 allows negative ones
+    pub metrics: Vec<i32>,
+}
+
+impl FramedRoute {
+    /// Parses the text of the attribute. A prefix without length is a host
+    /// route and the next hop and metrics may be left out.
+    /// ```
+    /// use ipnetwork::radius::FramedRoute;
+    /// let route = FramedRoute::decode(b"2001:db8:1::/48 2001:db8::1 10").unwrap();
+    /// assert_eq!(route.network.to_string(), "2001:db8:1::/48");
+    /// assert_eq!(route.next_hop, Some("2001:db8::1".parse().unwrap()));
+    /// assert_eq!(route.metrics, vec![10]);
+    /// ```
+    pub fn decode(value: &[u8]) -> Result<FramedRoute, Error> {
+        let text = std::str::from_utf8(value).map_err(|_| Error::NetworkParseError)?;
+        let mut fields = text.split_ascii_whitespace();
+        let prefix = fields.next().ok_or(Error::NetworkParseError)?;
+        let network = match parse::split_prefix(prefix.as_bytes()) {
+            Some(_) => IpNetwork::parse_ascii(prefix.as_bytes())?,
+            None => match parse::ip_addr(prefix.as_bytes())? {
+                IpAddr::V4(addr) => IpNetwork::V4(Ipv4Network::from_parts_unchecked(addr, 32)),
+                IpAddr::V6(addr) => IpNetwork::V6(Ipv6Network::from_parts_unchecked(addr, 128)),
+            },
+        };
+        let next_hop = match fields.next() {
+            Some(next_hop) => parse::ip_addr(next_hop.as_bytes())?,
+            None => unspecified(&network),
+        };
+        if next_hop.is_ipv4() != matches!(network, IpNetwork::V4(_)) {
+            return Err(Error::CidrMissMatch);
+        }
+        let metrics = fields
+            .map(|metric| metric.parse().map_err(|_| Error::NetworkParseError))
+            .collect::<Result<Vec<i32>, Error>>()?;
+        Ok(FramedRoute {
+            network,
+            next_hop: Some(next_hop).filter(|addr| !addr.is_unspecified()),
+            metrics,
+        })
+    }
+
+    /// Writes the text of the attribute, the next hop is always written
+    /// ```
+    /// use ipnetwork::radius::FramedRoute;
+    /// let route = FramedRoute::decode(b"192.0.2.0/24").unwrap();
+    /// assert_eq!(route.encode(), b"192.0.2.0/24 0.0.0.0".to_vec());
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let next_hop = self.next_hop.unwrap_or_else(|| unspecified(&self.network));
+        let mut text = format!("{} {}", self.network, next_hop);
+        for metric in self.metrics.iter() {
+            text.push_str(&format!(" {}", metric));
+        }
+        text.into_bytes()
+    }
+}
+
+fn unspecified(network: &IpNetwork) -> IpAddr {
+    match network {
+        IpNetwork::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpNetwork::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+/// Parses the binary value of a Framed-IPv6-Prefix or
+/// Delegated-IPv6-Prefix attribute: a reserved byte, the prefix length and
+/// as many bytes of the prefix as the length needs, up to 16. Fails with
+/// `InvalidNetwork` when bits past the prefix length are set.
+/// ```
+/// use ipnetwork::radius::decode_prefix;
+/// let prefix = decode_prefix(&[0, 56, 0x20, 0x01, 0x0d, 0xb8, 0, 1, 0x20]).unwrap();
+/// assert_eq!(prefix.to_string(), "2001:db8:1:2000::/56");
+/// ```
+pub fn decode_prefix(value: &[u8]) -> Result<Ipv6Network, Error> {
+    let (cidr, bytes) = match value {
+        [_, cidr, bytes @ ..] if *cidr <= 128 && bytes.len() <= 16 => (*cidr, bytes),
+        _ => return Err(Error::NetworkParseError),
+    };
+    if bytes.len() < usize::from(cidr).div_ceil(8) {
+        return Err(Error::NetworkParseError);
+    }
+    let mut octets = [0; 16];
+    octets[..bytes.len()].copy_from_slice(bytes);
+    Ipv6Network::new(u128::from_be_bytes(octets), cidr)
+}
+
+/// Writes the binary value of a Framed-IPv6-Prefix or
+/// Delegated-IPv6-Prefix attribute with as few prefix bytes as the length
+/// needs
+pub fn encode_prefix(prefix: &Ipv6Network) -> Vec<u8> {
+    let len = usize::from(prefix.cidr).div_ceil(8);
+    let mut value = vec![0, prefix.cidr];
+    value.extend_from_slice(&prefix.first.to_be_bytes()[..len]);
+    value
+}
+
+/// An attribute with a route or a prefix
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    /// An IPv4 route
+    FramedRoute(FramedRoute),
+    /// An IPv6 route
+    FramedIpv6Route(FramedRoute),
+    /// The prefix of the link to the user
+    FramedIpv6Prefix(Ipv6Network),
+    /// A prefix delegated to the user, with DHCPv6 for example
+    DelegatedIpv6Prefix(Ipv6Network),
+}
+
+impl Attribute {
+    /// Parses the value of an attribute of the type. Returns None for the
+    /// types that carry no route or prefix and fails with `CidrMissMatch`
+    /// when a route is of the other family than its type.
+    pub fn decode(kind: u8, value: &[u8]) -> Result<Option<Attribute>, Error> {
+        let route = |ipv4: bool| {
+            let route = FramedRoute::decode(value)?;
+            match matches!(route.network, IpNetwork::V4(_)) == ipv4 {
+                true => Ok(route),
+                false => Err(Error::CidrMissMatch),
+            }
+        };
+        Ok(Some(match kind {
+            FRAMED_ROUTE => Attribute::FramedRoute(route(true)?),
+            FRAMED_IPV6_ROUTE => Attribute::FramedIpv6Route(route(false)?),
+            FRAMED_IPV6_PREFIX => Attribute::FramedIpv6Prefix(decode_prefix(value)?),
+            DELEGATED_IPV6_PREFIX => Attribute::DelegatedIpv6Prefix(decode_prefix(value)?),
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Parses the attributes of a packet, from the end of the header on.
+    /// Attributes that carry no route or prefix are skipped.
+    /// ```
+    /// use ipnetwork::radius::Attribute;
+    /// let mut attributes = vec![1, 6, b'j', b'o', b'h', b'n'];
+    /// attributes.extend(Attribute::FramedIpv6Prefix("2001:db8:0:1::/64".parse().unwrap()).encode().unwrap());
+    /// let decoded = Attribute::decode_all(&attributes).unwrap();
+    /// assert_eq!(decoded, vec![Attribute::FramedIpv6Prefix("2001:db8:0:1::/64".parse().unwrap())]);
+    /// ```
+    pub fn decode_all(mut attributes: &[u8]) -> Result<Vec<Attribute>, Error> {
+        let mut decoded = Vec::new();
+        while let [kind, len, ..] = *attributes {
+            let len = usize::from(len);
+            let value = match attributes.get(2..len) {
+                Some(value) if len >= 2 => value,
+                _ => return Err(Error::NetworkParseError),
+            };
+            decoded.extend(Attribute::decode(kind, value)?);
+            attributes = &attributes[len..];
+        }
+        match attributes.is_empty() {
+            true => Ok(decoded),
+            false => Err(Error::NetworkParseError),
+        }
+    }
+
+    pub fn kind(&self) -> u8 {
+        match self {
+            Attribute::FramedRoute(_) => FRAMED_ROUTE,
+            Attribute::FramedIpv6Route(_) => FRAMED_IPV6_ROUTE,
+            Attribute::FramedIpv6Prefix(_) => FRAMED_IPV6_PREFIX,
+            Attribute::DelegatedIpv6Prefix(_) => DELEGATED_IPV6_PREFIX,
+        }
+    }
+
+    pub fn value(&self) -> Vec<u8> {
+        match self {
+            Attribute::FramedRoute(route) | Attribute::FramedIpv6Route(route) => route.encode(),
+            Attribute::FramedIpv6Prefix(prefix) | Attribute::DelegatedIpv6Prefix(prefix) => {
+                encode_prefix(prefix)
+            }
+        }
+    }
+
+    /// Writes the attribute with its type and length. Fails with
+    /// `NotEnoughAddresses` when a route has so many metrics its text is
+    /// longer than an attribute can carry.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let value = self.value();
+        let len = match value.len() <= MAX_VALUE_LEN {
+            true => u8::try_from(value.len() + 2).map_err(|_| Error::NotEnoughAddresses)?,
+            false => return Err(Error::NotEnoughAddresses),
+        };
+        let mut attribute = vec![self.kind(), len];
+        attribute.extend(value);
+        Ok(attribute)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(text: &str) -> Result<FramedRoute, Error> {
+        FramedRoute::decode(text.as_bytes())
+    }
+
+    #[test]
+    fn routes() {
+        let decoded = route("192.0.2.0/24 192.0.2.1 1 5").unwrap();
+        assert_eq!(decoded.encode(), b"192.0.2.0/24 192.0.2.1 1 5".to_vec());
+        assert_eq!(decoded.metrics, vec![1, 5]);
+        let example = route("192.168.1.0/24 192.168.1.1 1 2 -1 3 400").unwrap();
+        assert_eq!(example.metrics, vec![1, 2, -1, 3, 400]);
+        assert_eq!(
+            example.encode(),
+            b"192.168.1.0/24 192.168.1.1 1 2 -1 3 400".to_vec()
+        );
+        let host = route("  198.51.100.7\t0.0.0.0 ").unwrap();
+        assert_eq!(host.network.to_string(), "198.51.100.7/32");
+        assert_eq!(host.next_hop, None);
+        assert_eq!(route(""), Err(Error::NetworkParseError));
+        assert_eq!(route("192.0.2.1/24"), Err(Error::InvalidNetwork));
+        assert_eq!(route("192.0.2.0/24 2001:db8::1"), Err(Error::CidrMissMatch));
+        assert_eq!(
+            route("192.0.2.0/24 192.0.2.1 x"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            Attribute::decode(FRAMED_IPV6_ROUTE, b"192.0.2.0/24"),
+            Err(Error::CidrMissMatch)
+        );
+        let attribute = Attribute::FramedIpv6Route(route("2001:db8::/32 :: 1").unwrap());
+        let encoded = attribute.encode().unwrap();
+        assert_eq!(&encoded[..2], &[FRAMED_IPV6_ROUTE, 20]);
+        assert_eq!(Attribute::decode_all(&encoded), Ok(vec![attribute]));
+        let many = FramedRoute {
+            metrics: vec![i32::MIN; 30],
+            ..decoded
+        };
+        assert_eq!(
+            Attribute::FramedRoute(many).encode(),
+            Err(Error::NotEnoughAddresses)
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        for prefix in [
+            "::/0",
+            "2001:db8::/32",
+            "2001:db8:1:2::/63",
+            "2001:db8::1/128",
+        ]
+        .iter()
+        {
+            let prefix: Ipv6Network = prefix.parse().unwrap();
+            let value = encode_prefix(&prefix);
+            assert_eq!(value.len(), 2 + usize::from(prefix.cidr).div_ceil(8));
+            assert_eq!(decode_prefix(&value), Ok(prefix));
+        }
+        // prefixes may be sent with all 16 bytes
+        let mut long = vec![0, 48];
+        long.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 1]);
+        long.extend_from_slice(&[0; 10]);
+        assert_eq!(decode_prefix(&long).unwrap().to_string(), "2001:db8:1::/48");
+        assert_eq!(
+            decode_prefix(&[0, 64, 0x20, 0x01]),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(decode_prefix(&[0, 129]), Err(Error::NetworkParseError));
+        assert_eq!(decode_prefix(&[0, 4, 0x21]), Err(Error::InvalidNetwork));
+        assert_eq!(
+            Attribute::decode_all(&[DELEGATED_IPV6_PREFIX, 3, 0]),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            Attribute::decode_all(&[97, 1]),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(Attribute::decode_all(&[97]), Err(Error::NetworkParseError));
+        assert_eq!(Attribute::decode(1, b"john"), Ok(None));
+    }
+}
@@ -0,0 +1,431 @@
+//! Random selection of addresses and subnets from networks
+use crate::bits::AddressBits;
+use crate::{Error, Network};
+
+/// Source of random numbers used by the sampling helpers.
+///
+/// Implemented for every `FnMut() -> u64`, so any generator can be plugged
+/// in with a closure.
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> RandomSource for F {
+    fn next_u64(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// Returns a uniformly distributed value in `0..bound`. `bound` must not be 0.
+pub(crate) fn below<R: RandomSource + ?Sized>(rng: &mut R, bound: u128) -> u128 {
+    // Values under 2^128 % bound would make the low results more likely
+    let threshold = bound.wrapping_neg() % bound;
+    loop {
+        let value = (u128::from(rng.next_u64()) << 64) | u128::from(rng.next_u64());
+        if value >= threshold {
+            return value % bound;
+        }
+    }
+}
+
+/// Picks random addresses from a list of networks without enumerating them.
+///
+/// By default every address is equally likely, so a network is picked in
+/// proportion to its size. Custom weights can be set per network instead.
+/// ```
+/// use ipnetwork::Ipv4Network;
+/// use ipnetwork::random::AddressSampler;
+/// let networks: Vec<Ipv4Network> = vec!["10.0.0.0/8".parse().unwrap()];
+/// let sampler = AddressSampler::new(&networks);
+/// let mut seed = 7u64;
+/// let mut rng = || {
+///     seed ^= seed << 13;
+///     seed ^= seed >> 7;
+///     seed ^= seed << 17;
+///     seed
+/// };
+/// let addr = sampler.sample(&mut rng).unwrap();
+/// assert!(networks[0].first() <= addr && addr <= networks[0].last());
+/// ```
+#[derive(Debug)]
+pub struct AddressSampler<B: AddressBits> {
+    /// First address and hostmask of each network
+    networks: Vec<(B, u128)>,
+    /// Running total of the weights, used to pick a network
+    cumulative: Vec<u128>,
+}
+
+impl<B: AddressBits> AddressSampler<B> {
+    /// Creates a sampler where every address is equally likely. The
+    /// networks are aggregated first, so addresses of overlapping networks
+    /// are not picked more often.
+    pub fn new(networks: &[Network<B>]) -> AddressSampler<B> {
+        let networks = Network::aggregate(networks);
+        // Only ::/0 holds 2^128 addresses, and it is alone once aggregated
+        let weighted: Vec<(&Network<B>, u128)> = networks
+            .iter()
+            .map(|n| (n, AddressSampler::hostmask(n).saturating_add(1)))
+            .collect();
+        AddressSampler::build(&weighted)
+    }
+
+    /// Creates a sampler where a network is picked in proportion to its
+    /// weight, and then an address uniformly inside it
+    pub fn with_weights(networks: &[(Network<B>, u64)]) -> AddressSampler<B> {
+        let weighted: Vec<(&Network<B>, u128)> = networks
+            .iter()
+            .map(|(n, weight)| (n, u128::from(*weight)))
+            .collect();
+        AddressSampler::build(&weighted)
+    }
+
+    /// Returns a random address, or None when all weights are 0
+    pub fn sample<R: RandomSource + ?Sized>(&self, rng: &mut R) -> Option<B::Addr> {
+        let total = *self.cumulative.last()?;
+        if total == 0 {
+            return None;
+        }
+        let pick = below(rng, total);
+        let index = self.cumulative.partition_point(|&sum| sum <= pick);
+        let (first, hostmask) = self.networks[index];
+        let offset = match hostmask.checked_add(1) {
+            Some(size) => below(rng, size),
+            None => (u128::from(rng.next_u64()) << 64) | u128::from(rng.next_u64()),
+        };
+        let addr = B::from_u128(first.to_u128() | offset).expect("address lies inside the network");
+        Some(addr.to_addr())
+    }
+
+    fn hostmask(network: &Network<B>) -> u128 {
+        Network::<B>::cidr_to_hostmask(network.cidr).to_u128()
+    }
+
+    fn build(weighted: &[(&Network<B>, u128)]) -> AddressSampler<B> {
+        let mut total: u128 = 0;
+        let mut cumulative = Vec::with_capacity(weighted.len());
+        for (_, weight) in weighted {
+            total = total.saturating_add(*weight);
+            cumulative.push(total);
+        }
+        AddressSampler {
+            networks: weighted
+                .iter()
+                .map(|(n, _)| (n.first, AddressSampler::hostmask(n)))
+                .collect(),
+            cumulative,
+        }
+    }
+}
+
+/// Returns a uniformly chosen /`prefix` subnet of `parent`. Fails with
+/// `CidrMissMatch` when `prefix` is shorter than the parent's prefix and
+/// with `InvalidNetwork` when it is longer than the address.
+/// ```
+/// use ipnetwork::random::random_subnet;
+/// use ipnetwork::Ipv6Network;
+/// let parent: Ipv6Network = "2001:db8::/32".parse().unwrap();
+/// let mut counter = 0u64;
+/// let mut rng = || {
+///     counter = counter.wrapping_add(0x9e37_79b9_7f4a_7c15);
+///     counter
+/// };
+/// let subnet = random_subnet(&parent, 64, &mut rng).unwrap();
+/// assert_eq!(subnet.cidr, 64);
+/// assert!(parent.is_subnet(&subnet));
+/// ```
+pub fn random_subnet<B: AddressBits, R: RandomSource + ?Sized>(
+    parent: &Network<B>,
+    prefix: u8,
+    rng: &mut R,
+) -> Result<Network<B>, Error> {
+    if prefix > B::BITS {
+        return Err(Error::InvalidNetwork);
+    }
+    if prefix < parent.cidr {
+        return Err(Error::CidrMissMatch);
+    }
+    let index = match prefix - parent.cidr {
+        0 => 0,
+        128 => (u128::from(rng.next_u64()) << 64) | u128::from(rng.next_u64()),
+        bits => below(rng, 1 << bits),
+    };
+    let offset = index.checked_shl(u32::from(B::BITS - prefix)).unwrap_or(0);
+    let first =
+        B::from_u128(parent.first.to_u128() | offset).expect("subnet lies inside the parent");
+    Ok(Network {
+        first,
+        cidr: prefix,
+    })
+}
+
+/// Round function of the Feistel network, `half` bits of a keyed hash
+fn round(key: u64, round: u64, value: u64, half: u32) -> u64 {
+    // the splitmix64 finalizer
+    let mut z = key ^ value.wrapping_add(round.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    z & u64::MAX.checked_shr(64 - half).unwrap_or(0)
+}
+
+/// Iterator over the addresses of a network in a pseudo-random order set
+/// by a key, every address exactly once. Nothing is stored per address, so
+/// it runs over networks of any size.
+///
+/// The order is fixed by the key, workers that agree on a key can split
+/// the network with `shard` without coordinating further.
+/// ```
+/// use ipnetwork::random::Permutation;
+/// use ipnetwork::Ipv4Network;
+/// let network: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+/// let mut addrs: Vec<_> = Permutation::new(&network, 42).collect();
+/// assert_ne!(addrs, network.into_hosts().collect::<Vec<_>>());
+/// addrs.sort();
+/// assert_eq!(addrs, network.into_hosts().collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permutation<B: AddressBits> {
+    first: B,
+    /// Index of the last address, the network size minus one
+    last: u128,
+    /// Bits of each Feistel half, the index is walked until it falls in
+    /// the network when both halves hold more
+    half: u32,
+    key: u64,
+    /// Next position in the permuted order
+    position: u128,
+    step: u128,
+    done: bool,
+}
+
+impl<B: AddressBits> Permutation<B> {
+    pub fn new(network: &Network<B>, key: u64) -> Permutation<B> {
+        let bits = u32::from(B::BITS - network.cidr);
+        Permutation {
+            first: network.first,
+            last: Network::<B>::cidr_to_hostmask(network.cidr).to_u128(),
+            half: bits.div_ceil(2),
+            key,
+            position: 0,
+            step: 1,
+            done: false,
+        }
+    }
+
+    /// Keeps every `n`th address of the permuted order, starting at
+    /// `shard_index`. The `n` shards of a permutation are disjoint and
+    /// together hold every address.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `shard_index` is not below `n`.
+    pub fn shard(self, n: u128, shard_index: u128) -> Permutation<B> {
+        assert!(shard_index < n, "shard {} of {}", shard_index, n);
+        let position = shard_index
+            .checked_mul(self.step)
+            .and_then(|offset| self.position.checked_add(offset));
+        let step = self.step.checked_mul(n);
+        match (position, step) {
+            (Some(position), Some(step)) => Permutation {
+                position,
+                step,
+                done: self.done || position > self.last,
+                ..self
+            },
+            _ => Permutation { done: true, ..self },
+        }
+    }
+
+    /// One pass of the Feistel network over `2 * half` bits
+    fn encrypt(&self, index: u128) -> u128 {
+        let mask = u64::MAX.checked_shr(64 - self.half).unwrap_or(0);
+        let mut left = (index >> self.half) as u64 & mask;
+        let mut right = index as u64 & mask;
+        for i in 0..4 {
+            let next = left ^ round(self.key, i, right, self.half);
+            left = right;
+            right = next;
+        }
+        u128::from(left) << self.half | u128::from(right)
+    }
+
+    /// Address at a position of the permuted order
+    fn nth_addr(&self, position: u128) -> B::Addr {
+        let mut index = self.encrypt(position);
+        while index > self.last {
+            index = self.encrypt(index);
+        }
+        let offset = B::from_u128(index).expect("index lies inside the network");
+        (self.first + offset).to_addr()
+    }
+}
+
+impl<B: AddressBits> Iterator for Permutation<B> {
+    type Item = B::Addr;
+
+    fn next(&mut self) -> Option<B::Addr> {
+        if self.done {
+            return None;
+        }
+        let addr = self.nth_addr(self.position);
+        match self.position.checked_add(self.step) {
+            Some(position) if position <= self.last => self.position = position,
+            _ => self.done = true,
+        }
+        Some(addr)
+    }
+}
+
+/// Returns shard `shard_index` of `n` of the addresses of the network, in
+/// the pseudo-random order of `Permutation` with key 0. Shards are
+/// disjoint, together hold every address, and each spreads over the whole
+/// network instead of clustering in a few subnets.
+///
+/// # Panics
+///
+/// Panics when `shard_index` is not below `n`.
+/// ```
+/// use ipnetwork::random::shard;
+/// use ipnetwork::Ipv4Network;
+/// let network: Ipv4Network = "192.0.2.0/24".parse().unwrap();
+/// let shards: Vec<Vec<_>> = (0..3).map(|i| shard(&network, 3, i).collect()).collect();
+/// assert_eq!(shards.iter().map(Vec::len).sum::<usize>(), 256);
+/// assert!(shards[0].iter().all(|addr| !shards[1].contains(addr)));
+/// ```
+pub fn shard<B: AddressBits>(network: &Network<B>, n: u128, shard_index: u128) -> Permutation<B> {
+    Permutation::new(network, 0).shard(n, shard_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ipv4Network;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn xorshift(mut seed: u64) -> impl FnMut() -> u64 {
+        move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        }
+    }
+
+    #[test]
+    fn sample_by_size() {
+        let small: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+        let large: Ipv4Network = "10.1.0.0/22".parse().unwrap();
+        let sampler = AddressSampler::new(&[small, large]);
+        let mut rng = xorshift(42);
+        let in_small = (0..10_000)
+            .map(|_| sampler.sample(&mut rng).unwrap())
+            .filter(|addr| small.first() <= *addr && *addr <= small.last())
+            .count();
+        // a fifth of the addresses live in the /24
+        assert!(in_small > 1_700 && in_small < 2_300, "{}", in_small);
+        // a network inside another one adds no weight
+        let inner: Ipv4Network = "10.1.0.0/24".parse().unwrap();
+        let sampler = AddressSampler::new(&[small, large, inner, inner]);
+        let in_small = (0..10_000)
+            .map(|_| sampler.sample(&mut rng).unwrap())
+            .filter(|addr| small.first() <= *addr && *addr <= small.last())
+            .count();
+        assert!(in_small > 1_700 && in_small < 2_300, "{}", in_small);
+    }
+
+    #[test]
+    fn sample_v6() {
+        let mut rng = xorshift(9);
+        let all: crate::Ipv6Network = "::/0".parse().unwrap();
+        let sampler = AddressSampler::new(&[all, "2001:db8::/32".parse().unwrap()]);
+        let addrs: Vec<Ipv6Addr> = (0..4).map(|_| sampler.sample(&mut rng).unwrap()).collect();
+        assert!(addrs.windows(2).all(|pair| pair[0] != pair[1]));
+        let small: crate::Ipv6Network = "2001:db8::/127".parse().unwrap();
+        let halves = [(small, 1), ("2001:db8:1::/64".parse().unwrap(), 3)];
+        let sampler = AddressSampler::with_weights(&halves);
+        let in_small = (0..4_000)
+            .map(|_| sampler.sample(&mut rng).unwrap())
+            .filter(|addr| small.contains(addr))
+            .count();
+        assert!(in_small > 850 && in_small < 1_150, "{}", in_small);
+    }
+
+    #[test]
+    fn sample_with_weights() {
+        let a: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+        let b: Ipv4Network = "192.168.0.0/16".parse().unwrap();
+        let sampler = AddressSampler::with_weights(&[(a, 1), (b, 0)]);
+        let mut rng = xorshift(1);
+        for _ in 0..100 {
+            let addr = sampler.sample(&mut rng).unwrap();
+            assert!(a.first() <= addr && addr <= a.last());
+        }
+        let empty = AddressSampler::with_weights(&[(b, 0)]);
+        assert_eq!(empty.sample(&mut rng), None);
+        assert_eq!(AddressSampler::<u32>::new(&[]).sample(&mut rng), None);
+    }
+
+    #[test]
+    fn random_subnets() {
+        let parent: Ipv4Network = "10.0.0.0/22".parse().unwrap();
+        let mut rng = xorshift(5);
+        let mut seen = [0; 4];
+        for _ in 0..400 {
+            let subnet = random_subnet(&parent, 24, &mut rng).unwrap();
+            assert!(parent.is_subnet(&subnet));
+            seen[usize::from(subnet.first.to_be_bytes()[2])] += 1;
+        }
+        assert!(seen.iter().all(|&c| c > 60), "{:?}", seen);
+        assert_eq!(random_subnet(&parent, 22, &mut rng), Ok(parent));
+        assert_eq!(
+            random_subnet(&parent, 21, &mut rng),
+            Err(Error::CidrMissMatch)
+        );
+        assert_eq!(
+            random_subnet(&parent, 33, &mut rng),
+            Err(Error::InvalidNetwork)
+        );
+        let all: crate::Ipv6Network = "::/0".parse().unwrap();
+        let host = random_subnet(&all, 128, &mut rng).unwrap();
+        assert_eq!(host.cidr, 128);
+        let hosts: Vec<Ipv4Network> = (0..8)
+            .map(|_| random_subnet(&parent, 32, &mut rng).unwrap())
+            .collect();
+        assert!(hosts.iter().all(|h| parent.is_subnet(h)));
+    }
+
+    #[test]
+    fn permutations() {
+        let network: Ipv4Network = "10.0.0.0/21".parse().unwrap();
+        let mut addrs: Vec<Ipv4Addr> = Permutation::new(&network, 7).collect();
+        let other: Vec<Ipv4Addr> = Permutation::new(&network, 8).collect();
+        assert_ne!(addrs, other);
+        addrs.sort();
+        assert_eq!(addrs, network.into_hosts().collect::<Vec<_>>());
+        let host: Ipv4Network = "10.0.0.1/32".parse().unwrap();
+        assert_eq!(
+            Permutation::new(&host, 1).collect::<Vec<_>>(),
+            vec![host.first()]
+        );
+        // the shards of a /21 each hit most of its /24s
+        let mut seen = Vec::new();
+        for i in 0..16 {
+            let addrs: Vec<Ipv4Addr> = shard(&network, 16, i).collect();
+            assert_eq!(addrs.len(), 128);
+            let mut subnets: Vec<u8> = addrs.iter().map(|a| a.octets()[2]).collect();
+            subnets.sort_unstable();
+            subnets.dedup();
+            assert!(subnets.len() >= 7, "{:?}", subnets);
+            seen.extend(addrs);
+        }
+        seen.sort();
+        assert_eq!(seen, network.into_hosts().collect::<Vec<_>>());
+        assert_eq!(shard(&host, 2, 1).count(), 0);
+        let twice: Vec<Ipv4Addr> = shard(&network, 2, 1).shard(2, 0).collect();
+        assert_eq!(twice, shard(&network, 4, 1).collect::<Vec<_>>());
+        let all: crate::Ipv6Network = "::/0".parse().unwrap();
+        let mut huge = shard(&all, u128::MAX, 3);
+        assert!(huge.next().is_some());
+        assert_eq!(huge.next(), None);
+    }
+}
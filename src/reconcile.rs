@@ -0,0 +1,329 @@
+//! Converge allocated address space towards a desired state
+use crate::IpNetwork;
+
+/// A step needed to move the actual state towards the desired one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Release an allocation that is no longer wanted
+    Release { name: String, network: IpNetwork },
+    /// Grow or shrink an allocation in place
+    Resize {
+        name: String,
+        from: IpNetwork,
+        to: IpNetwork,
+    },
+    /// Replace several allocations by one network overlapping all of them
+    Merge {
+        name: String,
+        from: Vec<IpNetwork>,
+        to: IpNetwork,
+    },
+    /// Replace an allocation by several smaller ones inside it
+    Split {
+        name: String,
+        from: IpNetwork,
+        into: Vec<IpNetwork>,
+    },
+    /// Allocate a new network
+    Allocate { name: String, network: IpNetwork },
+}
+
+/// A desired network that overlaps space owned by someone else. The
+/// change needs a human decision and no action is emitted for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub name: String,
+    pub network: IpNetwork,
+    /// Owner of the overlapping allocation
+    pub other: String,
+    pub other_network: IpNetwork,
+}
+
+/// Result of `reconcile`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Reconciliation {
+    /// Actions in the order they should be applied: releases, then resizes,
+    /// merges and splits, then new allocations
+    pub actions: Vec<Action>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Computes the actions that turn the `actual` allocations into the
+/// `desired` ones. Both lists hold (owner, network) pairs and an owner may
+/// have several networks.
+///
+/// Per owner, networks present in both lists are left alone. A current
+/// network that overlaps exactly one wanted network is resized, or merged
+/// with the other current networks overlapping it. One that contains
+/// several wanted networks is split, and the rest is released. The
+/// remaining wanted networks are allocated. Networks that would overlap a
+/// network another owner keeps are reported as conflicts instead, and the
+/// allocation they would replace is kept.
+/// ```
+/// use ipnetwork::reconcile::{reconcile, Action};
+/// use ipnetwork::IpNetwork;
+/// let actual = [("web", IpNetwork::V4("10.0.0.0/24".parse().unwrap()))];
+/// let desired = [("web", IpNetwork::V4("10.0.0.0/23".parse().unwrap()))];
+/// let plan = reconcile(&desired, &actual);
+/// assert_eq!(plan.actions.len(), 1);
+/// assert!(matches!(plan.actions[0], Action::Resize { .. }));
+/// ```
+pub fn reconcile(desired: &[(&str, IpNetwork)], actual: &[(&str, IpNetwork)]) -> Reconciliation {
+    let mut releases = Vec::new();
+    let mut changes = Vec::new();
+    let mut allocations = Vec::new();
+    let mut conflicts = Vec::new();
+
+    let mut names: Vec<&str> = desired
+        .iter()
+        .chain(actual)
+        .map(|(name, _)| *name)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    for name in names {
+        let wanted: Vec<IpNetwork> = desired
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, network)| *network)
+            .filter(|network| !actual.contains(&(name, *network)))
+            .collect();
+        let mut unmatched = wanted.clone();
+        // Wanted networks already taken by a change, with its index
+        let mut claimed: Vec<(IpNetwork, usize)> = Vec::new();
+        for (_, current) in actual.iter().filter(|(n, _)| *n == name) {
+            if desired.contains(&(name, *current)) {
+                continue;
+            }
+            let overlapping: Vec<IpNetwork> = wanted
+                .iter()
+                .filter(|network| network.overlaps(current))
+                .copied()
+                .collect();
+            let claim = |network: &IpNetwork| {
+                claimed
+                    .iter()
+                    .find(|(claimed, _)| claimed == network)
+                    .map(|(_, index)| *index)
+            };
+            let changed = match overlapping.len() {
+                0 => false,
+                1 => match claim(&overlapping[0]).map(|index| &mut changes[index]) {
+                    Some(Action::Merge { from, .. }) => {
+                        from.push(*current);
+                        true
+                    }
+                    Some(change @ Action::Resize { .. }) => {
+                        if let Action::Resize { from, to, .. } = change.clone() {
+                            *change = Action::Merge {
+                                name: name.to_string(),
+                                from: vec![from, *current],
+                                to,
+                            };
+                        }
+                        true
+                    }
+                    Some(_) => false,
+                    None => {
+                        claimed.push((overlapping[0], changes.len()));
+                        changes.push(Action::Resize {
+                            name: name.to_string(),
+                            from: *current,
+                            to: overlapping[0],
+                        });
+                        true
+                    }
+                },
+                _ if overlapping
+                    .iter()
+                    .all(|n| current.is_subnet(n) && claim(n).is_none()) =>
+                {
+                    claimed.extend(overlapping.iter().map(|n| (*n, changes.len())));
+                    changes.push(Action::Split {
+                        name: name.to_string(),
+                        from: *current,
+                        into: overlapping.clone(),
+                    });
+                    true
+                }
+                _ => false,
+            };
+            match changed {
+                true => unmatched.retain(|network| !overlapping.contains(network)),
+                false => releases.push(Action::Release {
+                    name: name.to_string(),
+                    network: *current,
+                }),
+            }
+        }
+        allocations.extend(unmatched.into_iter().map(|network| Action::Allocate {
+            name: name.to_string(),
+            network,
+        }));
+    }
+
+    // Dropping a change keeps its current networks, which may in turn
+    // conflict with other changes, so check until nothing more is dropped
+    let mut dropped = vec![false; changes.len()];
+    loop {
+        conflicts.clear();
+        // Space that is still owned by someone once the actions are applied
+        let applied = changes
+            .iter()
+            .zip(&dropped)
+            .filter(|(_, dropped)| !**dropped)
+            .map(|(action, _)| action);
+        let applied: Vec<&Action> = releases.iter().chain(applied).collect();
+        let kept: Vec<&(&str, IpNetwork)> = actual
+            .iter()
+            .filter(|(name, network)| !applied.iter().any(|a| replaces(a, name, network)))
+            .collect();
+        let mut conflicting = |name: &str, network: &IpNetwork| {
+            let mut found = false;
+            let others = kept.iter().copied().chain(desired);
+            for (other, other_network) in others.filter(|(other, _)| *other != name) {
+                if network.overlaps(other_network) {
+                    found = true;
+                    conflicts.push(Conflict {
+                        name: name.to_string(),
+                        network: *network,
+                        other: other.to_string(),
+                        other_network: *other_network,
+                    });
+                }
+            }
+            found
+        };
+        let now_dropped: Vec<bool> = changes
+            .iter()
+            .map(|action| match action {
+                Action::Resize { name, to, .. } | Action::Merge { name, to, .. } => {
+                    conflicting(name, to)
+                }
+                Action::Split { name, into, .. } => {
+                    into.iter().filter(|n| conflicting(name, n)).count() > 0
+                }
+                _ => false,
+            })
+            .collect();
+        if now_dropped == dropped {
+            allocations.retain(|action| match action {
+                Action::Allocate { name, network } => !conflicting(name, network),
+                _ => true,
+            });
+            break;
+        }
+        dropped = now_dropped;
+    }
+    let mut dropped = dropped.into_iter();
+    changes.retain(|_| !dropped.next().unwrap_or(false));
+    conflicts.dedup();
+
+    let mut actions = releases;
+    actions.extend(changes);
+    actions.extend(allocations);
+    Reconciliation { actions, conflicts }
+}
+
+/// Returns true when the action gives up the owner's current network
+fn replaces(action: &Action, owner: &str, network: &IpNetwork) -> bool {
+    match action {
+        Action::Release { name, network: n }
+        | Action::Resize { name, from: n, .. }
+        | Action::Split { name, from: n, .. } => name == owner && n == network,
+        Action::Merge { name, from, .. } => name == owner && from.contains(network),
+        Action::Allocate { .. } => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        IpNetwork::V4(s.parse().unwrap())
+    }
+
+    #[test]
+    fn converge() {
+        let actual = [
+            ("db", net("10.0.0.0/24")),
+            ("old", net("10.0.1.0/24")),
+            ("web", net("10.0.2.0/23")),
+            ("api", net("10.0.4.0/25")),
+            ("api", net("10.0.4.128/25")),
+        ];
+        let desired = [
+            ("db", net("10.0.0.0/24")),
+            ("api", net("10.0.4.0/24")),
+            ("web", net("10.0.2.0/24")),
+            ("web", net("10.0.3.0/24")),
+            ("cache", net("10.0.1.0/25")),
+        ];
+        let plan = reconcile(&desired, &actual);
+        assert_eq!(
+            plan.actions,
+            vec![
+                Action::Release {
+                    name: "old".to_string(),
+                    network: net("10.0.1.0/24")
+                },
+                Action::Merge {
+                    name: "api".to_string(),
+                    from: vec![net("10.0.4.0/25"), net("10.0.4.128/25")],
+                    to: net("10.0.4.0/24")
+                },
+                Action::Split {
+                    name: "web".to_string(),
+                    from: net("10.0.2.0/23"),
+                    into: vec![net("10.0.2.0/24"), net("10.0.3.0/24")]
+                },
+                Action::Allocate {
+                    name: "cache".to_string(),
+                    network: net("10.0.1.0/25")
+                },
+            ]
+        );
+        assert!(plan.conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicts() {
+        let actual = [("db", net("10.0.0.0/24")), ("web", net("10.0.1.0/24"))];
+        let desired = [
+            ("db", net("10.0.0.0/24")),
+            ("web", net("10.0.0.0/23")),
+            ("new", net("10.0.0.128/25")),
+        ];
+        let plan = reconcile(&desired, &actual);
+        assert!(plan.actions.is_empty());
+        let pairs: Vec<(&str, &str)> = plan
+            .conflicts
+            .iter()
+            .map(|c| (c.name.as_str(), c.other.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![("web", "db"), ("web", "new"), ("new", "db"), ("new", "web")]
+        );
+    }
+
+    #[test]
+    fn dropped_change_keeps_space() {
+        let actual = [("a", net("10.0.0.0/23")), ("c", net("10.0.0.0/26"))];
+        let desired = [
+            ("a", net("10.0.0.0/24")),
+            ("b", net("10.0.1.0/24")),
+            ("c", net("10.0.0.0/26")),
+        ];
+        let plan = reconcile(&desired, &actual);
+        assert!(plan.actions.is_empty());
+        let pairs: Vec<(&str, &str)> = plan
+            .conflicts
+            .iter()
+            .map(|c| (c.name.as_str(), c.other.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("a", "c"), ("b", "a")]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Networks and addresses of reverse DNS names under `in-addr.arpa` and
+//! `ip6.arpa`
+use crate::{parse, Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const IPV4_SUFFIX: &str = "in-addr.arpa";
+const IPV6_SUFFIX: &str = "ip6.arpa";
+
+/// Labels in front of `suffix`, most significant first
+fn labels<'a>(name: &'a str, suffix: &str) -> Option<Vec<&'a str>> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let split = name.len().checked_sub(suffix.len())?;
+    if !name.get(split..)?.eq_ignore_ascii_case(suffix) {
+        return None;
+    }
+    let rest = &name[..split];
+    if rest.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = rest.strip_suffix('.')?;
+    Some(rest.split('.').rev().collect())
+}
+
+/// Parses an RFC 2317 classless delegation label, `start/len` or
+/// `start-end`, into the last octet and prefix length
+fn classless(label: &str) -> Result<(u8, u8), Error> {
+    let bytes = label.as_bytes();
+    let separator = bytes
+        .iter()
+        .position(|&b| b == b'/' || b == b'-')
+        .ok_or(Error::NetworkParseError)?;
+    let start = parse::octet(&bytes[..separator]).ok_or(Error::NetworkParseError)?;
+    let value = parse::octet(&bytes[separator + 1..]).ok_or(Error::NetworkParseError)?;
+    let cidr = match bytes[separator] {
+        b'/' => value,
+        _ => {
+            let size = u32::from(value.checked_sub(start).ok_or(Error::InvalidNetwork)?) + 1;
+            match size.is_power_of_two() {
+                true => 32 - size.trailing_zeros() as u8,
+                false => return Err(Error::InvalidNetwork),
+            }
+        }
+    };
+    match cidr > 24 {
+        true => Ok((start, cidr)),
+        false => Err(Error::InvalidNetwork),
+    }
+}
+
+fn parse_v4(labels: &[&str]) -> Result<Ipv4Network, Error> {
+    if labels.len() > 4 {
+        return Err(Error::NetworkParseError);
+    }
+    let mut octets = [0u8; 4];
+    let mut cidr = 8 * labels.len() as u8;
+    for (index, label) in labels.iter().enumerate() {
+        match parse::octet(label.as_bytes()) {
+            Some(octet) => octets[index] = octet,
+            None if index == 3 => {
+                let (start, len) = classless(label)?;
+                octets[3] = start;
+                cidr = len;
+            }
+            None => return Err(Error::NetworkParseError),
+        }
+    }
+    Ipv4Network::new(octets[0], octets[1], octets[2], octets[3], cidr)
+}
+
+fn parse_v6(labels: &[&str]) -> Result<Ipv6Network, Error> {
+    if labels.len() > 32 {
+        return Err(Error::NetworkParseError);
+    }
+    let mut first = 0u128;
+    for (index, label) in labels.iter().enumerate() {
+        let nibble = match label.as_bytes() {
+            [digit] => (*digit as char)
+                .to_digit(16)
+                .ok_or(Error::NetworkParseError)?,
+            _ => return Err(Error::NetworkParseError),
+        };
+        first |= u128::from(nibble) << (124 - 4 * index);
+    }
+    Ipv6Network::new(first, 4 * labels.len() as u8)
+}
+
+/// Returns the network a reverse zone is authoritative for. Zones may stop
+/// at any octet or nibble, and IPv4 zones may use RFC 2317 classless
+/// delegation labels like `0/26` or `0-63`.
+/// ```
+/// use ipnetwork::reverse::parse_zone;
+/// use ipnetwork::IpNetwork;
+/// let zone = parse_zone("2.0.192.in-addr.arpa.").unwrap();
+/// assert_eq!(zone, IpNetwork::V4("192.0.2.0/24".parse().unwrap()));
+/// let classless = parse_zone("64/26.2.0.192.in-addr.arpa").unwrap();
+/// assert_eq!(classless, IpNetwork::V4("192.0.2.64/26".parse().unwrap()));
+/// let nibbles = parse_zone("8.b.d.0.1.0.0.2.ip6.arpa").unwrap();
+/// assert_eq!(nibbles, IpNetwork::V6("2001:db8::/32".parse().unwrap()));
+/// ```
+pub fn parse_zone(name: &str) -> Result<IpNetwork, Error> {
+    if let Some(labels) = labels(name, IPV4_SUFFIX) {
+        return parse_v4(&labels).map(IpNetwork::V4);
+    }
+    match labels(name, IPV6_SUFFIX) {
+        Some(labels) => parse_v6(&labels).map(IpNetwork::V6),
+        None => Err(Error::NetworkParseError),
+    }
+}
+
+/// Returns the address of a PTR record name, which must have all four
+/// octets or all 32 nibbles
+/// ```
+/// use ipnetwork::reverse::parse_address;
+/// use std::net::IpAddr;
+/// let addr = parse_address("1.2.0.192.in-addr.arpa").unwrap();
+/// assert_eq!(addr, "192.0.2.1".parse::<IpAddr>().unwrap());
+/// ```
+pub fn parse_address(name: &str) -> Result<IpAddr, Error> {
+    match parse_zone(name)? {
+        IpNetwork::V4(n) if n.cidr == 32 => Ok(IpAddr::V4(Ipv4Addr::from(n.first))),
+        IpNetwork::V6(n) if n.cidr == 128 => Ok(IpAddr::V6(Ipv6Addr::from(n.first))),
+        _ => Err(Error::NetworkParseError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> IpNetwork {
+        IpNetwork::V4(s.parse().unwrap())
+    }
+
+    #[test]
+    fn ipv4_zones() {
+        assert_eq!(parse_zone("in-addr.arpa"), Ok(v4("0.0.0.0/0")));
+        assert_eq!(parse_zone("10.IN-ADDR.ARPA."), Ok(v4("10.0.0.0/8")));
+        assert_eq!(
+            parse_zone("0-127.2.0.192.in-addr.arpa"),
+            Ok(v4("192.0.2.0/25"))
+        );
+        assert_eq!(
+            parse_zone("0-62.2.0.192.in-addr.arpa"),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            parse_zone("1/26.2.0.192.in-addr.arpa"),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            parse_zone("0/24.2.0.192.in-addr.arpa"),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            parse_zone("0/26.0.192.in-addr.arpa"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            parse_zone("256.in-addr.arpa"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            parse_zone("1.1.1.1.1.in-addr.arpa"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(parse_zone("xin-addr.arpa"), Err(Error::NetworkParseError));
+        assert_eq!(parse_zone(".in-addr.arpa"), Err(Error::NetworkParseError));
+        assert_eq!(parse_zone("é.pa"), Err(Error::NetworkParseError));
+    }
+
+    #[test]
+    fn ipv6_zones() {
+        let name = "b.a.9.8.7.6.5.0.4.0.0.0.3.0.0.0.2.0.0.0.1.0.0.0.0.0.0.0.1.2.3.4.ip6.arpa.";
+        assert_eq!(
+            parse_address(name),
+            Ok("4321:0:1:2:3:4:567:89ab".parse().unwrap())
+        );
+        assert_eq!(
+            parse_zone("ip6.arpa"),
+            Ok(IpNetwork::V6("::/0".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_zone("F.ip6.arpa"),
+            Ok(IpNetwork::V6("f000::/4".parse().unwrap()))
+        );
+        assert_eq!(parse_zone("10.ip6.arpa"), Err(Error::NetworkParseError));
+        assert_eq!(parse_zone("g.ip6.arpa"), Err(Error::NetworkParseError));
+        assert_eq!(
+            parse_address("8.b.d.0.1.0.0.2.ip6.arpa"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(parse_zone("example.com"), Err(Error::NetworkParseError));
+    }
+}
@@ -0,0 +1,302 @@
+//! Kernel routes as iproute2 sees them: `ip route` command lines to apply
+//! routes and a reader for the output of `ip -j route`
+use crate::json::{self, Json};
+use crate::{parse, Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::net::IpAddr;
+
+/// A unicast route
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Route {
+    pub network: IpNetwork,
+    /// Gateway, None for routes to directly connected networks
+    pub via: Option<IpAddr>,
+    pub dev: Option<String>,
+    pub metric: Option<u32>,
+    /// Paths of a multipath route, empty for other routes
+    pub nexthops: Vec<NextHop>,
+}
+
+/// One path of a multipath route
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NextHop {
+    pub via: Option<IpAddr>,
+    pub dev: Option<String>,
+    pub weight: Option<u32>,
+}
+
+impl Route {
+    pub fn new(network: IpNetwork) -> Route {
+        Route {
+            network,
+            via: None,
+            dev: None,
+            metric: None,
+            nexthops: Vec::new(),
+        }
+    }
+
+    /// Arguments after `ip route add` that select and describe the route
+    fn arguments(&self) -> String {
+        let mut arguments = self.network.to_string();
+        if let Some(via) = self.via {
+            arguments.push_str(&format!(" via {}", via));
+        }
+        if let Some(dev) = &self.dev {
+            arguments.push_str(&format!(" dev {}", dev));
+        }
+        if let Some(metric) = self.metric {
+            arguments.push_str(&format!(" metric {}", metric));
+        }
+        for hop in &self.nexthops {
+            arguments.push_str(" nexthop");
+            if let Some(via) = hop.via {
+                arguments.push_str(&format!(" via {}", via));
+            }
+            if let Some(dev) = &hop.dev {
+                arguments.push_str(&format!(" dev {}", dev));
+            }
+            if let Some(weight) = hop.weight {
+                arguments.push_str(&format!(" weight {}", weight));
+            }
+        }
+        arguments
+    }
+
+    /// Command line adding the route
+    /// ```
+    /// use ipnetwork::route::Route;
+    /// use ipnetwork::IpNetwork;
+    /// let mut route = Route::new(IpNetwork::parse_ascii(b"10.0.0.0/8").unwrap());
+    /// route.via = Some("192.0.2.1".parse().unwrap());
+    /// route.dev = Some("eth0".to_string());
+    /// assert_eq!(route.add_command(), "ip route add 10.0.0.0/8 via 192.0.2.1 dev eth0");
+    /// ```
+    pub fn add_command(&self) -> String {
+        format!("ip route add {}", self.arguments())
+    }
+
+    /// Command line changing the gateway or device of an existing route
+    pub fn replace_command(&self) -> String {
+        format!("ip route replace {}", self.arguments())
+    }
+
+    /// Command line removing the route
+    pub fn del_command(&self) -> String {
+        format!("ip route del {}", self.arguments())
+    }
+}
+
+/// Returns the `ip route` command lines that turn the `actual` routes into
+/// the `desired` ones: removals first, then replacements and additions.
+/// Routes are told apart by network and metric like the kernel does, a
+/// route with a new gateway, device or set of paths is replaced.
+/// ```
+/// use ipnetwork::route::{route_commands, Route};
+/// use ipnetwork::IpNetwork;
+/// let route = |s: &str| Route::new(IpNetwork::parse_ascii(s.as_bytes()).unwrap());
+/// let actual = [route("10.0.0.0/8"), route("192.0.2.0/24")];
+/// let desired = [route("10.0.0.0/8"), route("2001:db8::/32")];
+/// assert_eq!(
+///     route_commands(&desired, &actual),
+///     vec!["ip route del 192.0.2.0/24", "ip route add 2001:db8::/32"]
+/// );
+/// ```
+pub fn route_commands(desired: &[Route], actual: &[Route]) -> Vec<String> {
+    let same = |a: &Route, b: &Route| a.network == b.network && a.metric == b.metric;
+    let mut commands: Vec<String> = actual
+        .iter()
+        .filter(|route| !desired.iter().any(|wanted| same(wanted, route)))
+        .map(Route::del_command)
+        .collect();
+    for wanted in desired {
+        match actual.iter().find(|route| same(wanted, route)) {
+            Some(route) if route == wanted => {}
+            Some(_) => commands.push(wanted.replace_command()),
+            None => commands.push(wanted.add_command()),
+        }
+    }
+    commands
+}
+
+/// Reads the `dst` of a route, `default` is the /0 of the family
+fn destination(dst: &str, ipv6: bool) -> Result<IpNetwork, Error> {
+    let bytes = dst.as_bytes();
+    match (dst, ipv6) {
+        ("default", false) => return Ok(IpNetwork::V4(Ipv4Network { first: 0, cidr: 0 })),
+        ("default", true) => return Ok(IpNetwork::V6(Ipv6Network { first: 0, cidr: 0 })),
+        _ => {}
+    }
+    match parse::split_prefix(bytes) {
+        Some(_) => IpNetwork::parse_ascii(bytes),
+        None => Ok(match parse::ip_addr(bytes)? {
+            IpAddr::V4(addr) => IpNetwork::V4(Ipv4Network::from_parts_unchecked(addr, 32)),
+            IpAddr::V6(addr) => IpNetwork::V6(Ipv6Network::from_parts_unchecked(addr, 128)),
+        }),
+    }
+}
+
+/// Reads an optional number field
+fn number(field: Option<&Json>) -> Result<Option<u32>, Error> {
+    match field {
+        Some(Json::Number(number)) => number
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::NetworkParseError),
+        Some(_) => Err(Error::NetworkParseError),
+        None => Ok(None),
+    }
+}
+
+/// Reads a path of the `nexthops` of a multipath route
+fn next_hop(hop: &Json) -> Result<NextHop, Error> {
+    let via = match hop.get("gateway").and_then(Json::as_str) {
+        Some(gateway) => Some(parse::ip_addr(gateway.as_bytes())?),
+        None => None,
+    };
+    Ok(NextHop {
+        via,
+        dev: hop.get("dev").and_then(Json::as_str).map(str::to_string),
+        weight: number(hop.get("weight"))?,
+    })
+}
+
+/// Reads the routes written by `ip -j route` or `ip -j -6 route`. `ipv6`
+/// tells which of the two ran, the family of `default` routes is not
+/// written. Routes of other types than unicast, like the local and
+/// broadcast routes of the local table, are skipped. The paths of multipath
+/// routes are read into `nexthops`.
+/// ```
+/// use ipnetwork::route::parse_ip_route_json;
+/// let json = r#"[{"dst":"default","gateway":"192.0.2.1","dev":"eth0","protocol":"dhcp","metric":100,"flags":[]},
+///                {"dst":"192.0.2.0/24","dev":"eth0","protocol":"kernel","scope":"link","prefsrc":"192.0.2.10","flags":[]}]"#;
+/// let routes = parse_ip_route_json(json, false).unwrap();
+/// assert_eq!(routes[0].network.to_string(), "0.0.0.0/0");
+/// assert_eq!(routes[0].metric, Some(100));
+/// assert_eq!(routes[1].via, None);
+/// assert_eq!(routes[1].add_command(), "ip route add 192.0.2.0/24 dev eth0");
+/// ```
+pub fn parse_ip_route_json(json: &str, ipv6: bool) -> Result<Vec<Route>, Error> {
+    let entries = match json::parse(json)? {
+        Json::Array(entries) => entries,
+        _ => return Err(Error::NetworkParseError),
+    };
+    let mut routes = Vec::with_capacity(entries.len());
+    for entry in entries.iter() {
+        if entry
+            .get("type")
+            .is_some_and(|kind| kind.as_str() != Some("unicast"))
+        {
+            continue;
+        }
+        let dst = entry
+            .get("dst")
+            .and_then(Json::as_str)
+            .ok_or(Error::NetworkParseError)?;
+        let via = match entry.get("gateway").and_then(Json::as_str) {
+            Some(gateway) => Some(parse::ip_addr(gateway.as_bytes())?),
+            None => None,
+        };
+        let nexthops = match entry.get("nexthops") {
+            Some(Json::Array(hops)) => hops.iter().map(next_hop).collect::<Result<_, _>>()?,
+            Some(_) => return Err(Error::NetworkParseError),
+            None => Vec::new(),
+        };
+        routes.push(Route {
+            network: destination(dst, ipv6)?,
+            via,
+            dev: entry.get("dev").and_then(Json::as_str).map(str::to_string),
+            metric: number(entry.get("metric"))?,
+            nexthops,
+        });
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(network: &str, via: Option<&str>, metric: Option<u32>) -> Route {
+        Route {
+            network: IpNetwork::parse_ascii(network.as_bytes()).unwrap(),
+            via: via.map(|via| via.parse().unwrap()),
+            dev: Some("eth0".to_string()),
+            metric,
+            nexthops: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let actual = [
+            route("0.0.0.0/0", Some("192.0.2.1"), Some(100)),
+            route("10.0.0.0/8", Some("192.0.2.2"), None),
+            route("2001:db8::/32", None, Some(256)),
+        ];
+        let desired = [
+            route("0.0.0.0/0", Some("192.0.2.254"), Some(100)),
+            route("10.0.0.0/8", Some("192.0.2.2"), None),
+            route("2001:db8::/32", None, Some(1024)),
+        ];
+        assert_eq!(
+            route_commands(&desired, &actual),
+            vec![
+                "ip route del 2001:db8::/32 dev eth0 metric 256",
+                "ip route replace 0.0.0.0/0 via 192.0.2.254 dev eth0 metric 100",
+                "ip route add 2001:db8::/32 dev eth0 metric 1024",
+            ]
+        );
+        assert!(route_commands(&actual, &actual).is_empty());
+    }
+
+    #[test]
+    fn read_json() {
+        let json = r#"
+        [ {"dst":"default","gateway":"fe80::1","dev":"eth0","protocol":"ra","metric":100,"flags":[],"pref":"medium"},
+          {"dst":"2001:db8:1::/64","dev":"eth0","protocol":"kernel","metric":256,"flags":[],"pref":"medium"},
+          {"type":"local","dst":"2001:db8:1::10","dev":"eth0","table":"local","protocol":"kernel","metric":0,"flags":[]},
+          {"dst":"2001:db8:2::1","dev":"wg0","flags":[]},
+          {"dst":"2001:db8:3::/48","metric":1024,"flags":[],"nexthops":[{"gateway":"fe80::2","dev":"eth1","weight":1,"flags":[]},
+                                                                    {"gateway":"fe80::3","dev":"eth2","weight":2,"flags":[]}]} ]
+        "#;
+        let routes = parse_ip_route_json(json, true).unwrap();
+        assert_eq!(routes.len(), 4);
+        assert_eq!(routes[0].network.to_string(), "::/0");
+        assert_eq!(routes[0].via, Some("fe80::1".parse().unwrap()));
+        assert_eq!(
+            routes[1].add_command(),
+            "ip route add 2001:db8:1::/64 dev eth0 metric 256"
+        );
+        assert_eq!(routes[2].network.to_string(), "2001:db8:2::1/128");
+        assert_eq!(routes[2].dev.as_deref(), Some("wg0"));
+        assert_eq!(routes[3].via, None);
+        assert_eq!(
+            routes[3].replace_command(),
+            "ip route replace 2001:db8:3::/48 metric 1024 \
+             nexthop via fe80::2 dev eth1 weight 1 nexthop via fe80::3 dev eth2 weight 2"
+        );
+        // a multipath route is left alone, or replaced as a whole
+        assert!(route_commands(&routes, &routes).is_empty());
+        let mut single = routes.clone();
+        single[3].nexthops.pop();
+        assert_eq!(
+            route_commands(&single, &routes),
+            vec!["ip route replace 2001:db8:3::/48 metric 1024 nexthop via fe80::2 dev eth1 weight 1"]
+        );
+        assert_eq!(parse_ip_route_json("[]", false), Ok(Vec::new()));
+        let bad = [
+            "",
+            "{}",
+            "[{}]",
+            r#"[{"dst":"10.0.0.1/8"}]"#,
+            r#"[{"dst":"10.0.0.0/8","metric":"x"}]"#,
+            r#"[{"dst":"10.0.0.0/8","nexthops":[{"weight":"x"}]}]"#,
+            r#"[{"dst":"10.0.0.0/8"}] x"#,
+            r#"[{"dst":"10.0.0.0/8"#,
+            &"[".repeat(100),
+        ];
+        for json in bad.iter() {
+            assert!(parse_ip_route_json(json, false).is_err(), "{}", json);
+        }
+    }
+}
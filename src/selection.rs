@@ -0,0 +1,413 @@
+//! Default address selection for IPv6 and IPv4, as described in RFC 6724
+use crate::Ipv6Network;
+use std::cmp::Ordering;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Row of a policy table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolicyEntry {
+    pub prefix: Ipv6Network,
+    pub precedence: u8,
+    pub label: u8,
+}
+
+/// Policy table used to pick source and destination addresses. IPv4
+/// addresses are looked up as IPv4-mapped IPv6 addresses.
+/// ```
+/// use ipnetwork::selection::PolicyTable;
+/// use std::net::IpAddr;
+/// let table = PolicyTable::default();
+/// let teredo: IpAddr = "2001::1".parse().unwrap();
+/// assert_eq!(table.lookup(&teredo).unwrap().precedence, 5);
+/// let global: IpAddr = "2001:db8::1".parse().unwrap();
+/// assert_eq!(table.lookup(&global).unwrap().precedence, 40);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyTable {
+    entries: Vec<PolicyEntry>,
+}
+
+/// Address configured on the host that can be used as source address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceAddress {
+    pub addr: IpAddr,
+    /// Length of the on-link prefix of the address, longest matching
+    /// prefix comparisons stop there
+    pub prefix_len: u8,
+    pub deprecated: bool,
+    /// Privacy address as of RFC 4941
+    pub temporary: bool,
+}
+
+impl SourceAddress {
+    /// A preferred, public source address
+    pub fn new(addr: IpAddr, prefix_len: u8) -> SourceAddress {
+        SourceAddress {
+            addr,
+            prefix_len,
+            deprecated: false,
+            temporary: false,
+        }
+    }
+}
+
+const LINK_LOCAL: u8 = 0x2;
+const SITE_LOCAL: u8 = 0x5;
+const GLOBAL: u8 = 0xe;
+
+/// Scope of an address, see section 3.1 of RFC 6724
+fn scope(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(addr) => scope_v4(addr),
+        IpAddr::V6(addr) => match addr.to_ipv4_mapped() {
+            Some(v4) => scope_v4(&v4),
+            None => scope_v6(addr),
+        },
+    }
+}
+
+fn scope_v4(addr: &Ipv4Addr) -> u8 {
+    match addr.is_loopback() || addr.is_link_local() {
+        true => LINK_LOCAL,
+        false => GLOBAL,
+    }
+}
+
+fn scope_v6(addr: &Ipv6Addr) -> u8 {
+    let segment = addr.segments()[0];
+    match segment {
+        _ if addr.is_multicast() => (segment & 0xf) as u8,
+        _ if addr.is_loopback() || segment & 0xffc0 == 0xfe80 => LINK_LOCAL,
+        _ if segment & 0xffc0 == 0xfec0 => SITE_LOCAL,
+        _ => GLOBAL,
+    }
+}
+
+/// Number of leading bits the addresses share, at most `limit`. Addresses
+/// of different families share none.
+fn common_prefix_len(a: &IpAddr, b: &IpAddr, limit: u8) -> u8 {
+    let shared = match (a, b) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => (u32::from(*a) ^ u32::from(*b)).leading_zeros(),
+        (IpAddr::V6(a), IpAddr::V6(b)) => (u128::from(*a) ^ u128::from(*b)).leading_zeros(),
+        _ => 0,
+    };
+    (shared as u8).min(limit)
+}
+
+fn same_family(a: &IpAddr, b: &IpAddr) -> bool {
+    a.is_ipv4() == b.is_ipv4()
+}
+
+/// Turns "prefer a" into an ordering where the preferred address is less
+fn prefer(a: bool, b: bool) -> Ordering {
+    b.cmp(&a)
+}
+
+impl Default for PolicyTable {
+    /// The default policy table of section 2.1 of RFC 6724
+    fn default() -> PolicyTable {
+        let entry = |first: u128, cidr, precedence, label| PolicyEntry {
+            prefix: Ipv6Network { first, cidr },
+            precedence,
+            label,
+        };
+        PolicyTable::new(vec![
+            entry(1, 128, 50, 0),
+            entry(0, 0, 40, 1),
+            entry(0xffff << 32, 96, 35, 4),
+            entry(0x2002 << 112, 16, 30, 2),
+            entry(0x2001 << 112, 32, 5, 5),
+            entry(0xfc00 << 112, 7, 3, 13),
+            entry(0, 96, 1, 3),
+            entry(0xfec0 << 112, 10, 1, 11),
+            entry(0x3ffe << 112, 16, 1, 12),
+        ])
+    }
+}
+
+impl PolicyTable {
+    /// Creates a custom policy table
+    pub fn new(entries: Vec<PolicyEntry>) -> PolicyTable {
+        PolicyTable { entries }
+    }
+
+    pub fn entries(&self) -> &[PolicyEntry] {
+        &self.entries
+    }
+
+    /// Returns the longest entry matching the address
+    pub fn lookup(&self, addr: &IpAddr) -> Option<&PolicyEntry> {
+        let addr = match addr {
+            IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+            IpAddr::V6(addr) => *addr,
+        };
+        self.entries
+            .iter()
+            .filter(|entry| entry.prefix.contains(&addr))
+            .max_by_key(|entry| entry.prefix.cidr)
+    }
+
+    fn precedence(&self, addr: &IpAddr) -> u8 {
+        self.lookup(addr).map_or(0, |entry| entry.precedence)
+    }
+
+    /// Addresses matching no entry share no label with any other address
+    fn same_label(&self, a: &IpAddr, b: &IpAddr) -> bool {
+        match (self.lookup(a), self.lookup(b)) {
+            (Some(a), Some(b)) => a.label == b.label,
+            _ => false,
+        }
+    }
+
+    /// Picks the source address to reach `destination` with, following the
+    /// rules of section 5 of RFC 6724. Only sources of the destination's
+    /// family are considered.
+    /// ```
+    /// use ipnetwork::selection::{PolicyTable, SourceAddress};
+    /// let sources = [
+    ///     SourceAddress::new("fe80::1".parse().unwrap(), 64),
+    ///     SourceAddress::new("2001:db8:3::1".parse().unwrap(), 64),
+    /// ];
+    /// let destination = "2001:db8:1::1".parse().unwrap();
+    /// let source = PolicyTable::default().select_source(&destination, &sources);
+    /// assert_eq!(source, Some(&sources[1]));
+    /// ```
+    pub fn select_source<'a>(
+        &self,
+        destination: &IpAddr,
+        sources: &'a [SourceAddress],
+    ) -> Option<&'a SourceAddress> {
+        let mut best: Option<&SourceAddress> = None;
+        for source in sources.iter().filter(|s| same_family(&s.addr, destination)) {
+            best = match best {
+                Some(b) if self.compare_sources(destination, b, source) != Ordering::Greater => {
+                    Some(b)
+                }
+                _ => Some(source),
+            };
+        }
+        best
+    }
+
+    /// Orders two source addresses for `destination`, the preferred one is
+    /// less
+    fn compare_sources(&self, d: &IpAddr, a: &SourceAddress, b: &SourceAddress) -> Ordering {
+        let scope_d = scope(d);
+        let (scope_a, scope_b) = (scope(&a.addr), scope(&b.addr));
+        // Rule 1: prefer same address
+        prefer(a.addr == *d, b.addr == *d)
+            // Rule 2: prefer appropriate scope
+            .then_with(|| match scope_a.cmp(&scope_b) {
+                Ordering::Less if scope_a < scope_d => Ordering::Greater,
+                Ordering::Less => Ordering::Less,
+                Ordering::Greater if scope_b < scope_d => Ordering::Less,
+                Ordering::Greater => Ordering::Greater,
+                Ordering::Equal => Ordering::Equal,
+            })
+            // Rule 3: avoid deprecated addresses
+            .then_with(|| prefer(!a.deprecated, !b.deprecated))
+            // Rule 6: prefer matching label
+            .then_with(|| prefer(self.same_label(&a.addr, d), self.same_label(&b.addr, d)))
+            // Rule 7: prefer temporary addresses
+            .then_with(|| prefer(a.temporary, b.temporary))
+            // Rule 8: use longest matching prefix
+            .then_with(|| {
+                let len_a = common_prefix_len(&a.addr, d, a.prefix_len);
+                let len_b = common_prefix_len(&b.addr, d, b.prefix_len);
+                len_b.cmp(&len_a)
+            })
+    }
+
+    /// Sorts destination addresses so the preferred one comes first,
+    /// following the rules of section 6 of RFC 6724. Addresses that compare
+    /// equal keep their order.
+    /// ```
+    /// use ipnetwork::selection::{PolicyTable, SourceAddress};
+    /// use std::net::IpAddr;
+    /// let sources = [
+    ///     SourceAddress::new("fe80::1".parse().unwrap(), 64),
+    ///     SourceAddress::new("198.51.100.117".parse().unwrap(), 24),
+    /// ];
+    /// let mut destinations: Vec<IpAddr> = vec![
+    ///     "2001:db8:1::1".parse().unwrap(),
+    ///     "198.51.100.121".parse().unwrap(),
+    /// ];
+    /// PolicyTable::default().sort_destinations(&mut destinations, &sources);
+    /// assert_eq!(destinations[0], "198.51.100.121".parse::<IpAddr>().unwrap());
+    /// ```
+    pub fn sort_destinations(&self, destinations: &mut [IpAddr], sources: &[SourceAddress]) {
+        let mut candidates: Vec<(IpAddr, Option<SourceAddress>)> = destinations
+            .iter()
+            .map(|d| (*d, self.select_source(d, sources).copied()))
+            .collect();
+        // The rules are not a total order, so sort by insertion instead of
+        // relying on the standard sorts
+        for i in 1..candidates.len() {
+            let mut j = i;
+            while j > 0
+                && self.compare_destinations(&candidates[j], &candidates[j - 1]) == Ordering::Less
+            {
+                candidates.swap(j, j - 1);
+                j -= 1;
+            }
+        }
+        for (slot, (destination, _)) in destinations.iter_mut().zip(candidates) {
+            *slot = destination;
+        }
+    }
+
+    /// Orders two destinations with their selected sources, the preferred
+    /// one is less
+    fn compare_destinations(
+        &self,
+        (da, sa): &(IpAddr, Option<SourceAddress>),
+        (db, sb): &(IpAddr, Option<SourceAddress>),
+    ) -> Ordering {
+        let (scope_da, scope_db) = (scope(da), scope(db));
+        // Rule 1: avoid unusable destinations
+        prefer(sa.is_some(), sb.is_some()).then_with(|| {
+            let (sa, sb) = match (sa, sb) {
+                (Some(sa), Some(sb)) => (sa, sb),
+                _ => return Ordering::Equal,
+            };
+            // Rule 2: prefer matching scope
+            prefer(scope_da == scope(&sa.addr), scope_db == scope(&sb.addr))
+                // Rule 3: avoid deprecated addresses
+                .then_with(|| prefer(!sa.deprecated, !sb.deprecated))
+                // Rule 5: prefer matching label
+                .then_with(|| prefer(self.same_label(&sa.addr, da), self.same_label(&sb.addr, db)))
+                // Rule 6: prefer higher precedence
+                .then_with(|| self.precedence(db).cmp(&self.precedence(da)))
+                // Rule 8: prefer smaller scope
+                .then_with(|| scope_da.cmp(&scope_db))
+                // Rule 9: use longest matching prefix
+                .then_with(|| match same_family(da, db) {
+                    true => {
+                        let len_a = common_prefix_len(&sa.addr, da, sa.prefix_len);
+                        let len_b = common_prefix_len(&sb.addr, db, sb.prefix_len);
+                        len_b.cmp(&len_a)
+                    }
+                    false => Ordering::Equal,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn source(s: &str) -> SourceAddress {
+        SourceAddress::new(addr(s), 64)
+    }
+
+    fn sorted(destinations: &[&str], sources: &[SourceAddress]) -> Vec<IpAddr> {
+        let mut destinations: Vec<IpAddr> = destinations.iter().map(|d| addr(d)).collect();
+        PolicyTable::default().sort_destinations(&mut destinations, sources);
+        destinations
+    }
+
+    #[test]
+    fn source_selection() {
+        let table = PolicyTable::default();
+        let select = |d: &str, sources: &[SourceAddress]| {
+            table.select_source(&addr(d), sources).map(|s| s.addr)
+        };
+        let sources = [source("2001:db8:3::1"), source("fe80::1")];
+        assert_eq!(
+            select("2001:db8:1::1", &sources),
+            Some(addr("2001:db8:3::1"))
+        );
+        assert_eq!(select("ff05::1", &sources), Some(addr("2001:db8:3::1")));
+        let sources = [source("2001:db8:1::1"), source("fe80::2")];
+        assert_eq!(select("fe80::1", &sources), Some(addr("fe80::2")));
+        let deprecated = SourceAddress {
+            deprecated: true,
+            ..source("2001:db8:1::1")
+        };
+        let sources = [source("2001:db8:2::1"), deprecated];
+        assert_eq!(
+            select("2001:db8:1::1", &sources),
+            Some(addr("2001:db8:1::1"))
+        );
+        assert_eq!(
+            select("2001:db8:1::2", &sources),
+            Some(addr("2001:db8:2::1"))
+        );
+        let sources = [source("2001:db8:3::2"), source("2001:db8:1::2")];
+        assert_eq!(
+            select("2001:db8:1::1", &sources),
+            Some(addr("2001:db8:1::2"))
+        );
+        let sources = [source("2001:db8:1::2"), source("2002:c633:6401::2")];
+        assert_eq!(
+            select("2002:c633:6401::1", &sources),
+            Some(addr("2002:c633:6401::2"))
+        );
+        let temporary = SourceAddress {
+            temporary: true,
+            ..source("2001:db8:1::d5e3:7953:13eb:22e8")
+        };
+        let sources = [source("2001:db8:1::2"), temporary];
+        assert_eq!(
+            select("2001:db8:1::d5e3:0:0:1", &sources),
+            Some(temporary.addr)
+        );
+        assert_eq!(select("10.0.0.1", &sources), None);
+    }
+
+    #[test]
+    fn destination_ordering() {
+        let sources = [
+            source("2001:db8:1::2"),
+            source("fe80::1"),
+            SourceAddress::new(addr("169.254.13.78"), 16),
+        ];
+        let order = sorted(&["198.51.100.121", "2001:db8:1::1"], &sources);
+        assert_eq!(order[0], addr("2001:db8:1::1"));
+
+        let sources = [
+            source("2001:db8:1::2"),
+            source("fe80::1"),
+            SourceAddress::new(addr("10.1.2.4"), 8),
+        ];
+        let order = sorted(&["10.1.2.3", "2001:db8:1::1"], &sources);
+        assert_eq!(order[0], addr("2001:db8:1::1"));
+
+        let sources = [source("2001:db8:1::2"), source("fe80::2")];
+        let order = sorted(&["2001:db8:1::1", "fe80::1"], &sources);
+        assert_eq!(order[0], addr("fe80::1"));
+
+        let sources = [source("2002:c633:6401::2"), source("fe80::2")];
+        let order = sorted(&["2001:db8:1::1", "2002:c633:6401::1"], &sources);
+        assert_eq!(order[0], addr("2002:c633:6401::1"));
+
+        let sources = [source("2001:db8:1::2"), source("2002:c633:6401::2")];
+        let order = sorted(
+            &["2002:c633:6401::1", "2001:db8:1::1", "10.0.0.1"],
+            &sources,
+        );
+        assert_eq!(order[0], addr("2001:db8:1::1"));
+        assert_eq!(order[2], addr("10.0.0.1"));
+    }
+
+    #[test]
+    fn custom_policy() {
+        let mut entries = PolicyTable::default().entries().to_vec();
+        // Prefer IPv4 over IPv6, as with "precedence ::ffff:0:0/96 100"
+        entries[2].precedence = 100;
+        let table = PolicyTable::new(entries);
+        let sources = [
+            source("2001:db8:1::2"),
+            SourceAddress::new(addr("10.1.2.4"), 8),
+        ];
+        let mut destinations = vec![addr("2001:db8:1::1"), addr("10.1.2.3")];
+        table.sort_destinations(&mut destinations, &sources);
+        assert_eq!(destinations[0], addr("10.1.2.3"));
+        assert_eq!(PolicyTable::new(vec![]).lookup(&addr("::1")), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! IPv6 rapid deployment (6rd, RFC 5969): the IPv6 prefix delegated to a
+//! customer edge is derived from its IPv4 address
+use crate::{Error, Ipv4Network, Ipv6Network};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Parameters of a 6rd domain. The customer edges of the domain share the
+/// high `ipv4_mask_len` bits of their IPv4 addresses, the other bits are
+/// appended to the 6rd prefix to form their delegated prefix.
+/// ```
+/// use ipnetwork::sixrd::SixRd;
+/// let domain = SixRd::new("2001:db8::/32".parse().unwrap(), 8, "10.0.0.1".parse().unwrap()).unwrap();
+/// let delegated = domain.delegated_prefix("10.100.200.1".parse().unwrap()).unwrap();
+/// assert_eq!(delegated.to_string(), "2001:db8:64c8:100::/56");
+/// let ce = domain.ipv4_address("2001:db8:64c8:100::1".parse().unwrap());
+/// assert_eq!(ce, Some("10.100.200.1".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SixRd {
+    prefix: Ipv6Network,
+    /// High bits shared by the IPv4 addresses of the domain, taken from
+    /// the border relay address
+    common: Ipv4Network,
+}
+
+impl SixRd {
+    /// Creates a domain from the 6rd prefix, the IPv4MaskLen and the IPv4
+    /// address of the border relay. Fails with `InvalidNetmask` when the
+    /// mask length is above 32 and with `InvalidNetwork` when the delegated
+    /// prefixes would be longer than 128 bits.
+    pub fn new(prefix: Ipv6Network, ipv4_mask_len: u8, br_addr: Ipv4Addr) -> Result<SixRd, Error> {
+        if ipv4_mask_len > 32 {
+            return Err(Error::InvalidNetmask);
+        }
+        if u32::from(prefix.cidr) + 32 - u32::from(ipv4_mask_len) > 128 {
+            return Err(Error::InvalidNetwork);
+        }
+        let common = Ipv4Network {
+            first: u32::from(br_addr) & !Ipv4Network::cidr_to_hostmask(ipv4_mask_len),
+            cidr: ipv4_mask_len,
+        };
+        Ok(SixRd { prefix, common })
+    }
+
+    pub fn prefix(&self) -> Ipv6Network {
+        self.prefix
+    }
+
+    pub fn ipv4_mask_len(&self) -> u8 {
+        self.common.cidr
+    }
+
+    /// IPv4 network holding the addresses of the customer edges
+    pub fn ipv4_network(&self) -> Ipv4Network {
+        self.common
+    }
+
+    /// Length of the prefixes delegated to the customer edges
+    pub fn delegated_len(&self) -> u8 {
+        self.prefix.cidr + (32 - self.common.cidr)
+    }
+
+    /// Returns the prefix delegated to the customer edge with the IPv4
+    /// address. Fails with `CidrMissMatch` when the address does not share
+    /// the common bits of the domain.
+    pub fn delegated_prefix(&self, ce_addr: Ipv4Addr) -> Result<Ipv6Network, Error> {
+        if !self.common.contains(&ce_addr) {
+            return Err(Error::CidrMissMatch);
+        }
+        let suffix =
+            u128::from(u32::from(ce_addr) & Ipv4Network::cidr_to_hostmask(self.common.cidr));
+        let cidr = self.delegated_len();
+        let offset = suffix.checked_shl(128 - u32::from(cidr)).unwrap_or(0);
+        Ok(Ipv6Network {
+            first: self.prefix.first | offset,
+            cidr,
+        })
+    }
+
+    /// Returns the IPv4 address of the customer edge whose delegated
+    /// prefix holds the address, None when it is outside the 6rd prefix
+    pub fn ipv4_address(&self, addr: Ipv6Addr) -> Option<Ipv4Addr> {
+        if !self.prefix.contains(&addr) {
+            return None;
+        }
+        let hostmask = Ipv4Network::cidr_to_hostmask(self.common.cidr);
+        let suffix = u128::from(addr)
+            .checked_shr(128 - u32::from(self.delegated_len()))
+            .unwrap_or(0) as u32;
+        Some(Ipv4Addr::from(self.common.first | (suffix & hostmask)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_and_extract() {
+        // the whole IPv4 address is embedded
+        let domain =
+            SixRd::new("2001:db8::/32".parse().unwrap(), 0, Ipv4Addr::UNSPECIFIED).unwrap();
+        assert_eq!(domain.delegated_len(), 64);
+        let delegated = domain
+            .delegated_prefix("192.0.2.130".parse().unwrap())
+            .unwrap();
+        assert_eq!(delegated.to_string(), "2001:db8:c000:282::/64");
+        assert_eq!(
+            domain.ipv4_address("2001:db8:c000:282:1::1".parse().unwrap()),
+            Some("192.0.2.130".parse().unwrap())
+        );
+        assert_eq!(domain.ipv4_address("2001:db9::".parse().unwrap()), None);
+
+        // every bit is shared, each domain serves one customer edge
+        let single = SixRd::new(
+            "2001:db8:1::/48".parse().unwrap(),
+            32,
+            "198.51.100.1".parse().unwrap(),
+        )
+        .unwrap();
+        let delegated = single
+            .delegated_prefix("198.51.100.1".parse().unwrap())
+            .unwrap();
+        assert_eq!(delegated.to_string(), "2001:db8:1::/48");
+        assert_eq!(
+            single.delegated_prefix("198.51.100.2".parse().unwrap()),
+            Err(Error::CidrMissMatch)
+        );
+
+        let wide = SixRd::new(
+            "2001:db8::/112".parse().unwrap(),
+            16,
+            "10.1.0.0".parse().unwrap(),
+        )
+        .unwrap();
+        let delegated = wide
+            .delegated_prefix("10.1.255.254".parse().unwrap())
+            .unwrap();
+        assert_eq!(delegated.to_string(), "2001:db8::fffe/128");
+        assert_eq!(
+            wide.ipv4_address(delegated.first()),
+            Some("10.1.255.254".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let prefix: Ipv6Network = "2001:db8::/32".parse().unwrap();
+        assert_eq!(
+            SixRd::new(prefix, 33, Ipv4Addr::UNSPECIFIED),
+            Err(Error::InvalidNetmask)
+        );
+        let long: Ipv6Network = "2001:db8::/100".parse().unwrap();
+        assert_eq!(
+            SixRd::new(long, 0, Ipv4Addr::UNSPECIFIED),
+            Err(Error::InvalidNetwork)
+        );
+    }
+}
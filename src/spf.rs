@@ -0,0 +1,199 @@
+//! Networks listed by the `ip4` and `ip6` mechanisms of SPF records
+use crate::{parse, Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+
+/// Result a mechanism gives for a matching sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qualifier {
+    /// `+`, also used when no qualifier is written
+    Pass,
+    /// `-`
+    Fail,
+    /// `~`
+    SoftFail,
+    /// `?`
+    Neutral,
+}
+
+/// An `ip4` or `ip6` mechanism of a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpMechanism {
+    pub qualifier: Qualifier,
+    pub network: IpNetwork,
+}
+
+/// Joins the character strings of a TXT record given in zone file
+/// form, `"v=spf1 ip4:192.0.2.0/24" " -all"`. Text without quotes is
+/// returned as is.
+fn unquote(record: &str) -> String {
+    match record.contains('"') {
+        true => record.split('"').skip(1).step_by(2).collect(),
+        false => record.to_string(),
+    }
+}
+
+/// Splits `address[/len]`, the length defaults to `max`
+fn address_and_len(value: &str, max: u8) -> Result<(&str, u8), Error> {
+    match value.split_once('/') {
+        Some((addr, len)) => {
+            let len = parse::prefix_len(len.as_bytes()).ok_or(Error::NetworkParseError)?;
+            match len <= max {
+                true => Ok((addr, len)),
+                false => Err(Error::InvalidNetwork),
+            }
+        }
+        None => Ok((value, max)),
+    }
+}
+
+/// Parses the value of a mechanism. Like SPF checks do, bits of the
+/// address past the prefix length are ignored.
+fn ip_network(value: &str, v6: bool) -> Result<IpNetwork, Error> {
+    match v6 {
+        false => {
+            let (addr, cidr) = address_and_len(value, 32)?;
+            let addr = parse::ipv4(addr.as_bytes()).ok_or(Error::NetworkParseError)?;
+            let first = addr & !Ipv4Network::cidr_to_hostmask(cidr);
+            Ok(IpNetwork::V4(Ipv4Network { first, cidr }))
+        }
+        true => {
+            let (addr, cidr) = address_and_len(value, 128)?;
+            let addr = Ipv6Addr::from_str(addr).map_err(|_| Error::NetworkParseError)?;
+            let first = u128::from(addr) & !Ipv6Network::cidr_to_hostmask(cidr);
+            Ok(IpNetwork::V6(Ipv6Network { first, cidr }))
+        }
+    }
+}
+
+/// Returns the `ip4` and `ip6` mechanisms of an SPF record, in record
+/// order. The record may be given as plain text or as the quoted strings
+/// of a TXT record. Mechanisms like `include` or `mx` need DNS lookups and
+/// are skipped. Fails with `NetworkParseError` when the text is not an SPF
+/// record or a mechanism cannot be read.
+/// ```
+/// use ipnetwork::spf::{parse_record, Qualifier};
+/// use ipnetwork::IpNetwork;
+/// let mechanisms = parse_record("v=spf1 ip4:192.0.2.0/24 ~ip6:2001:db8::/32 include:_spf.example.com -all").unwrap();
+/// assert_eq!(mechanisms.len(), 2);
+/// assert_eq!(mechanisms[0].network, IpNetwork::V4("192.0.2.0/24".parse().unwrap()));
+/// assert_eq!(mechanisms[1].qualifier, Qualifier::SoftFail);
+/// ```
+pub fn parse_record(record: &str) -> Result<Vec<IpMechanism>, Error> {
+    let record = unquote(record);
+    let mut terms = record.split_whitespace();
+    match terms.next() {
+        Some(version) if version.eq_ignore_ascii_case("v=spf1") => {}
+        _ => return Err(Error::NetworkParseError),
+    }
+    let mut mechanisms = Vec::new();
+    for term in terms {
+        let (qualifier, mechanism) = match term.as_bytes()[0] {
+            b'+' => (Qualifier::Pass, &term[1..]),
+            b'-' => (Qualifier::Fail, &term[1..]),
+            b'~' => (Qualifier::SoftFail, &term[1..]),
+            b'?' => (Qualifier::Neutral, &term[1..]),
+            _ => (Qualifier::Pass, term),
+        };
+        let (name, value) = match mechanism.split_once(':') {
+            Some(split) => split,
+            None => continue,
+        };
+        let network = match name {
+            _ if name.eq_ignore_ascii_case("ip4") => ip_network(value, false)?,
+            _ if name.eq_ignore_ascii_case("ip6") => ip_network(value, true)?,
+            _ => continue,
+        };
+        mechanisms.push(IpMechanism { qualifier, network });
+    }
+    Ok(mechanisms)
+}
+
+/// Returns the networks the records allow to send mail, the `Pass`
+/// mechanisms of all records, aggregated
+/// ```
+/// use ipnetwork::spf::authorized_networks;
+/// use ipnetwork::IpNetwork;
+/// let records = ["v=spf1 ip4:192.0.2.0/25 -all", "v=spf1 ip4:192.0.2.128/25 ip4:192.0.2.7 -all"];
+/// let networks = authorized_networks(&records).unwrap();
+/// assert_eq!(networks, vec![IpNetwork::V4("192.0.2.0/24".parse().unwrap())]);
+/// ```
+pub fn authorized_networks(records: &[&str]) -> Result<Vec<IpNetwork>, Error> {
+    let mut networks = Vec::new();
+    for record in records {
+        for mechanism in parse_record(record)? {
+            if mechanism.qualifier == Qualifier::Pass {
+                networks.push(mechanism.network);
+            }
+        }
+    }
+    Ok(IpNetwork::aggregate(&networks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(s: &str) -> IpNetwork {
+        match s.contains(':') {
+            true => IpNetwork::V6(s.parse().unwrap()),
+            false => IpNetwork::V4(s.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn mechanisms() {
+        let record = "\"v=spf1 IP4:192.0.2.1 +ip4:198.51.100.77/24 ip6:2001:db8::1\" \" -ip6:2001:db8::/32 a mx:mail.example.com redirect=_spf.example.com\"";
+        let mechanisms = parse_record(record).unwrap();
+        let expected = [
+            (Qualifier::Pass, "192.0.2.1/32"),
+            (Qualifier::Pass, "198.51.100.0/24"),
+            (Qualifier::Pass, "2001:db8::1/128"),
+            (Qualifier::Fail, "2001:db8::/32"),
+        ];
+        assert_eq!(mechanisms.len(), expected.len());
+        for (mechanism, (qualifier, net)) in mechanisms.iter().zip(expected.iter()) {
+            assert_eq!(mechanism.qualifier, *qualifier);
+            assert_eq!(mechanism.network, network(net));
+        }
+        assert_eq!(parse_record("v=spf1"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn invalid_records() {
+        assert_eq!(parse_record(""), Err(Error::NetworkParseError));
+        assert_eq!(parse_record("v=DKIM1 p=abc"), Err(Error::NetworkParseError));
+        assert_eq!(
+            parse_record("v=spf1 ip4:192.0.2"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            parse_record("v=spf1 ip4:192.0.2.0/"),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            parse_record("v=spf1 ip4:192.0.2.0/33"),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            parse_record("v=spf1 ip6:2001:db8::/129"),
+            Err(Error::InvalidNetwork)
+        );
+        assert_eq!(
+            authorized_networks(&["v=spf1 -all", "spf2.0/pra"]),
+            Err(Error::NetworkParseError)
+        );
+    }
+
+    #[test]
+    fn flatten_records() {
+        let records = [
+            "v=spf1 ip4:10.0.0.0/9 ?ip4:172.16.0.0/12 ip6:2001:db8::/48 -all",
+            "v=spf1 ip4:10.128.0.0/9 ip4:10.1.2.3 ~ip6:2001:db8:1::/48",
+        ];
+        assert_eq!(
+            authorized_networks(&records),
+            Ok(vec![network("10.0.0.0/8"), network("2001:db8::/48")])
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Terraform's `cidrsubnet()` and `cidrhost()` functions
+use crate::{Error, IpNetwork, Ipv4Network, Ipv6Network};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Parses a prefix the way Terraform does, host bits are cleared instead of
+/// rejected
+fn parse_prefix(prefix: &str) -> Result<(IpAddr, u8), Error> {
+    let mut parts = prefix.splitn(2, '/');
+    let addr: IpAddr = match parts.next().map(str::parse) {
+        Some(Ok(addr)) => addr,
+        _ => return Err(Error::NetworkParseError),
+    };
+    let bits = match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    match parts.next().map(str::parse::<u8>) {
+        Some(Ok(len)) if len <= bits => Ok((addr, len)),
+        _ => Err(Error::NetworkParseError),
+    }
+}
+
+/// Address as an integer together with the width of its family
+fn to_bits(addr: IpAddr) -> (u128, u8) {
+    match addr {
+        IpAddr::V4(addr) => (u128::from(u32::from(addr)), 32),
+        IpAddr::V6(addr) => (u128::from(addr), 128),
+    }
+}
+
+/// Mask covering the host part of a /`len` network of `bits` bits
+fn host_mask(bits: u8, len: u8) -> u128 {
+    let width_mask = u128::MAX >> (128 - u32::from(bits));
+    width_mask.checked_shr(u32::from(len)).unwrap_or(0)
+}
+
+/// Calculates a subnet address within the given prefix, like Terraform's
+/// `cidrsubnet(prefix, newbits, netnum)`.
+///
+/// The prefix may have host bits set, they are ignored. Fails with
+/// `NotEnoughAddresses` when the prefix cannot be extended by `newbits` or
+/// when `netnum` does not fit in `newbits` bits.
+/// ```
+/// use ipnetwork::terraform::cidrsubnet;
+/// use ipnetwork::IpNetwork;
+/// let subnet = cidrsubnet("172.16.0.0/12", 4, 2).unwrap();
+/// assert_eq!(subnet, IpNetwork::V4("172.18.0.0/16".parse().unwrap()));
+/// ```
+pub fn cidrsubnet(prefix: &str, newbits: u8, netnum: u128) -> Result<IpNetwork, Error> {
+    let (addr, len) = parse_prefix(prefix)?;
+    let (addr, bits) = to_bits(addr);
+    let new_len = match len.checked_add(newbits) {
+        Some(new_len) if new_len <= bits => new_len,
+        _ => return Err(Error::NotEnoughAddresses),
+    };
+    let max_netnum = u128::MAX.checked_shr(128 - u32::from(newbits)).unwrap_or(0);
+    if netnum > max_netnum {
+        return Err(Error::NotEnoughAddresses);
+    }
+    let network = addr & !host_mask(bits, len);
+    let shift = u32::from(bits - new_len);
+    let first = network | netnum.checked_shl(shift).unwrap_or(0);
+    Ok(match bits {
+        32 => IpNetwork::V4(Ipv4Network {
+            first: first as u32,
+            cidr: new_len,
+        }),
+        _ => IpNetwork::V6(Ipv6Network {
+            first,
+            cidr: new_len,
+        }),
+    })
+}
+
+/// Calculates a full host address within the given prefix, like
+/// Terraform's `cidrhost(prefix, hostnum)`.
+///
+/// A negative `hostnum` counts back from the end of the prefix, -1 is the
+/// last address. Fails with `NotEnoughAddresses` when the host number is
+/// outside of the prefix.
+/// ```
+/// use ipnetwork::terraform::cidrhost;
+/// use std::net::IpAddr;
+/// assert_eq!(cidrhost("10.12.112.0/20", 16).unwrap(), "10.12.112.16".parse::<IpAddr>().unwrap());
+/// assert_eq!(cidrhost("10.12.112.0/20", -1).unwrap(), "10.12.127.255".parse::<IpAddr>().unwrap());
+/// ```
+pub fn cidrhost(prefix: &str, hostnum: i128) -> Result<IpAddr, Error> {
+    let (addr, len) = parse_prefix(prefix)?;
+    let (addr, bits) = to_bits(addr);
+    let max_host = host_mask(bits, len);
+    let offset = match hostnum < 0 {
+        true => max_host.checked_sub(hostnum.unsigned_abs() - 1),
+        false => Some(hostnum as u128).filter(|&host| host <= max_host),
+    };
+    let host = (addr & !max_host) | offset.ok_or(Error::NotEnoughAddresses)?;
+    Ok(match bits {
+        32 => IpAddr::V4(Ipv4Addr::from(host as u32)),
+        _ => IpAddr::V6(Ipv6Addr::from(host)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> IpNetwork {
+        IpNetwork::V4(s.parse().unwrap())
+    }
+
+    #[test]
+    fn subnets() {
+        assert_eq!(cidrsubnet("10.1.2.0/24", 4, 15), Ok(v4("10.1.2.240/28")));
+        assert_eq!(cidrsubnet("10.1.2.77/24", 4, 15), Ok(v4("10.1.2.240/28")));
+        assert_eq!(cidrsubnet("10.0.0.0/8", 0, 0), Ok(v4("10.0.0.0/8")));
+        assert_eq!(
+            cidrsubnet("0.0.0.0/0", 32, u128::from(u32::MAX)),
+            Ok(v4("255.255.255.255/32"))
+        );
+        let v6 = cidrsubnet("fd00:fd12:3456:7890::/56", 16, 162).unwrap();
+        assert_eq!(
+            v6,
+            IpNetwork::V6(
+                Ipv6Network::new((0xfd00_fd12_3456_7800 << 64) | (0xa2 << 56), 72).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn subnet_errors() {
+        assert_eq!(
+            cidrsubnet("10.1.2.0/24", 4, 16),
+            Err(Error::NotEnoughAddresses)
+        );
+        assert_eq!(
+            cidrsubnet("10.1.2.0/24", 9, 0),
+            Err(Error::NotEnoughAddresses)
+        );
+        assert_eq!(
+            cidrsubnet("10.0.0.0/8", 0, 1),
+            Err(Error::NotEnoughAddresses)
+        );
+        assert_eq!(cidrsubnet("10.0.0.0", 8, 1), Err(Error::NetworkParseError));
+        assert_eq!(
+            cidrsubnet("10.0.0.0/33", 0, 0),
+            Err(Error::NetworkParseError)
+        );
+    }
+
+    #[test]
+    fn hosts() {
+        let addr = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(cidrhost("10.12.112.0/20", 268), Ok(addr("10.12.113.12")));
+        assert_eq!(cidrhost("10.12.112.0/20", -4096), Ok(addr("10.12.112.0")));
+        assert_eq!(
+            cidrhost("10.12.112.0/20", -4097),
+            Err(Error::NotEnoughAddresses)
+        );
+        assert_eq!(
+            cidrhost("10.12.112.0/20", 4096),
+            Err(Error::NotEnoughAddresses)
+        );
+        assert_eq!(
+            cidrhost("fd00:fd12:3456:7890::/56", 34),
+            Ok(addr("fd00:fd12:3456:7800::22"))
+        );
+        assert_eq!(
+            cidrhost("::/0", -1),
+            Ok(addr("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"))
+        );
+    }
+}
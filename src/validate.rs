@@ -0,0 +1,264 @@
+//! Validation of candidate networks and plans that reports every problem
+//! found instead of stopping at the first, for forms and reviews
+use crate::diagnostic::{diagnose, Diagnostic};
+use crate::{IpNetwork, Network};
+
+const fn v4(first: u32, cidr: u8) -> IpNetwork {
+    IpNetwork::V4(Network { first, cidr })
+}
+
+const fn v6(first: u128, cidr: u8) -> IpNetwork {
+    IpNetwork::V6(Network { first, cidr })
+}
+
+/// Special purpose blocks of the IANA registries (RFC 6890) that are not
+/// meant to be allocated from. The private ranges of RFC 1918 and the
+/// unique local addresses fc00::/7 are absent, they are the usual place
+/// to allocate from.
+const SPECIAL_PURPOSE: [(&str, IpNetwork); 20] = [
+    ("this network", v4(0x0000_0000, 8)),
+    ("shared address space", v4(0x6440_0000, 10)),
+    ("loopback", v4(0x7f00_0000, 8)),
+    ("link local", v4(0xa9fe_0000, 16)),
+    ("protocol assignments", v4(0xc000_0000, 24)),
+    ("documentation", v4(0xc000_0200, 24)),
+    ("benchmarking", v4(0xc612_0000, 15)),
+    ("documentation", v4(0xc633_6400, 24)),
+    ("documentation", v4(0xcb00_7100, 24)),
+    ("multicast", v4(0xe000_0000, 4)),
+    ("reserved", v4(0xf000_0000, 4)),
+    ("unspecified", v6(0, 128)),
+    ("loopback", v6(1, 128)),
+    ("IPv4-IPv6 translation", v6(0x0064_ff9b << 96, 96)),
+    ("IPv4-mapped", v6(0xffff << 32, 96)),
+    ("discard only", v6(0x0100 << 112, 64)),
+    ("protocol assignments", v6(0x2001 << 112, 23)),
+    ("documentation", v6(0x2001_0db8 << 96, 32)),
+    ("link local", v6(0xfe80 << 112, 10)),
+    ("multicast", v6(0xff00 << 112, 8)),
+];
+
+/// A problem of a candidate network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The text is not a valid network. An address with host bits set is
+    /// still checked for the other issues, as the suggested network.
+    Invalid(Diagnostic),
+    /// The network overlaps a special purpose block
+    Reserved {
+        network: IpNetwork,
+        block: IpNetwork,
+        purpose: &'static str,
+    },
+    /// The network overlaps a network someone else owns
+    Conflict {
+        network: IpNetwork,
+        owner: String,
+        other: IpNetwork,
+    },
+}
+
+/// Issues of one network against the special purpose blocks and the
+/// owned networks
+fn overlaps(network: IpNetwork, owned: &[(&str, IpNetwork)]) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for (purpose, block) in SPECIAL_PURPOSE.iter() {
+        if network.overlaps(block) {
+            issues.push(ValidationIssue::Reserved {
+                network,
+                block: *block,
+                purpose,
+            });
+        }
+    }
+    for (owner, other) in owned {
+        if network.overlaps(other) {
+            issues.push(ValidationIssue::Conflict {
+                network,
+                owner: owner.to_string(),
+                other: *other,
+            });
+        }
+    }
+    issues
+}
+
+/// Reads the network of the text, with the issue found in it
+fn candidate(input: &str) -> (Option<IpNetwork>, Option<ValidationIssue>) {
+    match diagnose(input.as_bytes()) {
+        Ok(network) => (Some(network), None),
+        Err(diagnostic @ Diagnostic::HostBitsSet { suggestion }) => {
+            (Some(suggestion), Some(ValidationIssue::Invalid(diagnostic)))
+        }
+        Err(diagnostic) => (None, Some(ValidationIssue::Invalid(diagnostic))),
+    }
+}
+
+/// Returns every issue of the network written in `input`: whether it
+/// parses, whether it is aligned, and which special purpose blocks and
+/// `allocations` it overlaps. An empty list means the network can be
+/// allocated.
+/// ```
+/// use ipnetwork::validate::{validate, ValidationIssue};
+/// use ipnetwork::IpNetwork;
+/// let allocations = [("db", IpNetwork::V4("198.51.0.0/24".parse().unwrap()))];
+/// let issues = validate("198.51.0.1/16", &allocations);
+/// // misaligned, overlaps a documentation block and the db network
+/// assert_eq!(issues.len(), 3);
+/// assert!(matches!(issues[2], ValidationIssue::Conflict { .. }));
+/// assert!(validate("10.0.0.0/16", &allocations).is_empty());
+/// ```
+pub fn validate(input: &str, allocations: &[(&str, IpNetwork)]) -> Vec<ValidationIssue> {
+    let (network, issue) = candidate(input);
+    let mut issues: Vec<ValidationIssue> = issue.into_iter().collect();
+    if let Some(network) = network {
+        issues.extend(overlaps(network, allocations));
+    }
+    issues
+}
+
+/// Returns the issues of every entry of a plan, as pairs of the entry
+/// name and the issue. Entries are checked like `validate`, and also
+/// against the entries before them in the plan.
+/// ```
+/// use ipnetwork::validate::{validate_plan, ValidationIssue};
+/// let plan = [("web", "10.0.0.0/24"), ("db", "10.0.0.128/25"), ("cache", "10.0.1.0/33")];
+/// let issues = validate_plan(&plan, &[]);
+/// assert_eq!(issues.len(), 2);
+/// assert_eq!(issues[0].0, "db");
+/// assert_eq!(issues[1].0, "cache");
+/// ```
+pub fn validate_plan(
+    plan: &[(&str, &str)],
+    allocations: &[(&str, IpNetwork)],
+) -> Vec<(String, ValidationIssue)> {
+    let mut owned = allocations.to_vec();
+    let mut issues = Vec::new();
+    for (name, input) in plan {
+        let (network, issue) = candidate(input);
+        issues.extend(issue.map(|issue| (name.to_string(), issue)));
+        if let Some(network) = network {
+            let found = overlaps(network, &owned);
+            issues.extend(found.into_iter().map(|issue| (name.to_string(), issue)));
+            owned.push((*name, network));
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        diagnose(s.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn every_issue_is_reported() {
+        let issues = validate("0.0.0.0/0", &[]);
+        let blocks: Vec<&str> = issues
+            .iter()
+            .map(|issue| match issue {
+                ValidationIssue::Reserved { purpose, .. } => *purpose,
+                _ => "",
+            })
+            .collect();
+        assert_eq!(blocks.len(), 11);
+        assert_eq!(blocks[0], "this network");
+        let table: Vec<IpNetwork> = SPECIAL_PURPOSE.iter().map(|(_, block)| *block).collect();
+        let written = [
+            "0.0.0.0/8",
+            "100.64.0.0/10",
+            "127.0.0.0/8",
+            "169.254.0.0/16",
+            "192.0.0.0/24",
+            "192.0.2.0/24",
+            "198.18.0.0/15",
+            "198.51.100.0/24",
+            "203.0.113.0/24",
+            "224.0.0.0/4",
+            "240.0.0.0/4",
+            "::/128",
+            "::1/128",
+            "64:ff9b::/96",
+            "::ffff:0:0/96",
+            "100::/64",
+            "2001::/23",
+            "2001:db8::/32",
+            "fe80::/10",
+            "ff00::/8",
+        ];
+        assert_eq!(
+            table,
+            written.iter().map(|block| net(block)).collect::<Vec<_>>()
+        );
+        for shared in [
+            "100.64.0.0/24",
+            "192.0.0.8/29",
+            "64:ff9b::/120",
+            "2001:1::/32",
+        ]
+        .iter()
+        {
+            assert_eq!(validate(shared, &[]).len(), 1, "{}", shared);
+        }
+        assert!(validate("172.16.0.0/12", &[]).is_empty());
+        assert!(validate("fd00::/8", &[]).is_empty());
+        assert_eq!(
+            validate("10.0.0.0/40", &[]),
+            vec![ValidationIssue::Invalid(Diagnostic::PrefixTooLong {
+                prefix: 40,
+                max: 32,
+                suggestion: net("10.0.0.0/32"),
+            })]
+        );
+        let issues = validate("fe80::1/16", &[("lan", net("fe80::/64"))]);
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::Invalid(Diagnostic::HostBitsSet {
+                    suggestion: net("fe80::/16")
+                }),
+                ValidationIssue::Reserved {
+                    network: net("fe80::/16"),
+                    block: net("fe80::/10"),
+                    purpose: "link local",
+                },
+                ValidationIssue::Conflict {
+                    network: net("fe80::/16"),
+                    owner: "lan".to_string(),
+                    other: net("fe80::/64"),
+                },
+            ]
+        );
+        assert!(validate("2001:db9::/32", &[]).is_empty());
+    }
+
+    #[test]
+    fn plans() {
+        let allocations = [("mgmt", net("10.1.0.0/24"))];
+        let plan = [
+            ("a", "10.0.0.0/23"),
+            ("b", "10.0.1.0/24"),
+            ("c", "10.1.0.0/16"),
+            ("d", "garbage"),
+            ("e", "10.2.0.0/24"),
+        ];
+        let issues = validate_plan(&plan, &allocations);
+        let summary: Vec<(&str, Option<&str>)> = issues
+            .iter()
+            .map(|(name, issue)| {
+                let owner = match issue {
+                    ValidationIssue::Conflict { owner, .. } => Some(owner.as_str()),
+                    _ => None,
+                };
+                (name.as_str(), owner)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("b", Some("a")), ("c", Some("mgmt")), ("d", None)]
+        );
+    }
+}
@@ -0,0 +1,842 @@
+//! Network device configuration for lists of networks: Cisco IOS prefix
+//! lists, ASA object groups, Junos prefix lists, FRR and BIRD static
+//! routes and BIRD prefix sets, and reading prefix lists back from
+//! existing configuration
+use crate::bits::AddressBits;
+use crate::filter::PrefixFilter;
+use crate::{Error, IpNetwork, Network};
+use std::fmt::Write;
+use std::net::IpAddr;
+
+/// Entry of a prefix list read from a configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixListEntry {
+    /// Sequence number of IOS entries
+    pub seq: Option<u32>,
+    pub permit: bool,
+    pub filter: PrefixFilter,
+}
+
+/// How the entries of a prefix list are evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Matching {
+    /// IOS and EOS prefix lists: the first matching entry decides
+    FirstMatch,
+    /// Junos route filters: the entries with the longest prefix containing
+    /// the route decide, the route does not match when their match types
+    /// fail
+    LongestMatch,
+}
+
+/// Named prefix list or Junos route filter read from a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixList {
+    pub name: String,
+    pub matching: Matching,
+    /// Entries in evaluation order
+    pub entries: Vec<PrefixListEntry>,
+}
+
+impl PrefixList {
+    /// Evaluates the list like a router, returns None when no entry
+    /// matches the network
+    pub fn decide(&self, network: &IpNetwork) -> Option<bool> {
+        let entry = match self.matching {
+            Matching::FirstMatch => self
+                .entries
+                .iter()
+                .find(|entry| entry.filter.matches(network)),
+            Matching::LongestMatch => {
+                let longest = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.filter.network().is_subnet(network))
+                    .map(|entry| prefix_len(&entry.filter.network()))
+                    .max()?;
+                self.entries.iter().find(|entry| {
+                    prefix_len(&entry.filter.network()) == longest && entry.filter.matches(network)
+                })
+            }
+        };
+        entry.map(|entry| entry.permit)
+    }
+
+    /// Evaluates the list like a router, networks matching no entry are
+    /// denied
+    pub fn permits(&self, network: &IpNetwork) -> bool {
+        self.decide(network).unwrap_or(false)
+    }
+}
+
+/// Evaluates the route filters of a Junos policy read by
+/// `parse_prefix_lists`: its terms are tried in order and the first term
+/// whose route filter matches decides. Returns None when the route falls
+/// through every term to the default policy.
+pub fn policy_permits(lists: &[PrefixList], policy: &str, network: &IpNetwork) -> Option<bool> {
+    lists
+        .iter()
+        .filter(|list| {
+            list.name == policy
+                || list
+                    .name
+                    .strip_prefix(policy)
+                    .is_some_and(|term| term.starts_with('/'))
+        })
+        .find_map(|list| list.decide(network))
+}
+
+fn prefix_len(network: &IpNetwork) -> u8 {
+    match network {
+        IpNetwork::V4(n) => n.cidr,
+        IpNetwork::V6(n) => n.cidr,
+    }
+}
+
+/// Prefix list entry matching the networks of `ge..=le` bits inside
+/// `network`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry<B: AddressBits> {
+    network: Network<B>,
+    ge: u8,
+    le: u8,
+}
+
+/// Sorts the networks and removes duplicates
+fn normalize<B: AddressBits>(networks: &mut Vec<Network<B>>) {
+    networks.sort_unstable();
+    networks.dedup();
+}
+
+/// Merges blocks whose sibling is present into their parent, until no
+/// block has its sibling left
+fn merge_siblings<B: AddressBits>(mut blocks: Vec<Network<B>>) -> Vec<Network<B>> {
+    loop {
+        normalize(&mut blocks);
+        let mut merged = Vec::with_capacity(blocks.len());
+        let mut index = 0;
+        while index < blocks.len() {
+            let block = blocks[index];
+            let step = Network::<B>::cidr_to_stepping(block.cidr);
+            let sibling = Network {
+                first: block.first | step,
+                cidr: block.cidr,
+            };
+            match blocks.get(index + 1) {
+                Some(next)
+                    if block.cidr > 0 && block.first & step == B::ZERO && *next == sibling =>
+                {
+                    merged.push(Network {
+                        first: block.first,
+                        cidr: block.cidr - 1,
+                    });
+                    index += 2;
+                }
+                _ => {
+                    merged.push(block);
+                    index += 1;
+                }
+            }
+        }
+        if merged.len() == blocks.len() {
+            return merged;
+        }
+        blocks = merged;
+    }
+}
+
+/// Turns the networks into prefix list entries matching exactly the same
+/// networks. Every network of a length that appears in all of a block is
+/// folded into one `ge`/`le` entry of the block.
+fn entries<B: AddressBits>(networks: &[Network<B>]) -> Vec<Entry<B>> {
+    let mut entries = Vec::new();
+    for len in 0..=B::BITS {
+        let same_length: Vec<Network<B>> =
+            networks.iter().filter(|n| n.cidr == len).copied().collect();
+        for network in merge_siblings(same_length) {
+            entries.push(Entry {
+                network,
+                ge: len,
+                le: len,
+            });
+        }
+    }
+    entries.sort_unstable();
+    let mut merged: Vec<Entry<B>> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match merged.last_mut() {
+            Some(last) if last.network == entry.network && last.le + 1 == entry.ge => {
+                last.le = entry.le
+            }
+            _ => merged.push(entry),
+        }
+    }
+    merged
+}
+
+fn split(networks: &[IpNetwork]) -> (Vec<Network<u32>>, Vec<Network<u128>>) {
+    let mut v4 = Vec::new();
+    let mut v6 = Vec::new();
+    for network in networks {
+        match network {
+            IpNetwork::V4(n) => v4.push(*n),
+            IpNetwork::V6(n) => v6.push(*n),
+        }
+    }
+    normalize(&mut v4);
+    normalize(&mut v6);
+    (v4, v6)
+}
+
+fn write_ios<B: AddressBits>(
+    out: &mut String,
+    command: &str,
+    name: &str,
+    networks: &[Network<B>],
+    wrap: fn(Network<B>) -> IpNetwork,
+) {
+    for (index, entry) in entries(networks).iter().enumerate() {
+        let filter = PrefixFilter::new(wrap(entry.network), Some(entry.ge), Some(entry.le))
+            .expect("entries are within their network");
+        let seq = (index + 1) * 5;
+        let _ = writeln!(
+            out,
+            "{} prefix-list {} seq {} permit {}",
+            command, name, seq, filter
+        );
+    }
+}
+
+/// Renders the networks as an IOS `ip prefix-list`, and an `ipv6
+/// prefix-list` of the same name for IPv6 networks. Complete sets of
+/// subnets are written as a single entry with `ge` and `le`. FRR reads the
+/// same syntax.
+/// ```
+/// use ipnetwork::vendor::ios_prefix_list;
+/// use ipnetwork::IpNetwork;
+/// let networks: Vec<IpNetwork> = ["10.0.0.0/25", "10.0.0.128/25", "192.0.2.1/32"]
+///     .iter()
+///     .map(|n| IpNetwork::V4(n.parse().unwrap()))
+///     .collect();
+/// assert_eq!(
+///     ios_prefix_list("CUSTOMERS", &networks),
+///     "ip prefix-list CUSTOMERS seq 5 permit 10.0.0.0/24 ge 25 le 25\n\
+///      ip prefix-list CUSTOMERS seq 10 permit 192.0.2.1/32\n"
+/// );
+/// ```
+pub fn ios_prefix_list(name: &str, networks: &[IpNetwork]) -> String {
+    let (v4, v6) = split(networks);
+    let mut out = String::new();
+    write_ios(&mut out, "ip", name, &v4, IpNetwork::V4);
+    write_ios(&mut out, "ipv6", name, &v6, IpNetwork::V6);
+    out
+}
+
+/// Renders the networks as an ASA network object group
+/// ```
+/// use ipnetwork::vendor::asa_object_group;
+/// use ipnetwork::IpNetwork;
+/// let networks = [
+///     IpNetwork::V4("10.0.0.0/8".parse().unwrap()),
+///     IpNetwork::V4("192.0.2.1/32".parse().unwrap()),
+/// ];
+/// assert_eq!(
+///     asa_object_group("INSIDE", &networks),
+///     "object-group network INSIDE\n \
+///      network-object 10.0.0.0 255.0.0.0\n \
+///      network-object host 192.0.2.1\n"
+/// );
+/// ```
+pub fn asa_object_group(name: &str, networks: &[IpNetwork]) -> String {
+    let (v4, v6) = split(networks);
+    let mut out = format!("object-group network {}\n", name);
+    for network in &v4 {
+        let _ = match network.cidr {
+            32 => writeln!(out, " network-object host {}", network.first()),
+            _ => writeln!(
+                out,
+                " network-object {} {}",
+                network.first(),
+                network.netmask()
+            ),
+        };
+    }
+    for network in &v6 {
+        let _ = match network.cidr {
+            128 => writeln!(out, " network-object host {}", network.first()),
+            _ => writeln!(out, " network-object {}", network),
+        };
+    }
+    out
+}
+
+/// Renders the networks as Junos `set` commands of a policy prefix list.
+/// Junos prefix lists only match exact prefixes, so every network gets
+/// its own line.
+/// ```
+/// use ipnetwork::vendor::junos_prefix_list;
+/// use ipnetwork::IpNetwork;
+/// let networks = [IpNetwork::V6("2001:db8::/32".parse().unwrap())];
+/// assert_eq!(
+///     junos_prefix_list("PEERS", &networks),
+///     "set policy-options prefix-list PEERS 2001:db8::/32\n"
+/// );
+/// ```
+pub fn junos_prefix_list(name: &str, networks: &[IpNetwork]) -> String {
+    let (v4, v6) = split(networks);
+    let mut out = String::new();
+    for network in &v4 {
+        let _ = writeln!(out, "set policy-options prefix-list {} {}", name, network);
+    }
+    for network in &v6 {
+        let _ = writeln!(out, "set policy-options prefix-list {} {}", name, network);
+    }
+    out
+}
+
+/// Attributes of the static route to a network, returned by the hook of
+/// `frr_static_routes` and `bird_static_routes`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RouteAttributes {
+    pub next_hop: Option<IpAddr>,
+    /// Interface, the route goes out of it without gateway when there is
+    /// no next hop. Routes with neither are blackholed.
+    pub dev: Option<String>,
+    /// Standard BGP communities as (AS, value)
+    pub communities: Vec<(u16, u16)>,
+}
+
+/// Renders FRR static routes to the networks, with the next hop and
+/// interface the hook returns for each. FRR static routes carry no
+/// communities, they are set by route maps, so the communities are left
+/// out.
+/// ```
+/// use ipnetwork::vendor::{frr_static_routes, RouteAttributes};
+/// use ipnetwork::IpNetwork;
+/// let networks = [IpNetwork::V4("10.0.0.0/8".parse().unwrap()), IpNetwork::V6("2001:db8::/32".parse().unwrap())];
+/// let config = frr_static_routes(&networks, |network| match network {
+///     IpNetwork::V4(_) => RouteAttributes { next_hop: Some("192.0.2.1".parse().unwrap()), ..Default::default() },
+///     IpNetwork::V6(_) => RouteAttributes::default(),
+/// });
+/// assert_eq!(config, "ip route 10.0.0.0/8 192.0.2.1\nipv6 route 2001:db8::/32 blackhole\n");
+/// ```
+pub fn frr_static_routes<F>(networks: &[IpNetwork], attributes: F) -> String
+where
+    F: Fn(&IpNetwork) -> RouteAttributes,
+{
+    let mut out = String::new();
+    for network in networks {
+        let route = attributes(network);
+        let command = match network {
+            IpNetwork::V4(_) => "ip",
+            IpNetwork::V6(_) => "ipv6",
+        };
+        let _ = write!(out, "{} route {}", command, network);
+        let _ = match (route.next_hop, &route.dev) {
+            (Some(next_hop), Some(dev)) => writeln!(out, " {} {}", next_hop, dev),
+            (Some(next_hop), None) => writeln!(out, " {}", next_hop),
+            (None, Some(dev)) => writeln!(out, " {}", dev),
+            (None, None) => writeln!(out, " blackhole"),
+        };
+    }
+    out
+}
+
+fn write_bird_static<B: AddressBits, F>(
+    out: &mut String,
+    name: &str,
+    channel: &str,
+    networks: &[Network<B>],
+    wrap: fn(Network<B>) -> IpNetwork,
+    attributes: &F,
+) where
+    F: Fn(&IpNetwork) -> RouteAttributes,
+{
+    if networks.is_empty() {
+        return;
+    }
+    let _ = writeln!(
+        out,
+        "protocol static {}_{} {{\n\t{};",
+        name, channel, channel
+    );
+    for network in networks.iter().map(|n| wrap(*n)) {
+        let route = attributes(&network);
+        let _ = write!(out, "\troute {}", network);
+        let _ = match (route.next_hop, &route.dev) {
+            (Some(next_hop), Some(dev)) => write!(out, " via {} dev \"{}\"", next_hop, dev),
+            (Some(next_hop), None) => write!(out, " via {}", next_hop),
+            (None, Some(dev)) => write!(out, " via \"{}\"", dev),
+            (None, None) => write!(out, " blackhole"),
+        };
+        match route.communities.is_empty() {
+            true => out.push_str(";\n"),
+            false => {
+                out.push_str(" {\n");
+                for (asn, value) in route.communities.iter() {
+                    let _ = writeln!(out, "\t\tbgp_community.add(({}, {}));", asn, value);
+                }
+                out.push_str("\t};\n");
+            }
+        }
+    }
+    out.push_str("}\n");
+}
+
+/// Renders BIRD 2 static protocols with routes to the networks, with the
+/// next hop, interface and communities the hook returns for each. BIRD
+/// needs a protocol per address family, they are named `{name}_ipv4` and
+/// `{name}_ipv6` and only written when there are networks of the family.
+/// ```
+/// use ipnetwork::vendor::{bird_static_routes, RouteAttributes};
+/// use ipnetwork::IpNetwork;
+/// let networks = [IpNetwork::V4("192.0.2.0/24".parse().unwrap())];
+/// let config = bird_static_routes("customers", &networks, |_| RouteAttributes {
+///     dev: Some("eth1".to_string()),
+///     communities: vec![(65000, 100)],
+///     ..Default::default()
+/// });
+/// assert_eq!(
+///     config,
+///     "protocol static customers_ipv4 {\n\
+///      \tipv4;\n\
+///      \troute 192.0.2.0/24 via \"eth1\" {\n\
+///      \t\tbgp_community.add((65000, 100));\n\
+///      \t};\n\
+///      }\n"
+/// );
+/// ```
+pub fn bird_static_routes<F>(name: &str, networks: &[IpNetwork], attributes: F) -> String
+where
+    F: Fn(&IpNetwork) -> RouteAttributes,
+{
+    let (v4, v6) = split(networks);
+    let mut out = String::new();
+    write_bird_static(&mut out, name, "ipv4", &v4, IpNetwork::V4, &attributes);
+    write_bird_static(&mut out, name, "ipv6", &v6, IpNetwork::V6, &attributes);
+    out
+}
+
+fn write_bird_set<B: AddressBits>(
+    out: &mut String,
+    name: &str,
+    networks: &[Network<B>],
+    wrap: fn(Network<B>) -> IpNetwork,
+) {
+    if networks.is_empty() {
+        return;
+    }
+    let patterns: Vec<String> = entries(networks)
+        .iter()
+        .map(|entry| match (entry.ge, entry.le) {
+            (ge, le) if ge == entry.network.cidr && le == ge => wrap(entry.network).to_string(),
+            (ge, le) => format!("{}{{{},{}}}", wrap(entry.network), ge, le),
+        })
+        .collect();
+    let _ = writeln!(out, "define {} = [ {} ];", name, patterns.join(", "));
+}
+
+/// Renders the networks as BIRD prefix set constants, `{name}_v4` and
+/// `{name}_v6` as BIRD sets hold one family. Complete sets of subnets are
+/// written as a single pattern with a length range.
+/// ```
+/// use ipnetwork::vendor::bird_prefix_sets;
+/// use ipnetwork::IpNetwork;
+/// let networks: Vec<IpNetwork> = ["10.0.0.0/25", "10.0.0.128/25", "2001:db8::/32"]
+///     .iter()
+///     .map(|n| IpNetwork::parse_ascii(n.as_bytes()).unwrap())
+///     .collect();
+/// assert_eq!(
+///     bird_prefix_sets("PEERS", &networks),
+///     "define PEERS_v4 = [ 10.0.0.0/24{25,25} ];\n\
+///      define PEERS_v6 = [ 2001:db8::/32 ];\n"
+/// );
+/// ```
+pub fn bird_prefix_sets(name: &str, networks: &[IpNetwork]) -> String {
+    let (v4, v6) = split(networks);
+    let mut out = String::new();
+    write_bird_set(&mut out, &format!("{}_v4", name), &v4, IpNetwork::V4);
+    write_bird_set(&mut out, &format!("{}_v6", name), &v6, IpNetwork::V6);
+    out
+}
+
+/// Parses `[seq N] permit|deny prefix [ge N] [le N]` of an IOS line
+fn parse_ios(words: &[&str]) -> Result<PrefixListEntry, Error> {
+    let (seq, words) = match words {
+        ["seq", seq, rest @ ..] => (
+            Some(seq.parse().map_err(|_| Error::NetworkParseError)?),
+            rest,
+        ),
+        _ => (None, words),
+    };
+    let (permit, words) = match words {
+        ["permit", rest @ ..] => (true, rest),
+        ["deny", rest @ ..] => (false, rest),
+        _ => return Err(Error::NetworkParseError),
+    };
+    Ok(PrefixListEntry {
+        seq,
+        permit,
+        filter: words.join(" ").parse()?,
+    })
+}
+
+/// Parses `prefix match-type [accept|reject]` of a Junos route filter,
+/// the action is None when the filter leaves it to its term
+fn parse_route_filter(words: &[&str]) -> Result<(PrefixFilter, Option<bool>), Error> {
+    let network = match words.first() {
+        Some(prefix) => prefix.parse::<PrefixFilter>()?.network(),
+        None => return Err(Error::NetworkParseError),
+    };
+    let own = PrefixFilter::exact(network).ge();
+    let len = |word: &str| word.trim_start_matches('/').parse::<u8>().ok();
+    let (filter, rest) = match &words[1..] {
+        ["exact", rest @ ..] => (PrefixFilter::new(network, None, None), rest),
+        ["orlonger", rest @ ..] => (PrefixFilter::new(network, Some(own), None), rest),
+        ["longer", rest @ ..] => (PrefixFilter::new(network, Some(own + 1), None), rest),
+        ["upto", le, rest @ ..] => (PrefixFilter::new(network, None, len(le)), rest),
+        ["prefix-length-range", range, rest @ ..] => {
+            let mut bounds = range.splitn(2, '-').map(len);
+            match (bounds.next().flatten(), bounds.next().flatten()) {
+                (Some(ge), Some(le)) => (PrefixFilter::new(network, Some(ge), Some(le)), rest),
+                _ => return Err(Error::NetworkParseError),
+            }
+        }
+        _ => return Err(Error::NetworkParseError),
+    };
+    let permit = match rest {
+        [] => None,
+        ["accept"] => Some(true),
+        ["reject"] => Some(false),
+        _ => return Err(Error::NetworkParseError),
+    };
+    Ok((filter?, permit))
+}
+
+fn add_entry(lists: &mut Vec<PrefixList>, name: &str, matching: Matching, entry: PrefixListEntry) {
+    match lists.iter_mut().find(|list| list.name == name) {
+        Some(list) => list.entries.push(entry),
+        None => lists.push(PrefixList {
+            name: name.to_string(),
+            matching,
+            entries: vec![entry],
+        }),
+    }
+}
+
+/// Reads the prefix lists of a configuration. Understands IOS `ip
+/// prefix-list` and `ipv6 prefix-list` lines, and Junos `set` lines of
+/// `policy-options prefix-list` and `route-filter` statements. Route
+/// filters are named `policy/term`, evaluated by longest match, and route
+/// filters without an action take the `then accept` or `then reject` of
+/// their term, see `policy_permits` for evaluating a whole policy. Other
+/// lines are ignored, a prefix list line that cannot be read fails with
+/// `NetworkParseError` or `InvalidNetwork`, as does a route filter without
+/// an action in a term that neither accepts nor rejects.
+/// ```
+/// use ipnetwork::vendor::parse_prefix_lists;
+/// use ipnetwork::IpNetwork;
+/// let config = "hostname edge1\n\
+///     ip prefix-list BOGONS seq 10 deny 10.0.0.0/8 le 32\n\
+///     ip prefix-list BOGONS seq 20 permit 0.0.0.0/0 le 24\n";
+/// let lists = parse_prefix_lists(config).unwrap();
+/// assert_eq!(lists[0].name, "BOGONS");
+/// assert!(!lists[0].permits(&IpNetwork::V4("10.1.0.0/16".parse().unwrap())));
+/// assert!(lists[0].permits(&IpNetwork::V4("192.0.2.0/24".parse().unwrap())));
+/// ```
+pub fn parse_prefix_lists(config: &str) -> Result<Vec<PrefixList>, Error> {
+    let mut lists = Vec::new();
+    // Route filters taking the action of their term, by list and index
+    let mut pending: Vec<(String, usize)> = Vec::new();
+    // The `then accept` or `then reject` of each term
+    let mut terms: Vec<(String, bool)> = Vec::new();
+    for line in config.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["ip", "prefix-list", _, "description", ..]
+            | ["ipv6", "prefix-list", _, "description", ..] => {}
+            ["ip", "prefix-list", name, rest @ ..] | ["ipv6", "prefix-list", name, rest @ ..] => {
+                add_entry(&mut lists, name, Matching::FirstMatch, parse_ios(rest)?)
+            }
+            ["set", "policy-options", "prefix-list", name, prefix] => {
+                let filter = PrefixFilter::exact(prefix.parse::<PrefixFilter>()?.network());
+                let entry = PrefixListEntry {
+                    seq: None,
+                    permit: true,
+                    filter,
+                };
+                add_entry(&mut lists, name, Matching::FirstMatch, entry)
+            }
+            ["set", "policy-options", "policy-statement", policy, rest @ ..] => {
+                let (term, rest) = match rest {
+                    ["term", term, rest @ ..] => (Some(*term), rest),
+                    _ => (None, rest),
+                };
+                let name = match term {
+                    Some(term) => format!("{}/{}", policy, term),
+                    None => policy.to_string(),
+                };
+                match rest {
+                    ["from", "route-filter", filter @ ..] => {
+                        let (filter, permit) = parse_route_filter(filter)?;
+                        let entry = PrefixListEntry {
+                            seq: None,
+                            permit: permit.unwrap_or(false),
+                            filter,
+                        };
+                        add_entry(&mut lists, &name, Matching::LongestMatch, entry);
+                        if permit.is_none() {
+                            let list = lists.iter().find(|list| list.name == name);
+                            let index = list.map_or(0, |list| list.entries.len() - 1);
+                            pending.push((name, index));
+                        }
+                    }
+                    ["then", "accept"] => terms.push((name, true)),
+                    ["then", "reject"] => terms.push((name, false)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    for (name, index) in pending {
+        let permit = terms
+            .iter()
+            .rev()
+            .find(|(term, _)| *term == name)
+            .map(|(_, permit)| *permit)
+            .ok_or(Error::NetworkParseError)?;
+        if let Some(list) = lists.iter_mut().find(|list| list.name == name) {
+            list.entries[index].permit = permit;
+        }
+    }
+    for list in &mut lists {
+        list.entries.sort_by_key(|entry| entry.seq);
+    }
+    Ok(lists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(networks: &[&str]) -> Vec<IpNetwork> {
+        networks
+            .iter()
+            .map(|n| IpNetwork::V4(n.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn ios_ranges() {
+        let mut networks = v4(&["10.0.0.0/24", "10.0.0.0/25", "10.0.0.128/25"]);
+        networks.extend(v4(&["10.0.0.0/26", "10.0.0.64/26"]));
+        networks.extend(v4(&["10.0.0.128/26", "10.0.0.192/26"]));
+        assert_eq!(
+            ios_prefix_list("A", &networks),
+            "ip prefix-list A seq 5 permit 10.0.0.0/24 le 26\n"
+        );
+        let hosts: Vec<IpNetwork> = "10.0.0.0/30"
+            .parse::<crate::Ipv4Network>()
+            .unwrap()
+            .into_subnets(32)
+            .map(IpNetwork::V4)
+            .collect();
+        assert_eq!(
+            ios_prefix_list("B", &hosts),
+            "ip prefix-list B seq 5 permit 10.0.0.0/30 ge 32\n"
+        );
+        let partial = v4(&["10.0.0.0/26", "10.0.0.64/26", "10.0.0.128/26"]);
+        assert_eq!(
+            ios_prefix_list("C", &partial),
+            "ip prefix-list C seq 5 permit 10.0.0.0/25 ge 26 le 26\n\
+             ip prefix-list C seq 10 permit 10.0.0.128/26\n"
+        );
+    }
+
+    #[test]
+    fn ios_mixed_families() {
+        let mut networks = v4(&["192.0.2.0/24", "192.0.2.0/24"]);
+        networks.push(IpNetwork::V6("2001:db8::/32".parse().unwrap()));
+        networks.push(IpNetwork::V6("::/0".parse().unwrap()));
+        assert_eq!(
+            ios_prefix_list("MIXED", &networks),
+            "ip prefix-list MIXED seq 5 permit 192.0.2.0/24\n\
+             ipv6 prefix-list MIXED seq 5 permit ::/0\n\
+             ipv6 prefix-list MIXED seq 10 permit 2001:db8::/32\n"
+        );
+    }
+
+    #[test]
+    fn read_ios() {
+        let config = "ip prefix-list A seq 20 permit 10.0.0.0/8 ge 24\n\
+                      ip prefix-list A description customers\n\
+                      ip prefix-list A seq 10 deny 10.0.0.0/16 le 32\n\
+                      ipv6 prefix-list B permit 2001:db8::/32 le 48\n\
+                      interface Loopback0\n";
+        let lists = parse_prefix_lists(config).unwrap();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].entries[0].seq, Some(10));
+        assert!(!lists[0].permits(&v4(&["10.0.1.0/24"])[0]));
+        assert!(lists[0].permits(&v4(&["10.1.1.0/24"])[0]));
+        assert!(!lists[0].permits(&v4(&["10.1.0.0/16"])[0]));
+        let v6 = IpNetwork::V6("2001:db8:1::/48".parse().unwrap());
+        assert!(lists[1].permits(&v6));
+
+        let rendered = ios_prefix_list("R", &v4(&["10.0.0.0/25", "10.0.0.128/25", "10.0.1.0/24"]));
+        let lists = parse_prefix_lists(&rendered).unwrap();
+        assert_eq!(lists[0].entries.len(), 2);
+        assert!(lists[0].permits(&v4(&["10.0.0.128/25"])[0]));
+        assert!(!lists[0].permits(&v4(&["10.0.0.0/24"])[0]));
+
+        let broken = [
+            "ip prefix-list A seq x permit 10.0.0.0/8",
+            "ip prefix-list A allow 10.0.0.0/8",
+        ];
+        for line in broken.iter() {
+            assert_eq!(parse_prefix_lists(line), Err(Error::NetworkParseError));
+        }
+        assert_eq!(
+            parse_prefix_lists("ip prefix-list A permit 10.0.0.0/8 ge 4"),
+            Err(Error::InvalidNetwork)
+        );
+    }
+
+    #[test]
+    fn read_junos() {
+        let config = "set policy-options prefix-list P 192.0.2.0/24\n\
+            set policy-options policy-statement IN term T from route-filter 10.0.0.0/8 orlonger\n\
+            set policy-options policy-statement IN term T from route-filter 10.0.0.0/8 upto /16 reject\n\
+            set policy-options policy-statement IN term T then accept\n\
+            set policy-options policy-statement IN term DROP from route-filter 192.0.2.0/24 orlonger\n\
+            set policy-options policy-statement IN term DROP from route-filter 192.0.2.0/24 exact accept\n\
+            set policy-options policy-statement IN term DROP then reject\n\
+            set policy-options policy-statement V6 from route-filter 2001:db8::/32 prefix-length-range /48-/64\n\
+            set policy-options policy-statement V6 from route-filter ::/0 longer reject\n\
+            set policy-options policy-statement V6 then accept\n";
+        let lists = parse_prefix_lists(config).unwrap();
+        let names: Vec<&str> = lists.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["P", "IN/T", "IN/DROP", "V6"]);
+        assert!(lists[0].permits(&v4(&["192.0.2.0/24"])[0]));
+        assert!(!lists[0].permits(&v4(&["192.0.2.0/25"])[0]));
+        assert_eq!(lists[1].entries[0].filter.le(), 32);
+        assert!(lists[1].permits(&v4(&["10.0.0.0/12"])[0]));
+        assert_eq!(lists[1].entries[1].filter.to_string(), "10.0.0.0/8 le 16");
+        assert!(!lists[2].permits(&v4(&["192.0.2.128/25"])[0]));
+        assert!(lists[2].entries[1].permit);
+        assert_eq!(lists[3].entries[1].filter.ge(), 1);
+        assert!(lists[3].permits(&IpNetwork::V6("2001:db8::/48".parse().unwrap())));
+        assert!(!lists[3].permits(&IpNetwork::V6("2001:db8::/40".parse().unwrap())));
+        assert_eq!(
+            parse_prefix_lists(
+                "set policy-options policy-statement X term T from route-filter 10.0.0.0/8 exact\n\
+                set policy-options policy-statement X term T then next term"
+            ),
+            Err(Error::NetworkParseError)
+        );
+        assert_eq!(
+            parse_prefix_lists("set policy-options policy-statement X from route-filter 10.0.0.0/8 through 10.0.0.0/16"),
+            Err(Error::NetworkParseError)
+        );
+    }
+
+    #[test]
+    fn junos_longest_match() {
+        let config = "set policy-options policy-statement IN term T from route-filter 10.0.0.0/8 orlonger reject\n\
+            set policy-options policy-statement IN term T from route-filter 10.1.0.0/16 orlonger accept\n\
+            set policy-options policy-statement IN term T from route-filter 172.16.0.0/12 orlonger accept\n\
+            set policy-options policy-statement IN term T from route-filter 172.16.0.0/16 exact reject\n\
+            set policy-options policy-statement IN term NEXT from route-filter 172.16.0.0/12 orlonger reject\n\
+            set policy-options policy-statement OUT from route-filter 10.0.0.0/8 exact accept\n";
+        let lists = parse_prefix_lists(config).unwrap();
+        assert_eq!(lists[0].matching, Matching::LongestMatch);
+        assert!(lists[0].permits(&v4(&["10.1.2.0/24"])[0]));
+        assert!(!lists[0].permits(&v4(&["10.2.0.0/16"])[0]));
+        assert_eq!(lists[0].decide(&v4(&["172.16.1.0/24"])[0]), None);
+        assert_eq!(lists[0].decide(&v4(&["172.17.0.0/16"])[0]), Some(true));
+        let route = v4(&["172.16.1.0/24"])[0];
+        assert_eq!(policy_permits(&lists, "IN", &route), Some(false));
+        assert_eq!(
+            policy_permits(&lists, "IN", &v4(&["192.0.2.0/24"])[0]),
+            None
+        );
+        assert_eq!(
+            policy_permits(&lists, "OUT", &v4(&["10.0.0.0/8"])[0]),
+            Some(true)
+        );
+        assert_eq!(policy_permits(&lists, "OU", &v4(&["10.0.0.0/8"])[0]), None);
+        let ios = parse_prefix_lists("ip prefix-list A permit 10.0.0.0/8 le 32").unwrap();
+        assert_eq!(ios[0].matching, Matching::FirstMatch);
+    }
+
+    #[test]
+    fn asa_and_junos() {
+        let mut networks = v4(&["10.0.0.0/25"]);
+        networks.push(IpNetwork::V6("2001:db8::1/128".parse().unwrap()));
+        networks.push(IpNetwork::V6("2001:db8::/64".parse().unwrap()));
+        assert_eq!(
+            asa_object_group("G", &networks),
+            "object-group network G\n \
+             network-object 10.0.0.0 255.255.255.128\n \
+             network-object 2001:db8::/64\n \
+             network-object host 2001:db8::1\n"
+        );
+        assert_eq!(
+            junos_prefix_list("P", &networks),
+            "set policy-options prefix-list P 10.0.0.0/25\n\
+             set policy-options prefix-list P 2001:db8::/64\n\
+             set policy-options prefix-list P 2001:db8::1/128\n"
+        );
+    }
+
+    #[test]
+    fn frr_and_bird() {
+        let mut networks = v4(&["10.0.0.0/8", "192.0.2.0/24"]);
+        networks.push(IpNetwork::V6("2001:db8::/32".parse().unwrap()));
+        let attributes = |network: &IpNetwork| match network {
+            IpNetwork::V4(n) if n.cidr == 8 => RouteAttributes {
+                next_hop: Some("192.0.2.1".parse().unwrap()),
+                dev: Some("eth0".to_string()),
+                communities: vec![(65000, 1), (65000, 2)],
+            },
+            IpNetwork::V4(_) => RouteAttributes::default(),
+            IpNetwork::V6(_) => RouteAttributes {
+                next_hop: Some("fe80::1".parse().unwrap()),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            frr_static_routes(&networks, attributes),
+            "ip route 10.0.0.0/8 192.0.2.1 eth0\n\
+             ip route 192.0.2.0/24 blackhole\n\
+             ipv6 route 2001:db8::/32 fe80::1\n"
+        );
+        assert_eq!(
+            bird_static_routes("edge", &networks, attributes),
+            "protocol static edge_ipv4 {\n\
+             \tipv4;\n\
+             \troute 10.0.0.0/8 via 192.0.2.1 dev \"eth0\" {\n\
+             \t\tbgp_community.add((65000, 1));\n\
+             \t\tbgp_community.add((65000, 2));\n\
+             \t};\n\
+             \troute 192.0.2.0/24 blackhole;\n\
+             }\n\
+             protocol static edge_ipv6 {\n\
+             \tipv6;\n\
+             \troute 2001:db8::/32 via fe80::1;\n\
+             }\n"
+        );
+        assert_eq!(bird_static_routes("edge", &[], attributes), "");
+        let mut sets = v4(&[
+            "10.0.0.0/24",
+            "10.0.0.0/25",
+            "10.0.0.128/25",
+            "192.0.2.1/32",
+        ]);
+        sets.extend(v4(&["10.0.0.0/24"]));
+        assert_eq!(
+            bird_prefix_sets("S", &sets),
+            "define S_v4 = [ 10.0.0.0/24{24,25}, 192.0.2.1/32 ];\n"
+        );
+        assert_eq!(bird_prefix_sets("S", &[]), "");
+    }
+}